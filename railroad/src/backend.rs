@@ -0,0 +1,167 @@
+//! Output backends. Each backend renders the grammar IR into a single output format.
+
+mod antlr;
+mod bundle;
+mod data_uri;
+mod dot;
+mod ebnf;
+#[cfg(feature = "eps")]
+mod eps;
+mod flowchart;
+mod geometry;
+mod html;
+mod json;
+mod markdown;
+mod mermaid;
+#[cfg(feature = "pdf")]
+mod pdf;
+#[cfg(feature = "png")]
+mod png;
+mod railroad_js;
+mod rustdoc;
+mod sprite;
+mod svg;
+mod text;
+mod tikz;
+mod typst;
+#[cfg(any(feature = "pdf", feature = "eps"))]
+mod vector;
+mod xml;
+
+use std::io;
+
+pub use self::bundle::BundleFile;
+use crate::{
+    config::Config,
+    ir::{Grammar, GrammarRule},
+    Error,
+};
+
+/// Renders a grammar into a particular output format
+pub trait Backend {
+    /// The file extension (without the leading dot) of files produced by this backend
+    fn extension(&self) -> &'static str;
+
+    /// Renders the grammar and writes the result to `out`
+    fn render(
+        &self,
+        grammar: &Grammar,
+        config: &Config,
+        out: &mut dyn io::Write,
+    ) -> Result<(), Error>;
+}
+
+/// The output formats with a built-in backend
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    /// A single SVG diagram of every rule
+    #[default]
+    Svg,
+    /// A sprite sheet: a single SVG with a `<symbol>` holding the diagram of each rule
+    Sprite,
+    /// A standalone HTML page embedding the SVG diagram
+    Html,
+    /// A PNG raster image of the SVG diagram
+    #[cfg(feature = "png")]
+    Png,
+    /// A small PNG overview of the entry rule's diagram, scaled to fit a fixed size
+    #[cfg(feature = "png")]
+    Thumbnail,
+    /// A vector PDF of the SVG diagram (on one page, or with a page for each rule)
+    #[cfg(feature = "pdf")]
+    Pdf,
+    /// An EPS (Encapsulated PostScript) figure of the SVG diagram
+    #[cfg(feature = "eps")]
+    Eps,
+    /// An HTML `<img>` tag of each rule's diagram, with the SVG inlined as a data URI
+    DataUri,
+    /// A Markdown image of each rule's diagram, with the SVG inlined as a data URI
+    DataUriMarkdown,
+    /// An HTML fragment of the SVG diagram for including in rustdoc documentation, styled to match its theme
+    Rustdoc,
+    /// A Markdown document with a heading, the doc comments and the diagram of each rule
+    Markdown,
+    /// A text diagram of each rule, drawn with box-drawing (or ASCII) characters
+    Text,
+    /// A Mermaid flowchart approximating the structure of each rule
+    Mermaid,
+    /// A Graphviz graph with a cluster for each rule holding the flowchart of its structure
+    Dot,
+    /// TikZ pictures of each rule's diagram, for LaTeX documents
+    Tikz,
+    /// JavaScript building the diagram of each rule with the railroad-diagrams library
+    RailroadJs,
+    /// A Typst document embedding the SVG diagram of each rule at its natural size
+    Typst,
+    /// The grammar as ISO 14977 EBNF text
+    Ebnf,
+    /// An approximate ANTLR4 grammar skeleton converted from the grammar
+    Antlr,
+    /// A zip archive of a static site: an HTML page showing the SVG diagram of each rule, its stylesheet and a JSON
+    /// description of each rule
+    Bundle,
+    /// The grammar IR as JSON, for use by other tooling
+    Json,
+    /// The grammar IR as XML, for XML tooling such as XSLT
+    Xml,
+    /// The laid-out diagram as JSON: the position of every node, label and connecting line, for custom renderers
+    JsonDiagram,
+}
+
+impl Format {
+    /// The backend that renders this format
+    pub fn backend(self) -> Box<dyn Backend> {
+        match self {
+            Format::Svg => Box::new(svg::SvgBackend),
+            Format::Sprite => Box::new(sprite::SpriteBackend),
+            Format::Html => Box::new(html::HtmlBackend),
+            #[cfg(feature = "png")]
+            Format::Png => Box::new(png::PngBackend),
+            #[cfg(feature = "png")]
+            Format::Thumbnail => Box::new(png::ThumbnailBackend),
+            #[cfg(feature = "pdf")]
+            Format::Pdf => Box::new(pdf::PdfBackend),
+            #[cfg(feature = "eps")]
+            Format::Eps => Box::new(eps::EpsBackend),
+            Format::DataUri => Box::new(data_uri::DataUriBackend { markdown: false }),
+            Format::DataUriMarkdown => Box::new(data_uri::DataUriBackend { markdown: true }),
+            Format::Rustdoc => Box::new(rustdoc::RustdocBackend),
+            Format::Markdown => Box::new(markdown::MarkdownBackend),
+            Format::Text => Box::new(text::TextBackend),
+            Format::Mermaid => Box::new(mermaid::MermaidBackend),
+            Format::Dot => Box::new(dot::DotBackend),
+            Format::Tikz => Box::new(tikz::TikzBackend),
+            Format::RailroadJs => Box::new(railroad_js::RailroadJsBackend),
+            Format::Typst => Box::new(typst::TypstBackend),
+            Format::Ebnf => Box::new(ebnf::EbnfBackend),
+            Format::Antlr => Box::new(antlr::AntlrBackend),
+            Format::Bundle => Box::new(bundle::BundleBackend),
+            Format::Json => Box::new(json::JsonBackend),
+            Format::Xml => Box::new(xml::XmlBackend),
+            Format::JsonDiagram => Box::new(geometry::GeometryBackend),
+        }
+    }
+}
+
+/// The files of the bundle of a grammar, for writing into a directory instead of a zip archive
+pub(crate) fn bundle_files(grammar: &Grammar, config: &Config) -> Result<Vec<BundleFile>, Error> {
+    bundle::files(grammar, config)
+}
+
+/// A grammar containing only the given rule, for backends drawing each rule separately
+pub(crate) fn single_rule(rule: &GrammarRule) -> Grammar {
+    Grammar {
+        docs: Vec::new(),
+        rules: vec![rule.clone()],
+    }
+}
+
+/// Renders the grammar in `src` with the rules selected by the config, for testing the backends
+#[cfg(test)]
+pub(crate) fn render_test(src: &str, config: &Config, format: Format) -> Vec<u8> {
+    // Panic safety: Tests only render valid grammars
+    let (grammar, _) = Grammar::parse(src).expect("valid grammar");
+    let mut out = Vec::new();
+    crate::render(&grammar, config, format, &mut out).expect("rendered grammar");
+    out
+}
@@ -0,0 +1,450 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+};
+
+use crate::{
+    backend::Backend,
+    config::Config,
+    deps::DependencyGraph,
+    diagram,
+    ir::{Expr, Grammar, GrammarRule, Modifier},
+    lint, Error,
+};
+
+/// How tightly an ANTLR construct binds, loosest first. A construct has to be grouped in parentheses when used where
+/// a tighter one is expected.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Prec {
+    /// Alternatives (`a | b`)
+    Choice,
+    /// Sequences (`a b`)
+    Sequence,
+    /// Rule references, literals, sets and suffixed or grouped constructs
+    Primary,
+}
+
+/// What a pest rule becomes in ANTLR
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Parser,
+    Lexer,
+    /// A lexer rule only used by other lexer rules
+    Fragment,
+}
+
+pub(crate) struct AntlrBackend;
+
+/// Converts expressions into ANTLR4 syntax, using the ANTLR names of the rules they reference
+struct Converter<'a> {
+    names: HashMap<&'a str, String>,
+}
+
+impl Converter<'_> {
+    /// Converts an expression, grouping it if it binds looser than `prec`
+    fn convert(&self, expr: &Expr, prec: Prec) -> String {
+        let (text, expr_prec) = self.antlr(expr);
+        if expr_prec < prec {
+            format!("({text})")
+        } else {
+            text
+        }
+    }
+
+    /// Converts an expression, along with how tightly the result binds
+    fn antlr(&self, expr: &Expr) -> (String, Prec) {
+        match expr {
+            Expr::Choice { choices } => (self.choices(choices).join(" | "), Prec::Choice),
+            Expr::Sequence { terms } => {
+                let terms = terms
+                    .iter()
+                    .map(|term| self.convert(term, Prec::Sequence))
+                    // Such as the start of input, which needs no matching
+                    .filter(|term| !term.is_empty())
+                    .collect::<Vec<_>>();
+                match terms.len() {
+                    0 | 1 => (terms.concat(), Prec::Primary),
+                    _ => (terms.join(" "), Prec::Sequence),
+                }
+            }
+            Expr::Ident { name, .. } => match self.names.get(name.as_str()) {
+                Some(name) => (name.clone(), Prec::Primary),
+                None if lint::is_builtin(name) => builtin(name),
+                // Undefined rules keep their names
+                None => (name.clone(), Prec::Primary),
+            },
+            Expr::Str { value, .. } => (format!("'{}'", literal(value)), Prec::Primary),
+            Expr::InsensitiveStr { value, .. } => insensitive(value),
+            Expr::Range { start, end, .. } => (
+                format!("'{}'..'{}'", literal(start), literal(end)),
+                Prec::Primary,
+            ),
+            Expr::Optional { expr } => (
+                format!("{}?", self.convert(expr, Prec::Primary)),
+                Prec::Primary,
+            ),
+            Expr::Repeat { expr } => (
+                format!("{}*", self.convert(expr, Prec::Primary)),
+                Prec::Primary,
+            ),
+            Expr::RepeatOnce { expr } => (
+                format!("{}+", self.convert(expr, Prec::Primary)),
+                Prec::Primary,
+            ),
+            Expr::RepeatRange { expr, min, max } => self.repeat(expr, *min, *max),
+            // ANTLR4 has no syntactic predicates, so lookahead is only marked for whoever finishes the port
+            Expr::PositivePredicate { expr } => (
+                comment(&format!("PEG: &{}", self.convert(expr, Prec::Primary))),
+                Prec::Primary,
+            ),
+            Expr::NegativePredicate { expr } => (
+                comment(&format!("PEG: !{}", self.convert(expr, Prec::Primary))),
+                Prec::Primary,
+            ),
+            // Nor has it a stack, so the expression is matched as is
+            Expr::Push { expr } => (
+                format!(
+                    "{} {}",
+                    self.convert(expr, Prec::Primary),
+                    comment("PEG: PUSH")
+                ),
+                Prec::Sequence,
+            ),
+            Expr::PeekSlice { start, end, .. } => (
+                comment(&format!("PEG: {}", diagram::peek_slice(*start, *end))),
+                Prec::Primary,
+            ),
+        }
+    }
+
+    /// Converts the alternatives of a choice
+    fn choices(&self, choices: &[Expr]) -> Vec<String> {
+        choices
+            .iter()
+            .map(|choice| self.convert(choice, Prec::Sequence))
+            .collect()
+    }
+
+    /// Converts a repetition of at least `min` and at most `max` (or unbounded) times, which ANTLR has no notation for,
+    /// into the required repetitions followed by the optional ones
+    fn repeat(&self, expr: &Expr, min: u32, max: Option<u32>) -> (String, Prec) {
+        let expr = self.convert(expr, Prec::Primary);
+        let mut terms = vec![expr.clone(); min as usize];
+        match max {
+            None => terms.push(format!("{expr}*")),
+            Some(max) => terms.extend((min..max).map(|_| format!("{expr}?"))),
+        }
+        match terms.len() {
+            0 | 1 => (terms.concat(), Prec::Primary),
+            _ => (terms.join(" "), Prec::Sequence),
+        }
+    }
+}
+
+/// The ANTLR equivalent of a pest built-in rule. The ASCII classes become sets, and other built-ins are assumed to be
+/// Unicode properties.
+fn builtin(name: &str) -> (String, Prec) {
+    let set = match name {
+        "ANY" => ".",
+        "EOI" => "EOF",
+        // Matching always starts at the start of input
+        "SOI" => "",
+        "NEWLINE" => return ("'\\n' | '\\r\\n' | '\\r'".into(), Prec::Choice),
+        "ASCII" => "[\\u0000-\\u007F]",
+        "ASCII_DIGIT" => "[0-9]",
+        "ASCII_NONZERO_DIGIT" => "[1-9]",
+        "ASCII_BIN_DIGIT" => "[01]",
+        "ASCII_OCT_DIGIT" => "[0-7]",
+        "ASCII_HEX_DIGIT" => "[0-9a-fA-F]",
+        "ASCII_ALPHA_LOWER" => "[a-z]",
+        "ASCII_ALPHA_UPPER" => "[A-Z]",
+        "ASCII_ALPHA" => "[a-zA-Z]",
+        "ASCII_ALPHANUMERIC" => "[a-zA-Z0-9]",
+        _ => {
+            // Pest names properties in upper case (`UPPERCASE_LETTER`), and ANTLR in title case (`Uppercase_Letter`)
+            let property = name
+                .split('_')
+                .map(|word| {
+                    let mut chars = word.chars();
+                    chars
+                        .next()
+                        .into_iter()
+                        .chain(chars.flat_map(char::to_lowercase))
+                        .collect::<String>()
+                })
+                .collect::<Vec<_>>()
+                .join("_");
+            return (format!("[\\p{{{property}}}]"), Prec::Primary);
+        }
+    };
+    (set.into(), Prec::Primary)
+}
+
+/// Converts the contents of a pest string (or character) literal, with its escapes as written, into the contents of
+/// an ANTLR literal
+fn literal(value: &str) -> String {
+    let mut text = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                // ANTLR literals are single quoted
+                Some('"') => text.push('"'),
+                Some('0') => text.push_str("\\u0000"),
+                Some('x') => {
+                    let hex = chars.by_ref().take(2).collect::<String>();
+                    text.push_str(&format!("\\u00{hex}"));
+                }
+                // `\\`, `\'`, `\n`, `\r`, `\t` and `\u{...}` are the same in ANTLR
+                Some(c) => {
+                    text.push('\\');
+                    text.push(c);
+                }
+                None => text.push_str("\\\\"),
+            },
+            '\'' => text.push_str("\\'"),
+            c => text.push(c),
+        }
+    }
+    text
+}
+
+/// Converts a case insensitive string into a sequence of sets matching either case of each letter (ANTLR only has
+/// case insensitivity for whole rules)
+fn insensitive(value: &str) -> (String, Prec) {
+    let mut terms = Vec::new();
+    let mut text = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        // Escapes aren't letters, so they are left in the literals between the sets
+        if c == '\\' {
+            text.push(c);
+            match chars.next() {
+                Some('u') => {
+                    text.push('u');
+                    text.extend(chars.by_ref().take_while(|&c| c != '}').chain(['}']));
+                }
+                Some('x') => {
+                    text.push('x');
+                    text.extend(chars.by_ref().take(2));
+                }
+                Some(c) => text.push(c),
+                None => {}
+            }
+            continue;
+        }
+        let (lower, upper) = (
+            c.to_lowercase().collect::<String>(),
+            c.to_uppercase().collect::<String>(),
+        );
+        if lower == upper {
+            text.push(c);
+            continue;
+        }
+        if !text.is_empty() {
+            terms.push(format!("'{}'", literal(&text)));
+            text.clear();
+        }
+        terms.push(format!("[{lower}{upper}]"));
+    }
+    if !text.is_empty() {
+        terms.push(format!("'{}'", literal(&text)));
+    }
+
+    match terms.len() {
+        0 => ("''".into(), Prec::Primary),
+        1 => (terms.remove(0), Prec::Primary),
+        _ => (terms.join(" "), Prec::Sequence),
+    }
+}
+
+/// An inline comment
+fn comment(text: &str) -> String {
+    // A comment can't contain its own end
+    format!("/* {} */", text.replace("*/", "* /"))
+}
+
+/// Decides whether each rule becomes a parser or lexer rule. Atomic rules (and the implicit whitespace and comment
+/// rules) match tokens, so they and the rules they use become lexer rules - as fragments if only lexer rules use them.
+fn kinds(grammar: &Grammar) -> HashMap<&str, Kind> {
+    let graph = DependencyGraph::new(grammar);
+    let is_token = |rule: &GrammarRule| {
+        matches!(rule.modifier, Modifier::Atomic | Modifier::CompoundAtomic)
+            || matches!(rule.name.as_str(), "WHITESPACE" | "COMMENT")
+    };
+    let lexer = grammar
+        .rules
+        .iter()
+        .filter(|rule| is_token(rule))
+        .flat_map(|rule| graph.reachable(&rule.name))
+        .collect::<HashSet<_>>();
+    let used_by_parser = grammar
+        .rules
+        .iter()
+        .filter(|rule| !lexer.contains(&rule.name))
+        .flat_map(|rule| graph.references(&rule.name))
+        .collect::<HashSet<_>>();
+
+    grammar
+        .rules
+        .iter()
+        .map(|rule| {
+            let kind = if !lexer.contains(&rule.name) {
+                Kind::Parser
+            } else if is_token(rule) || used_by_parser.contains(&rule.name) {
+                Kind::Lexer
+            } else {
+                Kind::Fragment
+            };
+            (rule.name.as_str(), kind)
+        })
+        .collect()
+}
+
+/// The ANTLR name of a rule: lexer rules have to start with an upper case letter, and parser rules with a lower case
+/// one
+fn antlr_name(name: &str, kind: Kind) -> String {
+    match kind {
+        Kind::Lexer | Kind::Fragment => name.to_uppercase(),
+        Kind::Parser if name.chars().all(|c| !c.is_lowercase()) => name.to_lowercase(),
+        Kind::Parser => name[..1].to_lowercase() + &name[1..],
+    }
+}
+
+/// The name of the grammar, which has to be an identifier (and match the `.g4` file name)
+fn grammar_name(config: &Config) -> String {
+    let name = config
+        .name
+        .as_deref()
+        .unwrap_or("Grammar")
+        .replace(|c: char| !(c.is_ascii_alphanumeric() || c == '_'), "_");
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{name}")
+    } else {
+        name
+    }
+}
+
+impl Backend for AntlrBackend {
+    fn extension(&self) -> &'static str {
+        "g4"
+    }
+
+    fn render(
+        &self,
+        grammar: &Grammar,
+        config: &Config,
+        out: &mut dyn io::Write,
+    ) -> Result<(), Error> {
+        let kinds = kinds(grammar);
+        let converter = Converter {
+            names: kinds
+                .iter()
+                .map(|(&name, &kind)| (name, antlr_name(name, kind)))
+                .collect(),
+        };
+
+        for doc in &grammar.docs {
+            writeln!(out, "// {}", doc.trim())?;
+        }
+        writeln!(out, "// An approximate skeleton converted from a pest grammar. Pest tries the alternatives of a")?;
+        writeln!(
+            out,
+            "// choice in order, and lookahead (marked PEG) has no ANTLR equivalent."
+        )?;
+        writeln!(out)?;
+        writeln!(out, "grammar {};", grammar_name(config))?;
+
+        for rule in &grammar.rules {
+            let kind = kinds[rule.name.as_str()];
+            writeln!(out)?;
+            for doc in &rule.docs {
+                writeln!(out, "// {}", doc.trim())?;
+            }
+            if rule.modifier == Modifier::Silent {
+                writeln!(
+                    out,
+                    "// Silent in pest (it has no node of its own in the parse tree)"
+                )?;
+            }
+
+            let name = &converter.names[rule.name.as_str()];
+            match kind {
+                Kind::Fragment => writeln!(out, "fragment {name}")?,
+                Kind::Parser | Kind::Lexer => writeln!(out, "{name}")?,
+            }
+            // Pest skips these implicitly between the terms of non-atomic rules
+            let command = match rule.name.as_str() {
+                "WHITESPACE" | "COMMENT" if rule.modifier == Modifier::Silent => Some("skip"),
+                "WHITESPACE" | "COMMENT" => Some("channel(HIDDEN)"),
+                _ => None,
+            };
+            let choices = match &rule.expr {
+                Expr::Choice { choices } if command.is_none() => converter.choices(choices),
+                // A lexer command only applies to its own alternative, so the alternatives are grouped
+                expr => vec![converter.convert(expr, Prec::Sequence)],
+            };
+            for (idx, choice) in choices.iter().enumerate() {
+                let sep = if idx == 0 { ':' } else { '|' };
+                write!(out, "    {sep}")?;
+                if !choice.is_empty() {
+                    write!(out, " {choice}")?;
+                }
+                if let Some(command) = command {
+                    write!(out, " -> {command}")?;
+                }
+                writeln!(out)?;
+            }
+            writeln!(out, "    ;")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{render_test, Format};
+
+    fn render(src: &str) -> String {
+        String::from_utf8(render_test(src, &Config::default(), Format::Antlr)).unwrap()
+    }
+
+    #[test]
+    fn golden() {
+        let src = r#"
+            //! Strings
+            string = @{ "\"" ~ (!("\"" | "\\") ~ ANY | "\\" ~ ("\"" | "n" | hex))* ~ "\"" }
+            hex = { ASCII_HEX_DIGIT{2} }
+            value = { string | ^"it's" | "*/" }
+        "#;
+        assert_eq!(
+            render(src),
+            r#"// Strings
+// An approximate skeleton converted from a pest grammar. Pest tries the alternatives of a
+// choice in order, and lookahead (marked PEG) has no ANTLR equivalent.
+
+grammar Grammar;
+
+STRING
+    : '"' (/* PEG: !('"' | '\\') */ . | '\\' ('"' | 'n' | HEX))* '"'
+    ;
+
+fragment HEX
+    : [0-9a-fA-F] [0-9a-fA-F]
+    ;
+
+value
+    : STRING
+    | [iI] [tT] '\'' [sS]
+    | '*/'
+    ;
+"#
+        );
+    }
+
+    #[test]
+    fn comments_are_closed_once() {
+        assert!(render(r#"x = { !"*/" ~ "a" }"#).contains("    : /* PEG: !'* /' */ 'a'\n"));
+    }
+}
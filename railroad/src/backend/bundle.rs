@@ -0,0 +1,355 @@
+use std::io;
+
+use railroad::{svg, Node};
+use serde::Serialize;
+
+use crate::{
+    backend::{single_rule, Backend},
+    config::Config,
+    diagram,
+    ir::{Grammar, Modifier},
+    Error, Warning,
+};
+
+/// The stylesheet of the index page (the diagrams carry their own)
+const PAGE_CSS: &str = "body {
+  margin: 0 auto;
+  max-width: 60em;
+  padding: 1em;
+  font-family: sans-serif;
+  line-height: 1.5;
+}
+nav ul {
+  columns: 12em;
+}
+section {
+  margin-top: 2em;
+}
+section img {
+  max-width: 100%;
+  height: auto;
+}
+.warnings {
+  color: #a00;
+}
+";
+
+/// The date of every file in the archive (1980-01-01, the earliest a zip file can hold), so that the same grammar
+/// always gives the same archive
+const DOS_DATE: u16 = (1 << 5) | 1;
+
+/// A file of a diagram bundle
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BundleFile {
+    /// The path of the file within the bundle, with `/` separators
+    pub path: String,
+    pub contents: Vec<u8>,
+}
+
+/// The contents of `metadata.json`
+#[derive(Serialize)]
+struct Metadata<'a> {
+    title: Option<&'a str>,
+    rules: Vec<RuleMetadata<'a>>,
+    warnings: &'a [Warning],
+}
+
+/// The details of a rule in `metadata.json`
+#[derive(Serialize)]
+struct RuleMetadata<'a> {
+    name: &'a str,
+    modifier: Modifier,
+    line: Option<usize>,
+    docs: &'a [String],
+    /// The path of the rule's diagram within the bundle
+    file: String,
+    width: i64,
+    height: i64,
+}
+
+/// The files of a static site of diagrams: an `index.html` page showing the diagram of each rule (in `rules/`) under a
+/// heading with its doc comments, its stylesheet and a `metadata.json` describing each rule
+pub(crate) fn files(grammar: &Grammar, config: &Config) -> Result<Vec<BundleFile>, Error> {
+    let rule_config = Config {
+        // The headings and warnings are on the page instead
+        title: None,
+        warnings: Vec::new(),
+        legend: false,
+        ..config.clone()
+    };
+    let mut rules = grammar.rules.iter().collect::<Vec<_>>();
+    // Pest doesn't allow duplicate rules, but if there are any they share a file
+    rules.dedup_by(|a, b| a.name == b.name);
+
+    let title = config.title(grammar);
+    let mut files = Vec::with_capacity(rules.len() + 3);
+    let mut metadata = Metadata {
+        title,
+        rules: Vec::with_capacity(rules.len()),
+        warnings: &config.warnings,
+    };
+    for rule in &rules {
+        let diagram = diagram::make_diagram(
+            &single_rule(rule),
+            &rule_config,
+            config.theme.stylesheet(),
+            config.theme.dark_stylesheet(),
+        )?;
+        let file = format!("rules/{}.svg", rule.name);
+        metadata.rules.push(RuleMetadata {
+            name: &rule.name,
+            modifier: rule.modifier,
+            line: rule.line,
+            docs: &rule.docs,
+            file: file.clone(),
+            width: diagram.width(),
+            height: diagram.height(),
+        });
+        files.push(BundleFile {
+            path: file,
+            contents: diagram::to_svg(&diagram, &rule_config).into_bytes(),
+        });
+    }
+
+    let mut index = String::new();
+    let title = svg::encode_minimal(title.unwrap_or("Railroad diagrams"));
+    index.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    index.push_str(&format!("<title>{title}</title>\n"));
+    index.push_str("<link rel=\"stylesheet\" href=\"style.css\">\n");
+    index.push_str("</head>\n<body>\n");
+    index.push_str(&format!("<h1>{title}</h1>\n"));
+    index.push_str("<nav>\n<ul>\n");
+    for rule in &rules {
+        // Rule names are identifiers, so they never need escaping
+        index.push_str(&format!(
+            "<li><a href=\"#{}\">{}</a></li>\n",
+            config.id(&rule.name),
+            config.label(rule)
+        ));
+    }
+    index.push_str("</ul>\n</nav>\n");
+    for (rule, meta) in rules.iter().zip(&metadata.rules) {
+        let id = config.id(&rule.name);
+        index.push_str(&format!("<section id=\"{id}\">\n"));
+        index.push_str(&format!(
+            "<h2><a href=\"#{id}\">{}</a></h2>\n",
+            config.label(rule)
+        ));
+        for doc in &rule.docs {
+            index.push_str(&format!("<p>{}</p>\n", svg::encode_minimal(doc)));
+        }
+        index.push_str(&format!(
+            "<img src=\"{}\" width=\"{}\" height=\"{}\" alt=\"Railroad diagram of {}\">\n",
+            meta.file, meta.width, meta.height, rule.name
+        ));
+        index.push_str("</section>\n");
+    }
+    if !config.warnings.is_empty() {
+        index.push_str("<h2>Warnings</h2>\n<ul class=\"warnings\">\n");
+        for warning in &config.warnings {
+            index.push_str(&format!(
+                "<li>{}</li>\n",
+                svg::encode_minimal(&warning.to_string())
+            ));
+        }
+        index.push_str("</ul>\n");
+    }
+    index.push_str("</body>\n</html>\n");
+
+    let metadata = serde_json::to_vec_pretty(&metadata).map_err(io::Error::from)?;
+    // The page comes first, then its stylesheet and the metadata, then the diagrams in rule order
+    files.splice(
+        0..0,
+        [
+            BundleFile {
+                path: "index.html".to_string(),
+                contents: index.into_bytes(),
+            },
+            BundleFile {
+                path: "style.css".to_string(),
+                contents: PAGE_CSS.as_bytes().to_vec(),
+            },
+            BundleFile {
+                path: "metadata.json".to_string(),
+                contents: metadata,
+            },
+        ],
+    );
+    Ok(files)
+}
+
+/// Writes files into a zip archive, compressed with deflate (without ZIP64 support, which diagrams never need)
+fn write_zip(files: &[BundleFile], out: &mut dyn io::Write) -> io::Result<()> {
+    let too_large = || io::Error::other("the bundle is too large for a zip file");
+    let mut offset = 0u32;
+    let mut central = Vec::new();
+
+    for file in files {
+        let compressed = miniz_oxide::deflate::compress_to_vec(&file.contents, 6);
+        let crc = crc32fast::hash(&file.contents);
+        let name = file.path.as_bytes();
+        let size = u32::try_from(file.contents.len()).map_err(|_| too_large())?;
+        let compressed_size = u32::try_from(compressed.len()).map_err(|_| too_large())?;
+
+        // The fields shared by the local and central headers: version needed (2.0), flags (UTF-8 names), method
+        // (deflate), time, date, CRC, sizes and name length
+        let mut common = Vec::with_capacity(26);
+        common.extend(20u16.to_le_bytes());
+        common.extend((1u16 << 11).to_le_bytes());
+        common.extend(8u16.to_le_bytes());
+        common.extend(0u16.to_le_bytes());
+        common.extend(DOS_DATE.to_le_bytes());
+        common.extend(crc.to_le_bytes());
+        common.extend(compressed_size.to_le_bytes());
+        common.extend(size.to_le_bytes());
+        common.extend((name.len() as u16).to_le_bytes());
+
+        let mut local = Vec::with_capacity(30 + name.len());
+        local.extend(0x04034b50u32.to_le_bytes());
+        local.extend(&common);
+        // No extra field
+        local.extend(0u16.to_le_bytes());
+        local.extend(name);
+        out.write_all(&local)?;
+        out.write_all(&compressed)?;
+
+        central.extend(0x02014b50u32.to_le_bytes());
+        // Version made by
+        central.extend(20u16.to_le_bytes());
+        central.extend(&common);
+        // No extra field, comment, disk number, or attributes
+        central.extend([0; 12]);
+        central.extend(offset.to_le_bytes());
+        central.extend(name);
+
+        offset = (local.len() + compressed.len())
+            .try_into()
+            .ok()
+            .and_then(|len| offset.checked_add(len))
+            .ok_or_else(too_large)?;
+    }
+
+    let count = u16::try_from(files.len()).map_err(|_| too_large())?;
+    let central_size = u32::try_from(central.len()).map_err(|_| too_large())?;
+    out.write_all(&central)?;
+    let mut end = Vec::with_capacity(22);
+    end.extend(0x06054b50u32.to_le_bytes());
+    // Disk numbers
+    end.extend([0; 4]);
+    end.extend(count.to_le_bytes());
+    end.extend(count.to_le_bytes());
+    end.extend(central_size.to_le_bytes());
+    end.extend(offset.to_le_bytes());
+    // No comment
+    end.extend(0u16.to_le_bytes());
+    out.write_all(&end)
+}
+
+/// A zip archive of a static site of diagrams (see [`files`]), for uploading to a static host or attaching to a
+/// release
+pub(crate) struct BundleBackend;
+
+impl Backend for BundleBackend {
+    fn extension(&self) -> &'static str {
+        "zip"
+    }
+
+    fn render(
+        &self,
+        grammar: &Grammar,
+        config: &Config,
+        out: &mut dyn io::Write,
+    ) -> Result<(), Error> {
+        write_zip(&files(grammar, config)?, out)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+    use crate::backend::{render_test, Format};
+
+    const GRAMMAR: &str = r#"
+        //! The <"quoted"> & escaped grammar
+        /// Matches <tags> & "quotes"
+        value = { "<&>" ~ item }
+        item = { "a" }
+    "#;
+
+    fn bundle_files() -> Vec<BundleFile> {
+        let (grammar, _) = Grammar::parse(GRAMMAR).unwrap();
+        files(&grammar, &Config::default()).unwrap()
+    }
+
+    #[test]
+    fn site_files() {
+        let files = bundle_files();
+        let paths: Vec<_> = files.iter().map(|file| file.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "index.html",
+                "style.css",
+                "metadata.json",
+                "rules/value.svg",
+                "rules/item.svg"
+            ]
+        );
+
+        let index = String::from_utf8(files[0].contents.clone()).unwrap();
+        assert!(
+            index.contains("<title>The &lt;&quot;quoted&quot;&gt; &amp; escaped grammar</title>\n")
+        );
+        assert!(index.contains("<p>Matches &lt;tags&gt; &amp; &quot;quotes&quot;</p>\n"));
+        assert!(index.contains("<img src=\"rules/item.svg\" "));
+
+        let metadata: Value = serde_json::from_slice(&files[2].contents).unwrap();
+        assert_eq!(metadata["title"], "The <\"quoted\"> & escaped grammar");
+        assert_eq!(metadata["rules"][0]["name"], "value");
+        assert_eq!(
+            metadata["rules"][0]["docs"][0],
+            "Matches <tags> & \"quotes\""
+        );
+        assert_eq!(metadata["rules"][1]["file"], "rules/item.svg");
+
+        let svg = String::from_utf8(files[3].contents.clone()).unwrap();
+        assert!(svg.contains("&quot;&lt;&amp;&gt;&quot;"));
+    }
+
+    #[test]
+    fn zip_archive() {
+        let zip = render_test(GRAMMAR, &Config::default(), Format::Bundle);
+        let u16_at =
+            |bytes: &[u8], idx: usize| u16::from_le_bytes([bytes[idx], bytes[idx + 1]]) as usize;
+        let u32_at = |bytes: &[u8], idx: usize| {
+            u32::from_le_bytes([bytes[idx], bytes[idx + 1], bytes[idx + 2], bytes[idx + 3]])
+                as usize
+        };
+
+        // Each file is stored in order, deflated under a local header
+        let files = bundle_files();
+        let mut rest = zip.as_slice();
+        for file in &files {
+            assert_eq!(rest[..4], *b"PK\x03\x04");
+            let (size, name_len) = (u32_at(rest, 18), u16_at(rest, 26));
+            assert_eq!(&rest[30..30 + name_len], file.path.as_bytes());
+            let data = &rest[30 + name_len..30 + name_len + size];
+            assert_eq!(
+                miniz_oxide::inflate::decompress_to_vec(data).unwrap(),
+                file.contents
+            );
+            assert_eq!(u32_at(rest, 14), crc32fast::hash(&file.contents) as usize);
+            rest = &rest[30 + name_len + size..];
+        }
+
+        // Followed by the central directory and its end record, which counts the files
+        assert_eq!(rest[..4], *b"PK\x01\x02");
+        let end = &zip[zip.len() - 22..];
+        assert_eq!(end[..4], *b"PK\x05\x06");
+        assert_eq!(u16_at(end, 10), files.len());
+        assert_eq!(u32_at(end, 16), zip.len() - rest.len());
+    }
+}
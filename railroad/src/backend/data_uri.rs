@@ -0,0 +1,130 @@
+use std::io;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use railroad::Node;
+
+use crate::{
+    backend::{single_rule, Backend},
+    config::Config,
+    diagram,
+    ir::Grammar,
+    Error,
+};
+
+/// Writes an image of each rule's diagram with the SVG inlined as a data URI, as an HTML `<img>` tag or a Markdown
+/// image
+pub(crate) struct DataUriBackend {
+    pub(crate) markdown: bool,
+}
+
+impl Backend for DataUriBackend {
+    fn extension(&self) -> &'static str {
+        if self.markdown {
+            "md"
+        } else {
+            "html"
+        }
+    }
+
+    fn render(
+        &self,
+        grammar: &Grammar,
+        config: &Config,
+        out: &mut dyn io::Write,
+    ) -> Result<(), Error> {
+        let rule_config = Config {
+            title: None,
+            warnings: Vec::new(),
+            legend: false,
+            ..config.clone()
+        };
+
+        for (idx, rule) in grammar.rules.iter().enumerate() {
+            let diagram = diagram::make_diagram(
+                &single_rule(rule),
+                &rule_config,
+                config.theme.stylesheet(),
+                config.theme.dark_stylesheet(),
+            )?;
+            let uri = format!(
+                "data:image/svg+xml;base64,{}",
+                STANDARD.encode(diagram::to_svg(&diagram, &rule_config))
+            );
+            let alt = format!("Railroad diagram of {}", rule.name);
+
+            if idx > 0 {
+                writeln!(out)?;
+            }
+            if self.markdown {
+                writeln!(out, "![{alt}]({uri})")?;
+            } else {
+                writeln!(out, "<!-- {} -->", rule.name)?;
+                writeln!(
+                    out,
+                    "<img src=\"{uri}\" alt=\"{alt}\" width=\"{}\" height=\"{}\">",
+                    diagram.width(),
+                    diagram.height()
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{render_test, Format};
+
+    const GRAMMAR: &str = r#"
+        value = { "<&>" ~ item }
+        item = { "\"" }
+    "#;
+
+    /// The SVG encoded in a data URI at the start of `text`
+    fn decode(text: &str) -> String {
+        let text = text.strip_prefix("data:image/svg+xml;base64,").unwrap();
+        let end = text.find(['"', ')']).unwrap();
+        String::from_utf8(STANDARD.decode(&text[..end]).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn img_tags() {
+        let out =
+            String::from_utf8(render_test(GRAMMAR, &Config::default(), Format::DataUri)).unwrap();
+        let lines: Vec<_> = out.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0], "<!-- value -->");
+        assert_eq!(lines[2], "");
+        assert_eq!(lines[3], "<!-- item -->");
+
+        let img = lines[1].strip_prefix("<img src=\"").unwrap();
+        let svg = decode(img);
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("&quot;&lt;&amp;&gt;&quot;"));
+        assert!(img.contains("\" alt=\"Railroad diagram of value\" width=\""));
+        assert!(
+            decode(lines[4].strip_prefix("<img src=\"").unwrap()).contains("&quot;\\&quot;&quot;")
+        );
+    }
+
+    #[test]
+    fn markdown_images() {
+        let out = String::from_utf8(render_test(
+            GRAMMAR,
+            &Config::default(),
+            Format::DataUriMarkdown,
+        ))
+        .unwrap();
+        let lines: Vec<_> = out.lines().collect();
+        assert_eq!(lines.len(), 3);
+        let svg = decode(
+            lines[0]
+                .strip_prefix("![Railroad diagram of value](")
+                .unwrap(),
+        );
+        assert!(svg.contains("&quot;&lt;&amp;&gt;&quot;"));
+        assert!(lines[2].starts_with("![Railroad diagram of item](data:image/svg+xml;base64,"));
+        assert!(lines[2].ends_with(')'));
+    }
+}
@@ -0,0 +1,155 @@
+use std::{collections::HashMap, io};
+
+use crate::{
+    backend::{
+        flowchart::{Flowchart, Shape},
+        Backend,
+    },
+    config::Config,
+    ir::Grammar,
+    Error,
+};
+
+/// Escapes text for a quoted DOT string
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+pub(crate) struct DotBackend;
+
+impl Backend for DotBackend {
+    fn extension(&self) -> &'static str {
+        "dot"
+    }
+
+    fn render(
+        &self,
+        grammar: &Grammar,
+        config: &Config,
+        out: &mut dyn io::Write,
+    ) -> Result<(), Error> {
+        let charts = grammar
+            .rules
+            .iter()
+            .map(|rule| Flowchart::new(&rule.expr))
+            .collect::<Vec<_>>();
+        let rule_idx = grammar
+            .rules
+            .iter()
+            .enumerate()
+            .map(|(idx, rule)| (rule.name.as_str(), idx))
+            .collect::<HashMap<_, _>>();
+
+        writeln!(out, "digraph grammar {{")?;
+        writeln!(out, "    rankdir=LR;")?;
+        writeln!(out, "    compound=true;")?;
+        writeln!(out, "    node [fontname=\"monospace\"];")?;
+        if let Some(title) = config.title(grammar) {
+            writeln!(out, "    label=\"{}\";", escape(title))?;
+            writeln!(out, "    labelloc=t;")?;
+        }
+
+        for (idx, (rule, chart)) in grammar.rules.iter().zip(&charts).enumerate() {
+            writeln!(out)?;
+            writeln!(out, "    subgraph cluster_{idx} {{")?;
+            writeln!(out, "        label=\"{}\";", escape(&config.label(rule)))?;
+            for (node, (shape, label)) in chart.nodes.iter().enumerate() {
+                let attrs = match shape {
+                    // The start and end of the rule
+                    Shape::Circle => "shape=circle, width=0.15, label=\"\"".to_string(),
+                    Shape::Rect => format!("shape=box, label=\"{}\"", escape(label)),
+                    Shape::Rounded => {
+                        format!("shape=box, style=rounded, label=\"{}\"", escape(label))
+                    }
+                    Shape::Hexagon => format!("shape=hexagon, label=\"{}\"", escape(label)),
+                };
+                writeln!(out, "        r{idx}n{node} [{attrs}];")?;
+            }
+            for (from, to, label) in &chart.edges {
+                match label {
+                    Some(label) => writeln!(
+                        out,
+                        "        r{idx}n{from} -> r{idx}n{to} [label=\"{}\"];",
+                        escape(label)
+                    )?,
+                    None => writeln!(out, "        r{idx}n{from} -> r{idx}n{to};")?,
+                }
+            }
+            writeln!(out, "    }}")?;
+        }
+
+        // Rule references point at the cluster of the rule, without affecting the layout of the flowcharts
+        writeln!(out)?;
+        for (idx, chart) in charts.iter().enumerate() {
+            for (node, (shape, label)) in chart.nodes.iter().enumerate() {
+                let target = match shape {
+                    Shape::Rect => rule_idx.get(label.as_str()),
+                    _ => None,
+                };
+                if let Some(target) = target {
+                    writeln!(
+                        out,
+                        "    r{idx}n{node} -> r{target}n{} [style=dashed, constraint=false, lhead=cluster_{target}];",
+                        Flowchart::START
+                    )?;
+                }
+            }
+        }
+        writeln!(out, "}}")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{render_test, Format};
+
+    #[test]
+    fn golden() {
+        let src = r#"
+            //! The "quoted" grammar
+            value = { "\"" ~ ("\\" ~ ANY)+ ~ item }
+            item = { "a" }
+        "#;
+        let out = String::from_utf8(render_test(src, &Config::default(), Format::Dot)).unwrap();
+        assert_eq!(
+            out,
+            r#"digraph grammar {
+    rankdir=LR;
+    compound=true;
+    node [fontname="monospace"];
+    label="The \"quoted\" grammar";
+    labelloc=t;
+
+    subgraph cluster_0 {
+        label="value";
+        r0n0 [shape=circle, width=0.15, label=""];
+        r0n1 [shape=circle, width=0.15, label=""];
+        r0n2 [shape=box, style=rounded, label="\"\\\"\""];
+        r0n3 [shape=box, style=rounded, label="\"\\\\\""];
+        r0n4 [shape=box, label="ANY"];
+        r0n5 [shape=box, label="item"];
+        r0n3 -> r0n4;
+        r0n4 -> r0n3;
+        r0n2 -> r0n3;
+        r0n4 -> r0n5;
+        r0n0 -> r0n2;
+        r0n5 -> r0n1;
+    }
+
+    subgraph cluster_1 {
+        label="item";
+        r1n0 [shape=circle, width=0.15, label=""];
+        r1n1 [shape=circle, width=0.15, label=""];
+        r1n2 [shape=box, style=rounded, label="\"a\""];
+        r1n0 -> r1n2;
+        r1n2 -> r1n1;
+    }
+
+    r0n5 -> r1n0 [style=dashed, constraint=false, lhead=cluster_1];
+}
+"#
+        );
+    }
+}
@@ -0,0 +1,360 @@
+use std::{collections::HashSet, io};
+
+use crate::{
+    backend::Backend,
+    config::Config,
+    diagram,
+    ir::{Expr, Grammar},
+    lint, Error,
+};
+
+/// How tightly an EBNF construct binds, loosest first. A construct has to be grouped in parentheses when used where
+/// a tighter one is expected.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Prec {
+    /// Alternatives (`a | b`)
+    Choice,
+    /// Concatenation (`a , b`)
+    Sequence,
+    /// Exception (`a - b`)
+    Exception,
+    /// Repetition count (`3 * a`)
+    Factor,
+    /// Identifiers, terminals, special sequences and bracketed constructs
+    Primary,
+}
+
+pub(crate) struct EbnfBackend;
+
+/// Converts expressions into ISO 14977 EBNF. Constructs without an EBNF equivalent (character ranges, case
+/// insensitive strings and built-in rules) become special sequences (`? ... ?`), and lookahead, which matches nothing,
+/// becomes a comment - except for the `!a ~ b` idiom where `a` is a single character or `b` is `ANY`, which is the
+/// exception `b - a`.
+struct Converter<'a> {
+    /// The rules defined by the grammar, which take precedence over built-ins of the same name
+    rules: HashSet<&'a str>,
+}
+
+impl Converter<'_> {
+    /// Converts an expression, grouping it if it binds looser than `prec`
+    fn convert(&self, expr: &Expr, prec: Prec) -> String {
+        let (text, expr_prec) = self.ebnf(expr);
+        if expr_prec < prec {
+            format!("( {text} )")
+        } else {
+            text
+        }
+    }
+
+    /// Converts an expression, along with how tightly the result binds
+    fn ebnf(&self, expr: &Expr) -> (String, Prec) {
+        match expr {
+            Expr::Choice { choices } => (self.choices(choices).join(" | "), Prec::Choice),
+            Expr::Sequence { terms } => self.sequence(terms),
+            Expr::Ident { name, .. }
+                if lint::is_builtin(name) && !self.rules.contains(name.as_str()) =>
+            {
+                (format!("? {name} ?"), Prec::Primary)
+            }
+            Expr::Ident { name, .. } => (name.clone(), Prec::Primary),
+            Expr::Str { value, .. } => (terminal(value), Prec::Primary),
+            Expr::InsensitiveStr { value, .. } => (
+                special(&format!("case insensitive \"{value}\"")),
+                Prec::Primary,
+            ),
+            Expr::Range { start, end, .. } => (
+                special(&format!("any character from '{start}' to '{end}'")),
+                Prec::Primary,
+            ),
+            Expr::Optional { expr } => (
+                format!("[ {} ]", self.convert(expr, Prec::Choice)),
+                Prec::Primary,
+            ),
+            Expr::Repeat { expr } => (
+                format!("{{ {} }}", self.convert(expr, Prec::Choice)),
+                Prec::Primary,
+            ),
+            Expr::RepeatOnce { expr } => self.repeat(expr, 1, None),
+            Expr::RepeatRange { expr, min, max } => self.repeat(expr, *min, *max),
+            Expr::PositivePredicate { expr } => (
+                comment(&format!("followed by {}", self.convert(expr, Prec::Choice))),
+                Prec::Primary,
+            ),
+            // EBNF has no stack, so the expression is matched as is
+            Expr::Push { expr } => (
+                format!(
+                    "{} , {}",
+                    self.convert(expr, Prec::Sequence),
+                    comment("pushed onto the stack")
+                ),
+                Prec::Sequence,
+            ),
+            Expr::PeekSlice { start, end, .. } => (
+                special(&format!(
+                    "the strings of the stack slice {}",
+                    diagram::peek_slice(*start, *end)
+                )),
+                Prec::Primary,
+            ),
+            Expr::NegativePredicate { expr } => (
+                comment(&format!(
+                    "not followed by {}",
+                    self.convert(expr, Prec::Choice)
+                )),
+                Prec::Primary,
+            ),
+        }
+    }
+
+    /// Converts the alternatives of a choice
+    fn choices(&self, choices: &[Expr]) -> Vec<String> {
+        choices
+            .iter()
+            .map(|choice| self.convert(choice, Prec::Sequence))
+            .collect()
+    }
+
+    /// Converts a sequence, turning a negative lookahead followed by another term into an exception where they mean
+    /// the same
+    fn sequence(&self, terms: &[Expr]) -> (String, Prec) {
+        let mut parts = Vec::with_capacity(terms.len());
+        let mut terms = terms.iter().peekable();
+        while let Some(term) = terms.next() {
+            match (term, terms.peek()) {
+                (Expr::NegativePredicate { expr }, Some(next)) if self.is_exception(expr, next) => {
+                    parts.push((
+                        format!(
+                            "{} - {}",
+                            self.convert(next, Prec::Factor),
+                            self.convert(expr, Prec::Factor)
+                        ),
+                        Prec::Exception,
+                    ));
+                    terms.next();
+                }
+                _ => parts.push(self.ebnf(term)),
+            }
+        }
+
+        match parts.len() {
+            // An empty sequence is valid EBNF (and matches nothing, like the empty sequence in the IR)
+            0 => (String::new(), Prec::Primary),
+            1 => parts.remove(0),
+            _ => {
+                let text = parts
+                    .into_iter()
+                    .map(|(text, prec)| match prec {
+                        Prec::Choice => format!("( {text} )"),
+                        _ => text,
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" , ");
+                (text, Prec::Sequence)
+            }
+        }
+    }
+
+    /// Returns true if `!excluded ~ expr` can be written as the exception `expr - excluded`. An exception compares
+    /// whole matches while lookahead only looks at the start of the input, so only the idioms where the two agree are
+    /// rewritten: excluding a single character, or any character but the excluded expression (`!a ~ ANY`).
+    fn is_exception(&self, excluded: &Expr, expr: &Expr) -> bool {
+        let single_char = match excluded {
+            Expr::Str { value, .. } => {
+                unescape(value).is_some_and(|text| text.chars().count() == 1)
+            }
+            Expr::Range { .. } => true,
+            _ => false,
+        };
+        let any = matches!(expr, Expr::Ident { name, .. } if name == "ANY" && !self.rules.contains("ANY"));
+        single_char || any
+    }
+
+    /// Converts a repetition of at least `min` and at most `max` (or unbounded) times: the required repetitions
+    /// followed by the optional ones
+    fn repeat(&self, expr: &Expr, min: u32, max: Option<u32>) -> (String, Prec) {
+        let mut parts = Vec::new();
+        match min {
+            0 => {}
+            1 => parts.push(self.convert(expr, Prec::Factor)),
+            _ => parts.push(format!("{min} * {}", self.convert(expr, Prec::Primary))),
+        }
+        let inner = self.convert(expr, Prec::Choice);
+        match max.map(|max| max.saturating_sub(min)) {
+            None => parts.push(format!("{{ {inner} }}")),
+            Some(0) => {}
+            Some(1) => parts.push(format!("[ {inner} ]")),
+            Some(optional) => parts.push(format!("{optional} * [ {inner} ]")),
+        }
+
+        match parts.len() {
+            0 => (String::new(), Prec::Primary),
+            1 => (parts.remove(0), Prec::Factor),
+            _ => (parts.join(" , "), Prec::Sequence),
+        }
+    }
+}
+
+/// Converts a pest string literal into an EBNF terminal. EBNF has no escapes, so strings that can't be written
+/// between either kind of quote become special sequences.
+fn terminal(value: &str) -> String {
+    match unescape(value) {
+        Some(text) if !text.is_empty() && !text.contains('"') => format!("\"{text}\""),
+        Some(text) if !text.is_empty() && !text.contains('\'') => format!("'{text}'"),
+        _ => special(&format!("\"{value}\"")),
+    }
+}
+
+/// Removes the quote and backslash escapes of a pest string, or returns `None` if it has any other escape (those are
+/// for characters that can't be written in EBNF)
+fn unescape(value: &str) -> Option<String> {
+    let mut text = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next()? {
+                c @ ('"' | '\'' | '\\') => text.push(c),
+                _ => return None,
+            },
+            c => text.push(c),
+        }
+    }
+    Some(text)
+}
+
+/// A special sequence, whose meaning EBNF leaves to the reader
+fn special(text: &str) -> String {
+    // A question mark would end the sequence early, so escape it like a string literal would
+    format!("? {} ?", text.replace('?', "\\?"))
+}
+
+/// A comment
+fn comment(text: &str) -> String {
+    // A comment can't contain its own end
+    format!("(* {} *)", text.replace("*)", "* )"))
+}
+
+impl Backend for EbnfBackend {
+    fn extension(&self) -> &'static str {
+        "ebnf"
+    }
+
+    fn render(
+        &self,
+        grammar: &Grammar,
+        _config: &Config,
+        out: &mut dyn io::Write,
+    ) -> Result<(), Error> {
+        let converter = Converter {
+            rules: grammar
+                .rules
+                .iter()
+                .map(|rule| rule.name.as_str())
+                .collect(),
+        };
+
+        for doc in &grammar.docs {
+            writeln!(out, "{}", comment(doc.trim()))?;
+        }
+        for (idx, rule) in grammar.rules.iter().enumerate() {
+            if idx > 0 || !grammar.docs.is_empty() {
+                writeln!(out)?;
+            }
+            for doc in &rule.docs {
+                writeln!(out, "{}", comment(doc.trim()))?;
+            }
+            // The modifier changes how whitespace is handled, which EBNF can't express
+            if let Some(label) = rule.modifier.label() {
+                writeln!(out, "{}", comment(&format!("{label} rule")))?;
+            }
+
+            match &rule.expr {
+                // Give each top level alternative a line of its own, lined up under the first
+                Expr::Choice { choices } if choices.len() > 1 => {
+                    let indent = " ".repeat(rule.name.len() + 1);
+                    let choices = converter.choices(choices);
+                    writeln!(out, "{} = {}", rule.name, choices[0])?;
+                    for choice in &choices[1..] {
+                        writeln!(out, "{indent}| {choice}")?;
+                    }
+                    writeln!(out, "{indent};")?;
+                }
+                expr => match converter.convert(expr, Prec::Choice) {
+                    // A rule whose terms were all unsupported matches the empty sequence
+                    body if body.is_empty() => writeln!(out, "{} = ;", rule.name)?,
+                    body => writeln!(out, "{} = {body} ;", rule.name)?,
+                },
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(source: &str) -> String {
+        let (grammar, _) = Grammar::parse(source).unwrap();
+        let mut out = Vec::new();
+        EbnfBackend
+            .render(&grammar, &Config::default(), &mut out)
+            .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn docs_modifiers_and_groups() {
+        assert_eq!(
+            render("//! Numbers\nnum = @{ \"-\"? ~ (\"0\" | ASCII_NONZERO_DIGIT ~ ASCII_DIGIT*) }"),
+            "(* Numbers *)\n\
+             \n\
+             (* atomic rule *)\n\
+             num = [ \"-\" ] , ( \"0\" | ? ASCII_NONZERO_DIGIT ? , { ? ASCII_DIGIT ? } ) ;\n"
+        );
+    }
+
+    #[test]
+    fn repeats() {
+        assert_eq!(
+            render("list = { num ~ (\",\" ~ num)* ~ num{2} ~ num{1,3} ~ num+ }\nnum = { \"1\" }"),
+            "list = num , { \",\" , num } , 2 * num , num , 2 * [ num ] , num , { num } ;\n\
+             \n\
+             num = \"1\" ;\n"
+        );
+    }
+
+    #[test]
+    fn choices_line_up() {
+        assert_eq!(
+            render("kw = { ^\"if\" | 'a'..'z' }"),
+            "kw = ? case insensitive \"if\" ?\n   \
+             | ? any character from 'a' to 'z' ?\n   \
+             ;\n"
+        );
+    }
+
+    #[test]
+    fn exceptions() {
+        assert_eq!(render("x = { !\"a\" ~ \"b\" }"), "x = \"b\" - \"a\" ;\n");
+        assert_eq!(
+            render("x = { !'0'..'9' ~ y }\ny = { \"b\" }")
+                .lines()
+                .next(),
+            Some("x = y - ? any character from '0' to '9' ? ;")
+        );
+        assert_eq!(
+            render("x = { !(\"\\\"\" | \"ab\") ~ ANY }"),
+            "x = ? ANY ? - ( '\"' | \"ab\" ) ;\n"
+        );
+    }
+
+    #[test]
+    fn lookahead_without_exception() {
+        assert_eq!(
+            render("w = { !\"ab\" ~ x ~ &\"y\" ~ \"z\" }\nx = { \"b\" }")
+                .lines()
+                .next(),
+            Some("w = (* not followed by \"ab\" *) , x , (* followed by \"y\" *) , \"z\" ;")
+        );
+    }
+}
@@ -0,0 +1,206 @@
+use std::io;
+
+use usvg::{
+    tiny_skia_path::{PathSegment, Point, Transform},
+    FillRule, LineCap, LineJoin, Node, Paint,
+};
+
+use crate::{backend::vector, backend::Backend, config::Config, ir::Grammar, Error};
+
+/// The size of a CSS pixel (the unit of the SVG diagrams) in PostScript points
+const PX: f32 = 0.75;
+
+/// A PostScript number, rounded so the output isn't cluttered with float noise
+fn num(value: f32) -> String {
+    let text = format!("{value:.3}");
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    match text {
+        "-0" => "0".to_string(),
+        text => text.to_string(),
+    }
+}
+
+/// Sets the current color to a paint. PostScript has no transparency, so the color is blended with the white page
+/// instead. Gradients and patterns (which the diagrams don't use) are drawn black.
+fn set_color(paint: &Paint, opacity: f32, out: &mut String) {
+    let channel = |value: u8| num(1.0 - opacity * (1.0 - value as f32 / 255.0));
+    match paint {
+        Paint::Color(color) => out.push_str(&format!(
+            "{} {} {} setrgbcolor\n",
+            channel(color.red),
+            channel(color.green),
+            channel(color.blue)
+        )),
+        _ => out.push_str("0 setgray\n"),
+    }
+}
+
+/// Appends the commands tracing a path (in the coordinates of the page, which has its origin at the top left)
+fn trace(path: &usvg::Path, out: &mut String) {
+    let transform = path.abs_transform();
+    let point = |mut p: Point| {
+        transform.map_point(&mut p);
+        format!("{} {}", num(p.x), num(p.y))
+    };
+
+    out.push_str("newpath\n");
+    let mut last = Point::zero();
+    for segment in path.data().segments() {
+        match segment {
+            PathSegment::MoveTo(p) => {
+                out.push_str(&format!("{} moveto\n", point(p)));
+                last = p;
+            }
+            PathSegment::LineTo(p) => {
+                out.push_str(&format!("{} lineto\n", point(p)));
+                last = p;
+            }
+            // PostScript only has cubic curves, which can draw any quadratic one
+            PathSegment::QuadTo(q, p) => {
+                let c1 = Point::from_xy(
+                    last.x + 2.0 / 3.0 * (q.x - last.x),
+                    last.y + 2.0 / 3.0 * (q.y - last.y),
+                );
+                let c2 =
+                    Point::from_xy(p.x + 2.0 / 3.0 * (q.x - p.x), p.y + 2.0 / 3.0 * (q.y - p.y));
+                out.push_str(&format!(
+                    "{} {} {} curveto\n",
+                    point(c1),
+                    point(c2),
+                    point(p)
+                ));
+                last = p;
+            }
+            PathSegment::CubicTo(c1, c2, p) => {
+                out.push_str(&format!(
+                    "{} {} {} curveto\n",
+                    point(c1),
+                    point(c2),
+                    point(p)
+                ));
+                last = p;
+            }
+            PathSegment::Close => out.push_str("closepath\n"),
+        }
+    }
+}
+
+/// The factor a transform scales lengths (such as stroke widths) by
+fn scale(transform: Transform) -> f32 {
+    (transform.sx * transform.sy - transform.kx * transform.ky)
+        .abs()
+        .sqrt()
+}
+
+/// Appends the commands drawing a path
+fn draw_path(path: &usvg::Path, out: &mut String) {
+    if !path.is_visible() {
+        return;
+    }
+
+    if let Some(fill) = path.fill() {
+        trace(path, out);
+        set_color(fill.paint(), fill.opacity().get(), out);
+        out.push_str(match fill.rule() {
+            FillRule::NonZero => "fill\n",
+            FillRule::EvenOdd => "eofill\n",
+        });
+    }
+    if let Some(stroke) = path.stroke() {
+        trace(path, out);
+        set_color(stroke.paint(), stroke.opacity().get(), out);
+        let scale = scale(path.abs_transform());
+        out.push_str(&format!(
+            "{} setlinewidth {} setlinecap {} setlinejoin\n",
+            num(stroke.width().get() * scale),
+            match stroke.linecap() {
+                LineCap::Butt => 0,
+                LineCap::Round => 1,
+                LineCap::Square => 2,
+            },
+            match stroke.linejoin() {
+                LineJoin::Miter | LineJoin::MiterClip => 0,
+                LineJoin::Round => 1,
+                LineJoin::Bevel => 2,
+            }
+        ));
+        let dashes = stroke.dasharray().unwrap_or_default();
+        out.push_str(&format!(
+            "[{}] {} setdash\n",
+            dashes
+                .iter()
+                .map(|dash| num(dash * scale))
+                .collect::<Vec<_>>()
+                .join(" "),
+            num(stroke.dashoffset() * scale)
+        ));
+        out.push_str("stroke\n");
+    }
+}
+
+/// Appends the commands drawing every path in a group, including the outlines of the glyphs of its text
+fn draw_group(group: &usvg::Group, out: &mut String) {
+    for node in group.children() {
+        match node {
+            Node::Group(group) => draw_group(group, out),
+            Node::Path(path) => draw_path(path, out),
+            Node::Text(text) => draw_group(text.flattened(), out),
+            // The diagrams don't contain images
+            Node::Image(_) => {}
+        }
+    }
+}
+
+pub(crate) struct EpsBackend;
+
+impl Backend for EpsBackend {
+    fn extension(&self) -> &'static str {
+        "eps"
+    }
+
+    fn render(
+        &self,
+        grammar: &Grammar,
+        config: &Config,
+        out: &mut dyn io::Write,
+    ) -> Result<(), Error> {
+        let tree = vector::tree(grammar, config, &vector::parse_options())?;
+        let size = tree.size();
+        let (width, height) = (size.width() * PX, size.height() * PX);
+
+        let mut eps = String::new();
+        eps.push_str("%!PS-Adobe-3.0 EPSF-3.0\n");
+        eps.push_str(&format!(
+            "%%BoundingBox: 0 0 {} {}\n",
+            width.ceil(),
+            height.ceil()
+        ));
+        eps.push_str(&format!(
+            "%%HiResBoundingBox: 0 0 {} {}\n",
+            num(width),
+            num(height)
+        ));
+        if let Some(title) = config.title(grammar) {
+            // DSC comments end at the end of the line
+            eps.push_str(&format!("%%Title: {}\n", title.replace(['\r', '\n'], " ")));
+        }
+        eps.push_str("%%Creator: pest_railroad\n");
+        eps.push_str("%%LanguageLevel: 2\n");
+        eps.push_str("%%EndComments\n");
+        eps.push_str("gsave\n");
+        // Flip the page, so its origin is at the top left like the SVG's, and convert its units
+        eps.push_str(&format!(
+            "0 {} translate {} {} scale\n",
+            num(height),
+            num(PX),
+            num(-PX)
+        ));
+        draw_group(tree.root(), &mut eps);
+        eps.push_str("grestore\n");
+        eps.push_str("showpage\n");
+        eps.push_str("%%EOF\n");
+
+        out.write_all(eps.as_bytes())?;
+        Ok(())
+    }
+}
@@ -0,0 +1,180 @@
+//! The structure of rules as flowcharts, for the backends of graph drawing tools
+
+use crate::{diagram, ir::Expr};
+
+/// The shapes of flowchart nodes
+#[derive(Clone, Copy)]
+pub(super) enum Shape {
+    /// The start and end of a rule
+    Circle,
+    /// A reference to another rule (or a built-in)
+    Rect,
+    /// A string or character range
+    Rounded,
+    /// A lookahead, which matches nothing itself
+    Hexagon,
+}
+
+/// The nodes of an expression that can come first and last, and whether it can match nothing at all
+struct Fragment {
+    first: Vec<usize>,
+    last: Vec<usize>,
+    nullable: bool,
+}
+
+/// Builds the flowchart of a rule. Only the terms of the rule become nodes, with an edge from each term to every term
+/// that can follow it, so no junction nodes are needed.
+pub(super) struct Flowchart {
+    pub(super) nodes: Vec<(Shape, String)>,
+    pub(super) edges: Vec<(usize, usize, Option<String>)>,
+}
+
+impl Flowchart {
+    /// The node the rule starts from
+    pub(super) const START: usize = 0;
+    /// The node the rule ends at
+    pub(super) const END: usize = 1;
+
+    pub(super) fn new(expr: &Expr) -> Flowchart {
+        let mut chart = Flowchart {
+            nodes: vec![
+                (Shape::Circle, String::new()),
+                (Shape::Circle, String::new()),
+            ],
+            edges: Vec::new(),
+        };
+        let fragment = chart.fragment(expr);
+        chart.connect(&[Self::START], &fragment.first, None);
+        chart.connect(&fragment.last, &[Self::END], None);
+        if fragment.nullable {
+            chart.connect(&[Self::START], &[Self::END], None);
+        }
+        chart
+    }
+
+    fn node(&mut self, shape: Shape, label: String) -> Fragment {
+        self.nodes.push((shape, label));
+        let idx = self.nodes.len() - 1;
+        Fragment {
+            first: vec![idx],
+            last: vec![idx],
+            nullable: false,
+        }
+    }
+
+    /// Adds an edge from each of `from` to each of `to`
+    fn connect(&mut self, from: &[usize], to: &[usize], label: Option<&str>) {
+        for &from in from {
+            for &to in to {
+                let edge = (from, to, label.map(str::to_string));
+                if !self.edges.contains(&edge) {
+                    self.edges.push(edge);
+                }
+            }
+        }
+    }
+
+    /// Adds the nodes and (internal) edges of an expression
+    fn fragment(&mut self, expr: &Expr) -> Fragment {
+        match expr {
+            Expr::Choice { choices } => {
+                let mut choice = Fragment {
+                    first: Vec::new(),
+                    last: Vec::new(),
+                    nullable: false,
+                };
+                for expr in choices {
+                    let fragment = self.fragment(expr);
+                    choice.first.extend(fragment.first);
+                    choice.last.extend(fragment.last);
+                    choice.nullable |= fragment.nullable;
+                }
+                choice
+            }
+            Expr::Sequence { terms } => {
+                let mut sequence = Fragment {
+                    first: Vec::new(),
+                    last: Vec::new(),
+                    nullable: true,
+                };
+                for term in terms {
+                    let fragment = self.fragment(term);
+                    self.connect(&sequence.last, &fragment.first, None);
+                    if sequence.nullable {
+                        sequence.first.extend(&fragment.first);
+                    }
+                    if !fragment.nullable {
+                        sequence.last.clear();
+                    }
+                    sequence.last.extend(fragment.last);
+                    sequence.nullable &= fragment.nullable;
+                }
+                sequence
+            }
+            Expr::Ident { name, .. } => self.node(Shape::Rect, name.clone()),
+            Expr::Str { .. } | Expr::InsensitiveStr { .. } | Expr::Range { .. } => {
+                self.node(Shape::Rounded, notation(expr))
+            }
+            Expr::Optional { expr } => {
+                let mut fragment = self.fragment(expr);
+                fragment.nullable = true;
+                fragment
+            }
+            Expr::Repeat { expr } => self.repeat(expr, 0, None, None),
+            Expr::RepeatOnce { expr } => self.repeat(expr, 1, None, None),
+            Expr::RepeatRange { expr, min, max } => {
+                self.repeat(expr, *min, *max, Some(&bounds(*min, *max)))
+            }
+            Expr::PositivePredicate { .. }
+            | Expr::NegativePredicate { .. }
+            | Expr::Push { .. }
+            | Expr::PeekSlice { .. } => self.node(Shape::Hexagon, notation(expr)),
+        }
+    }
+
+    /// Adds a repeated expression, with edges looping back from its last nodes to its first (labeled with the number
+    /// of repetitions, if bounded)
+    fn repeat(&mut self, expr: &Expr, min: u32, max: Option<u32>, label: Option<&str>) -> Fragment {
+        let mut fragment = self.fragment(expr);
+        if max.is_none_or(|max| max > 1) {
+            self.connect(&fragment.last, &fragment.first, label);
+        }
+        fragment.nullable |= min == 0;
+        fragment
+    }
+}
+
+/// The pest notation of an expression, for labelling nodes
+fn notation(expr: &Expr) -> String {
+    // Operands that aren't a single term are parenthesized
+    let operand = |expr: &Expr| match expr {
+        Expr::Choice { .. } | Expr::Sequence { .. } => format!("({})", notation(expr)),
+        _ => notation(expr),
+    };
+    match expr {
+        Expr::Choice { choices } => choices.iter().map(operand).collect::<Vec<_>>().join(" | "),
+        Expr::Sequence { terms } => terms.iter().map(operand).collect::<Vec<_>>().join(" ~ "),
+        Expr::Ident { name, .. } => name.clone(),
+        Expr::Str { value, .. } => format!("\"{value}\""),
+        Expr::InsensitiveStr { value, .. } => format!("^\"{value}\""),
+        Expr::Range { start, end, .. } => format!("'{start}'..'{end}'"),
+        Expr::Optional { expr } => format!("{}?", operand(expr)),
+        Expr::Repeat { expr } => format!("{}*", operand(expr)),
+        Expr::RepeatOnce { expr } => format!("{}+", operand(expr)),
+        Expr::RepeatRange { expr, min, max } => format!("{}{}", operand(expr), bounds(*min, *max)),
+        Expr::PositivePredicate { expr } => format!("&{}", operand(expr)),
+        Expr::NegativePredicate { expr } => format!("!{}", operand(expr)),
+        Expr::Push { expr } => format!("PUSH({})", notation(expr)),
+        Expr::PeekSlice { start, end, .. } => diagram::peek_slice(*start, *end),
+    }
+}
+
+/// The pest notation of the bounds of a repetition (such as `{2,4}`)
+fn bounds(min: u32, max: Option<u32>) -> String {
+    match max {
+        Some(max) if min == max => format!("{{{min}}}"),
+        None => format!("{{{min},}}"),
+        Some(max) if min == 0 => format!("{{,{max}}}"),
+        Some(max) => format!("{{{min},{max}}}"),
+    }
+}
@@ -0,0 +1,377 @@
+use std::io;
+
+use railroad::{Diagram, Node};
+use serde::Serialize;
+
+use crate::{
+    backend::Backend,
+    config::Config,
+    diagram::{self, RuleLayout},
+    ir::Grammar,
+    Error,
+};
+
+// The railroad nodes only expose their layout by drawing themselves, so the geometry is read back from the SVG elements
+// they draw
+
+/// An element of the SVG drawn by the railroad nodes
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    text: String,
+    children: Vec<Element>,
+}
+
+impl Element {
+    fn attr(&self, key: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn num(&self, key: &str) -> i64 {
+        self.attr(key)
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(0)
+    }
+}
+
+/// Reverses the minimal escaping of text and attribute values done by railroad
+fn unescape(text: &str) -> String {
+    text.replace("&quot;", "\"")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&#x27;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Parses the element at the start of `input`, advancing past it. This only needs to understand the SVG written by
+/// railroad: one tag per line, with either text or child elements.
+fn parse_element(input: &mut &str) -> Option<Element> {
+    let rest = input.trim_start().strip_prefix('<')?;
+    let end = rest.find([' ', '>', '/'])?;
+    let (name, mut rest) = rest.split_at(end);
+
+    let mut attributes = Vec::new();
+    let closed = loop {
+        rest = rest.trim_start();
+        if let Some(after) = rest.strip_prefix("/>") {
+            rest = after;
+            break true;
+        }
+        if let Some(after) = rest.strip_prefix('>') {
+            rest = after;
+            break false;
+        }
+        let (key, after) = rest.split_once("=\"")?;
+        let (value, after) = after.split_once('"')?;
+        attributes.push((key.to_string(), unescape(value)));
+        rest = after;
+    };
+
+    let mut element = Element {
+        name: name.to_string(),
+        attributes,
+        text: String::new(),
+        children: Vec::new(),
+    };
+    if !closed {
+        loop {
+            let content = rest.trim_start();
+            if let Some(after) = content.strip_prefix("</") {
+                rest = &after[after.find('>')? + 1..];
+                break;
+            } else if content.starts_with('<') {
+                rest = content;
+                element.children.push(parse_element(&mut rest)?);
+            } else {
+                let end = content.find('<')?;
+                element.text.push_str(&unescape(content[..end].trim()));
+                rest = &content[end..];
+            }
+        }
+    }
+    *input = rest;
+    Some(element)
+}
+
+/// A terminal, rule reference or built-in rule reference
+#[derive(Serialize)]
+struct NodeBox {
+    /// `terminal`, `nonterminal` or `builtin`
+    kind: &'static str,
+    label: String,
+    x: i64,
+    y: i64,
+    width: i64,
+    height: i64,
+    /// The link of a rule reference, if the config has a link template
+    #[serde(skip_serializing_if = "Option::is_none")]
+    href: Option<String>,
+}
+
+/// A comment, such as a rule's name, a doc comment or the label of a repeat
+#[derive(Serialize)]
+struct Label {
+    text: String,
+    /// The middle of the baseline of the text
+    x: i64,
+    y: i64,
+}
+
+/// A frame around part of a rule, labeled with what it means (such as lookahead)
+#[derive(Serialize)]
+struct Frame {
+    x: i64,
+    y: i64,
+    width: i64,
+    height: i64,
+}
+
+/// The parts of the diagram inside a rule (or outside all of them)
+#[derive(Default, Serialize)]
+struct Parts {
+    nodes: Vec<NodeBox>,
+    labels: Vec<Label>,
+    frames: Vec<Frame>,
+    /// The lines connecting the nodes, as SVG path data
+    connections: Vec<String>,
+}
+
+impl Parts {
+    /// Collects the parts drawn by an element, with the link of any enclosing `<a>`
+    fn collect(&mut self, element: &Element, href: Option<&str>) {
+        let class = element.attr("class").unwrap_or_default();
+        match element.name.as_str() {
+            "g" if class == "terminal" || class == "nonterminal" => {
+                let rect = element.children.iter().find(|child| child.name == "rect");
+                let text = element.children.iter().find(|child| child.name == "text");
+                if let (Some(rect), Some(text)) = (rect, text) {
+                    self.nodes.push(NodeBox {
+                        kind: if class == "terminal" {
+                            "terminal"
+                        } else {
+                            "nonterminal"
+                        },
+                        label: text.text.clone(),
+                        x: rect.num("x"),
+                        y: rect.num("y"),
+                        width: rect.num("width"),
+                        height: rect.num("height"),
+                        href: href.map(str::to_string),
+                    });
+                }
+            }
+            // The bounds of a built-in's hexagon are those of its box
+            "g" if class == "builtin" => {
+                let polygon = element
+                    .children
+                    .iter()
+                    .find(|child| child.name == "polygon");
+                let text = element.children.iter().find(|child| child.name == "text");
+                if let (Some(polygon), Some(text)) = (polygon, text) {
+                    let points = polygon
+                        .attr("points")
+                        .unwrap_or_default()
+                        .split([' ', ','])
+                        .filter_map(|num| num.parse::<i64>().ok())
+                        .collect::<Vec<_>>();
+                    let xs = points.iter().step_by(2);
+                    let ys = points.iter().skip(1).step_by(2);
+                    let (x, y) = (xs.clone().min(), ys.clone().min());
+                    let (max_x, max_y) = (xs.max(), ys.max());
+                    if let (Some(x), Some(y), Some(max_x), Some(max_y)) = (x, y, max_x, max_y) {
+                        self.nodes.push(NodeBox {
+                            kind: "builtin",
+                            label: text.text.clone(),
+                            x: *x,
+                            y: *y,
+                            width: max_x - x,
+                            height: max_y - y,
+                            href: href.map(str::to_string),
+                        });
+                    }
+                }
+            }
+            "a" => {
+                let href = element.attr("xlink:href").or(element.attr("href"));
+                for child in &element.children {
+                    self.collect(child, href);
+                }
+            }
+            "text" => self.labels.push(Label {
+                text: element.text.clone(),
+                x: element.num("x"),
+                y: element.num("y"),
+            }),
+            // The background of the whole diagram
+            "rect" if class == "railroad_canvas" => {}
+            // The only other boxes are the frames of labeled boxes
+            "rect" => self.frames.push(Frame {
+                x: element.num("x"),
+                y: element.num("y"),
+                width: element.num("width"),
+                height: element.num("height"),
+            }),
+            "path" => {
+                if let Some(path) = element.attr("d") {
+                    self.connections.push(path.trim().to_string());
+                }
+            }
+            _ => {
+                for child in &element.children {
+                    self.collect(child, href);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct RuleGeometry {
+    #[serde(flatten)]
+    layout: RuleLayout,
+    #[serde(flatten)]
+    parts: Parts,
+}
+
+/// The laid-out diagram of a grammar
+#[derive(Serialize)]
+struct Geometry {
+    width: i64,
+    height: i64,
+    rules: Vec<RuleGeometry>,
+    /// The parts outside of the rules: the doc comments and warnings
+    #[serde(flatten)]
+    other: Parts,
+}
+
+/// The parts of the rule whose row contains the height `y` (or the other parts, if none does)
+fn parts_at<'a>(rules: &'a mut [RuleGeometry], other: &'a mut Parts, y: i64) -> &'a mut Parts {
+    match rules
+        .iter_mut()
+        .find(|rule| (rule.layout.y..rule.layout.y + rule.layout.height).contains(&y))
+    {
+        Some(rule) => &mut rule.parts,
+        None => other,
+    }
+}
+
+pub(crate) struct GeometryBackend;
+
+impl Backend for GeometryBackend {
+    fn extension(&self) -> &'static str {
+        "json"
+    }
+
+    fn render(
+        &self,
+        grammar: &Grammar,
+        config: &Config,
+        out: &mut dyn io::Write,
+    ) -> Result<(), Error> {
+        // The layout doesn't depend on the CSS, so the diagram is drawn without any
+        let diagram = Diagram::new(diagram::make_grammar(grammar, config));
+        let svg = diagram.to_string();
+        let root = parse_element(&mut svg.as_str())
+            .ok_or_else(|| Error::Render("the diagram could not be read back".to_string()))?;
+
+        let mut all = Parts::default();
+        all.collect(&root, None);
+
+        // Sort the parts into the rules they fall within
+        let mut rules = diagram::layout_rules(grammar, config)
+            .into_iter()
+            .map(|layout| RuleGeometry {
+                layout,
+                parts: Parts::default(),
+            })
+            .collect::<Vec<_>>();
+        let mut other = Parts::default();
+        for node in all.nodes {
+            parts_at(&mut rules, &mut other, node.y).nodes.push(node);
+        }
+        for label in all.labels {
+            parts_at(&mut rules, &mut other, label.y).labels.push(label);
+        }
+        for frame in all.frames {
+            parts_at(&mut rules, &mut other, frame.y).frames.push(frame);
+        }
+        for path in all.connections {
+            // Paths start by moving to their first point
+            let y = path
+                .split_whitespace()
+                .nth(2)
+                .and_then(|y| y.parse().ok())
+                .unwrap_or(0);
+            parts_at(&mut rules, &mut other, y).connections.push(path);
+        }
+
+        let geometry = Geometry {
+            width: diagram.width(),
+            height: diagram.height(),
+            rules,
+            other,
+        };
+        serde_json::to_writer_pretty(&mut *out, &geometry).map_err(io::Error::from)?;
+        writeln!(out)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+    use crate::backend::{render_test, Format};
+
+    #[test]
+    fn nodes_fall_within_their_rules() {
+        let src = r#"
+            value = { "<&\"'>" ~ item{2} }
+            item = { "a" }
+        "#;
+        let out = render_test(src, &Config::default(), Format::JsonDiagram);
+        let geometry: Value = serde_json::from_slice(&out).unwrap();
+        let rules = geometry["rules"].as_array().unwrap();
+        assert_eq!(rules.len(), 2);
+
+        // Labels are read back from the SVG without its escaping
+        let nodes = rules[0]["nodes"].as_array().unwrap();
+        let labels: Vec<_> = nodes
+            .iter()
+            .map(|node| {
+                (
+                    node["kind"].as_str().unwrap(),
+                    node["label"].as_str().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            labels,
+            [("terminal", r#""<&\"'>""#), ("nonterminal", "item")]
+        );
+        // The bounds of the repeat are a label on its loop rather than a frame
+        let labels: Vec<_> = rules[0]["labels"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|label| label["text"].as_str().unwrap())
+            .collect();
+        assert!(labels.contains(&"2×"), "{labels:?}");
+        assert!(rules[0]["frames"].as_array().unwrap().is_empty());
+
+        for rule in rules {
+            let num = |value: &Value, key| value[key].as_i64().unwrap();
+            let rows = num(rule, "y")..=num(rule, "y") + num(rule, "height");
+            for node in rule["nodes"].as_array().unwrap() {
+                assert!(rows.contains(&num(node, "y")));
+                assert!(rows.contains(&(num(node, "y") + num(node, "height"))));
+            }
+            assert!(!rule["connections"].as_array().unwrap().is_empty());
+        }
+        assert!(geometry["nodes"].as_array().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,279 @@
+use std::io;
+
+use railroad::svg;
+
+use crate::{
+    backend::{single_rule, Backend},
+    config::Config,
+    diagram,
+    ir::{Grammar, GrammarRule},
+    syntax, Error,
+};
+
+/// Lays out the table of contents as a sidebar next to the diagram
+const TOC_CSS: &str = "
+.toc-layout { display: flex; align-items: flex-start; gap: 2em; }
+.toc { position: sticky; top: 0; max-height: 100vh; overflow-y: auto; }
+.toc ul { list-style: none; padding: 0; }
+.toc-search { width: 100%; box-sizing: border-box; }
+";
+
+/// Hides the rules (in the table of contents and the diagrams) that don't match the filter box
+const SEARCH_SCRIPT: &str = "
+document.querySelector('.toc-search').addEventListener('input', function () {
+  const query = this.value.trim().toLowerCase();
+  document.querySelectorAll('[data-search]').forEach(function (element) {
+    element.hidden = !element.dataset.search.includes(query);
+  });
+});
+";
+
+/// Styles the references that expand, and the diagrams they expand into
+const EXPAND_CSS: &str = "
+[data-expand] { cursor: pointer; }
+[data-expand].expanded rect { stroke-dasharray: 6 3; }
+.expansion { margin: 0.5em 0 0.5em 1.5em; padding-left: 1em; border-left: 3px solid hsl(210, 60%, 70%); }
+";
+
+/// Expands a clicked rule reference into a copy of the rule's diagram under the diagram it's in, or collapses it again
+const EXPAND_SCRIPT: &str = "
+document.querySelectorAll('[data-expand]').forEach(function (node) {
+  // Only the rules on the page can be expanded
+  if (!document.getElementById(node.dataset.expand)) {
+    node.removeAttribute('data-expand');
+  }
+});
+document.addEventListener('click', function (event) {
+  const node = event.target.closest('[data-expand]');
+  if (!node) {
+    return;
+  }
+  event.preventDefault();
+  const rule = node.dataset.expand;
+  const expansions = node.closest('.expandable').querySelector(':scope > .expansions');
+  const open = Array.from(expansions.children).find(function (child) {
+    return child.dataset.rule === rule;
+  });
+  if (open) {
+    open.remove();
+    node.classList.remove('expanded');
+    return;
+  }
+
+  // The diagram is only copied once the reference is expanded
+  const diagram = document.getElementById(rule).querySelector('svg').cloneNode(true);
+  diagram.querySelectorAll('.expanded').forEach(function (expanded) {
+    expanded.classList.remove('expanded');
+  });
+  const expansion = document.createElement('div');
+  expansion.className = 'expansion expandable';
+  expansion.dataset.rule = rule;
+  const nested = document.createElement('div');
+  nested.className = 'expansions';
+  expansion.append(diagram, nested);
+  expansions.append(expansion);
+  node.classList.add('expanded');
+});
+";
+
+pub(crate) struct HtmlBackend;
+
+impl HtmlBackend {
+    fn write_toc(
+        &self,
+        grammar: &Grammar,
+        config: &Config,
+        out: &mut dyn io::Write,
+    ) -> io::Result<()> {
+        writeln!(out, "<nav class=\"toc\">")?;
+        writeln!(out, "<h2>Rules</h2>")?;
+        if config.search {
+            writeln!(
+                out,
+                "<input type=\"search\" class=\"toc-search\" placeholder=\"Filter rules\">"
+            )?;
+        }
+        writeln!(out, "<ul>")?;
+        for rule in &grammar.rules {
+            // Rule names are identifiers, so only the docs need encoding
+            let label = config.label(rule);
+            let id = config.id(&rule.name);
+            let search = search_attr(rule, config);
+            match rule.docs.first() {
+                Some(doc) => writeln!(
+                    out,
+                    "<li{search}><a href=\"#{id}\" title=\"{}\">{label}</a></li>",
+                    svg::encode_minimal(doc)
+                )?,
+                None => writeln!(out, "<li{search}><a href=\"#{id}\">{label}</a></li>")?,
+            }
+        }
+        writeln!(out, "</ul>")?;
+        writeln!(out, "</nav>")
+    }
+
+    /// Writes the diagrams of the page: one of each rule (along with its source, if included), or one of the whole
+    /// grammar if combined
+    fn write_body(
+        &self,
+        grammar: &Grammar,
+        config: &Config,
+        out: &mut dyn io::Write,
+    ) -> Result<(), Error> {
+        if !config.combined || config.include_source || config.search || config.expandable {
+            self.write_rules(grammar, config, out)
+        } else {
+            self.write_diagram(grammar, config, out)
+        }
+    }
+
+    /// Writes the SVG diagram of a grammar
+    fn write_diagram(
+        &self,
+        grammar: &Grammar,
+        config: &Config,
+        out: &mut dyn io::Write,
+    ) -> Result<(), Error> {
+        let diagram = diagram::make_diagram(
+            grammar,
+            config,
+            config.theme.stylesheet(),
+            config.theme.dark_stylesheet(),
+        )?;
+        writeln!(out, "{}", diagram::to_svg(&diagram, config))?;
+        Ok(())
+    }
+
+    /// Writes a section for each rule, holding its diagram (followed by its source, if included), and then a diagram of
+    /// any embedded warnings
+    fn write_rules(
+        &self,
+        grammar: &Grammar,
+        config: &Config,
+        out: &mut dyn io::Write,
+    ) -> Result<(), Error> {
+        let mut rule_config = Config {
+            // The grammar title belongs to the page, not each diagram
+            title: None,
+            warnings: Vec::new(),
+            legend: false,
+            // The sections are the targets of links to the rules instead
+            anchors: false,
+            toc: false,
+            footer: None,
+            ..config.clone()
+        };
+        for rule in &grammar.rules {
+            writeln!(
+                out,
+                "<section id=\"{}\"{}>",
+                config.id(&rule.name),
+                search_attr(rule, config)
+            )?;
+            if config.expandable {
+                // The diagrams of the expanded references go under the diagram
+                writeln!(out, "<div class=\"expandable\">")?;
+                self.write_diagram(&single_rule(rule), &rule_config, out)?;
+                writeln!(out, "<div class=\"expansions\"></div>")?;
+                writeln!(out, "</div>")?;
+            } else {
+                self.write_diagram(&single_rule(rule), &rule_config, out)?;
+            }
+            // Fonts embedded in one diagram are available to the whole page
+            rule_config.embed_fonts = false;
+            if config.include_source {
+                writeln!(out, "<details class=\"source\">")?;
+                writeln!(out, "<summary>Source</summary>")?;
+                writeln!(
+                    out,
+                    "<pre><code>{}</code></pre>",
+                    syntax::highlight(&rule.source)
+                )?;
+                writeln!(out, "</details>")?;
+            }
+            writeln!(out, "</section>")?;
+        }
+
+        // The legend, warnings and footer are drawn once, after all the rules
+        if config.legend || !config.warnings.is_empty() || config.footer.is_some() {
+            let no_rules = Grammar {
+                docs: Vec::new(),
+                rules: Vec::new(),
+            };
+            let warnings_config = Config {
+                title: None,
+                embed_fonts: rule_config.embed_fonts,
+                ..config.clone()
+            };
+            self.write_diagram(&no_rules, &warnings_config, out)?;
+        }
+        Ok(())
+    }
+}
+
+/// The attribute holding the text the filter box matches a rule against (its name and doc comments), if there is one
+fn search_attr(rule: &GrammarRule, config: &Config) -> String {
+    if !config.search {
+        return String::new();
+    }
+    let text = std::iter::once(&rule.name)
+        .chain(&rule.docs)
+        .map(|text| text.to_lowercase())
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!(" data-search=\"{}\"", svg::encode_minimal(&text))
+}
+
+impl Backend for HtmlBackend {
+    fn extension(&self) -> &'static str {
+        "html"
+    }
+
+    fn render(
+        &self,
+        grammar: &Grammar,
+        config: &Config,
+        out: &mut dyn io::Write,
+    ) -> Result<(), Error> {
+        writeln!(out, "<!DOCTYPE html>")?;
+        writeln!(out, "<html>")?;
+        writeln!(out, "<head>")?;
+        writeln!(out, "<meta charset=\"utf-8\">")?;
+        let title = config.title(grammar).unwrap_or("Railroad diagram");
+        let title = svg::encode_minimal(title);
+
+        writeln!(out, "<title>{title}</title>")?;
+        let toc = config.toc || config.search;
+        if toc {
+            writeln!(out, "<style>{TOC_CSS}</style>")?;
+        }
+        if config.include_source {
+            writeln!(out, "<style>{}</style>", syntax::SYNTAX_CSS)?;
+        }
+        if config.expandable {
+            writeln!(out, "<style>{EXPAND_CSS}</style>")?;
+        }
+        writeln!(out, "</head>")?;
+        writeln!(out, "<body>")?;
+        writeln!(out, "<h1>{title}</h1>")?;
+        if toc {
+            writeln!(out, "<div class=\"toc-layout\">")?;
+            self.write_toc(grammar, config, out)?;
+            writeln!(out, "<main>")?;
+            self.write_body(grammar, config, out)?;
+            writeln!(out, "</main>")?;
+            writeln!(out, "</div>")?;
+        } else {
+            self.write_body(grammar, config, out)?;
+        }
+        if config.search {
+            writeln!(out, "<script>{SEARCH_SCRIPT}</script>")?;
+        }
+        if config.expandable {
+            writeln!(out, "<script>{EXPAND_SCRIPT}</script>")?;
+        }
+        writeln!(out, "</body>")?;
+        writeln!(out, "</html>")?;
+        Ok(())
+    }
+}
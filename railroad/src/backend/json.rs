@@ -0,0 +1,22 @@
+use std::io;
+
+use crate::{backend::Backend, config::Config, ir::Grammar, Error};
+
+pub(crate) struct JsonBackend;
+
+impl Backend for JsonBackend {
+    fn extension(&self) -> &'static str {
+        "json"
+    }
+
+    fn render(
+        &self,
+        grammar: &Grammar,
+        _config: &Config,
+        out: &mut dyn io::Write,
+    ) -> Result<(), Error> {
+        serde_json::to_writer_pretty(&mut *out, grammar).map_err(io::Error::from)?;
+        writeln!(out)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,93 @@
+use std::io;
+
+use crate::{
+    backend::{single_rule, Backend},
+    config::Config,
+    diagram,
+    ir::{Grammar, GrammarRule},
+    Error,
+};
+
+pub(crate) struct MarkdownBackend;
+
+impl MarkdownBackend {
+    /// Writes the diagram of a rule: an image link if the config has an image template, or else the SVG inlined as raw
+    /// HTML
+    fn write_diagram(
+        &self,
+        rule: &GrammarRule,
+        config: &Config,
+        out: &mut dyn io::Write,
+    ) -> Result<(), Error> {
+        if let Some(src) = config.image(&rule.name) {
+            writeln!(out, "![Railroad diagram of {}]({src})", rule.name)?;
+            return Ok(());
+        }
+
+        // The doc comments are already in the text
+        let mut grammar = single_rule(rule);
+        grammar.rules[0].docs.clear();
+        let diagram = diagram::make_diagram(
+            &grammar,
+            config,
+            config.theme.stylesheet(),
+            config.theme.dark_stylesheet(),
+        )?;
+        // A blank line would end the raw HTML block
+        for line in diagram::to_svg(&diagram, config).lines() {
+            if !line.trim().is_empty() {
+                writeln!(out, "{line}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Backend for MarkdownBackend {
+    fn extension(&self) -> &'static str {
+        "md"
+    }
+
+    fn render(
+        &self,
+        grammar: &Grammar,
+        config: &Config,
+        out: &mut dyn io::Write,
+    ) -> Result<(), Error> {
+        let mut rule_config = Config {
+            // The title is the heading of the document instead
+            title: None,
+            warnings: Vec::new(),
+            legend: false,
+            ..config.clone()
+        };
+
+        if let Some(title) = config.title(grammar) {
+            writeln!(out, "# {title}")?;
+            writeln!(out)?;
+        }
+        for rule in &grammar.rules {
+            writeln!(out, "## {}", config.label(rule))?;
+            writeln!(out)?;
+            if !rule.docs.is_empty() {
+                for doc in &rule.docs {
+                    writeln!(out, "{}", doc.trim())?;
+                }
+                writeln!(out)?;
+            }
+            self.write_diagram(rule, &rule_config, out)?;
+            // Fonts embedded in one diagram are available to the whole document
+            rule_config.embed_fonts = false;
+            writeln!(out)?;
+        }
+
+        if !config.warnings.is_empty() {
+            writeln!(out, "## Warnings")?;
+            writeln!(out)?;
+            for warning in &config.warnings {
+                writeln!(out, "- {warning}")?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,128 @@
+use std::io;
+
+use crate::{
+    backend::{
+        flowchart::{Flowchart, Shape},
+        Backend,
+    },
+    config::Config,
+    ir::Grammar,
+    Error,
+};
+
+/// Escapes text for a quoted Mermaid label, which uses entity codes in place of HTML escapes
+fn escape(text: &str) -> String {
+    text.replace('#', "#35;")
+        .replace('"', "#quot;")
+        .replace('<', "#lt;")
+        .replace('>', "#gt;")
+}
+
+pub(crate) struct MermaidBackend;
+
+impl Backend for MermaidBackend {
+    fn extension(&self) -> &'static str {
+        "mmd"
+    }
+
+    fn render(
+        &self,
+        grammar: &Grammar,
+        config: &Config,
+        out: &mut dyn io::Write,
+    ) -> Result<(), Error> {
+        if let Some(title) = config.title(grammar) {
+            writeln!(out, "---")?;
+            writeln!(
+                out,
+                "title: \"{}\"",
+                title.replace('\\', "\\\\").replace('"', "\\\"")
+            )?;
+            writeln!(out, "---")?;
+        }
+        writeln!(out, "flowchart LR")?;
+
+        // Mermaid reserves some words (such as `end`) that are also valid rule names, so the ids are numbered and the
+        // rule names are only used as labels
+        for (rule_idx, rule) in grammar.rules.iter().enumerate() {
+            let chart = Flowchart::new(&rule.expr);
+            let id = |node: usize| format!("r{rule_idx}n{node}");
+
+            writeln!(
+                out,
+                "    subgraph r{rule_idx} [\"{}\"]",
+                escape(&config.label(rule))
+            )?;
+            writeln!(out, "        direction LR")?;
+            for (idx, (shape, label)) in chart.nodes.iter().enumerate() {
+                let label = escape(label);
+                let id = id(idx);
+                match shape {
+                    Shape::Circle => writeln!(out, "        {id}((\" \"))")?,
+                    Shape::Rect => writeln!(out, "        {id}[\"{label}\"]")?,
+                    Shape::Rounded => writeln!(out, "        {id}(\"{label}\")")?,
+                    Shape::Hexagon => writeln!(out, "        {id}{{{{\"{label}\"}}}}")?,
+                }
+            }
+            for (from, to, label) in &chart.edges {
+                match label {
+                    Some(label) => writeln!(
+                        out,
+                        "        {} -->|\"{}\"| {}",
+                        id(*from),
+                        escape(label),
+                        id(*to)
+                    )?,
+                    None => writeln!(out, "        {} --> {}", id(*from), id(*to))?,
+                }
+            }
+            writeln!(out, "    end")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{render_test, Format};
+
+    #[test]
+    fn golden() {
+        let src = r##"
+            //! The "quoted" grammar
+            end = { "<" ~ ("#" | "\"")+ ~ !">" ~ end{1,2} }
+        "##;
+        let out = String::from_utf8(render_test(src, &Config::default(), Format::Mermaid)).unwrap();
+        assert_eq!(
+            out,
+            r##"---
+title: "The \"quoted\" grammar"
+---
+flowchart LR
+    subgraph r0 ["end"]
+        direction LR
+        r0n0((" "))
+        r0n1((" "))
+        r0n2("#quot;#lt;#quot;")
+        r0n3("#quot;#35;#quot;")
+        r0n4("#quot;\#quot;#quot;")
+        r0n5{{"!#quot;#gt;#quot;"}}
+        r0n6["end"]
+        r0n3 --> r0n3
+        r0n3 --> r0n4
+        r0n4 --> r0n3
+        r0n4 --> r0n4
+        r0n2 --> r0n3
+        r0n2 --> r0n4
+        r0n3 --> r0n5
+        r0n4 --> r0n5
+        r0n6 -->|"{1,2}"| r0n6
+        r0n5 --> r0n6
+        r0n0 --> r0n2
+        r0n6 --> r0n1
+    end
+"##
+        );
+    }
+}
@@ -0,0 +1,229 @@
+use std::{collections::HashMap, io};
+
+use pdf_writer::{Content, Finish, Name, Pdf, Rect, Ref, Str};
+use svg2pdf::{usvg, ConversionOptions, PageOptions};
+
+use crate::{
+    backend::{single_rule, vector, Backend},
+    config::Config,
+    ir::Grammar,
+    Error,
+};
+
+/// The margin around the header and diagram of a paginated page, in points
+const MARGIN: f32 = 36.0;
+
+/// The font size of the header of a paginated page, in points
+const HEADER_SIZE: f32 = 16.0;
+
+/// The space between the header of a paginated page and its diagram, in points
+const HEADER_GAP: f32 = 12.0;
+
+pub(crate) struct PdfBackend;
+
+impl PdfBackend {
+    /// Writes a PDF with a page of its own for the diagram of each rule under a header with its name, on pages of the
+    /// config's page size or else sized to fit each diagram
+    fn render_pages(
+        &self,
+        grammar: &Grammar,
+        config: &Config,
+        options: &usvg::Options,
+    ) -> Result<Vec<u8>, Error> {
+        let mut alloc = Ref::new(1);
+        let catalog_id = alloc.bump();
+        let page_tree_id = alloc.bump();
+        let font_id = alloc.bump();
+        let font_name = Name(b"F1");
+        let mut pdf = Pdf::new();
+        // Headers use a standard font, which every PDF reader has
+        pdf.type1_font(font_id)
+            .base_font(Name(b"Helvetica-Bold"))
+            .encoding_predefined(Name(b"WinAnsiEncoding"));
+        let mut page_ids = Vec::with_capacity(grammar.rules.len());
+
+        let rule_config = Config {
+            warnings: Vec::new(),
+            legend: false,
+            ..config.clone()
+        };
+        // Any legend and embedded warnings get a page of their own at the end
+        let last_page = Grammar {
+            docs: Vec::new(),
+            rules: Vec::new(),
+        };
+        let pages = grammar
+            .rules
+            .iter()
+            .map(|rule| (config.label(rule), single_rule(rule), &rule_config))
+            .chain((config.legend || !config.warnings.is_empty()).then(|| {
+                let header = if config.warnings.is_empty() {
+                    "Legend"
+                } else if config.legend {
+                    "Legend and warnings"
+                } else {
+                    "Warnings"
+                };
+                (header.to_string(), last_page, config)
+            }));
+
+        for (header, page_grammar, page_config) in pages {
+            let tree = vector::tree(&page_grammar, page_config, options)?;
+            let (chunk, svg_id) = svg2pdf::to_chunk(&tree, ConversionOptions::default())
+                .map_err(|err| Error::Render(err.to_string()))?;
+            // The chunk numbers its objects from 1, so move them after the ones already written
+            let mut ids = HashMap::new();
+            let chunk = chunk.renumber(|old| *ids.entry(old).or_insert_with(|| alloc.bump()));
+            // Panic safety: The chunk contains the diagram's object
+            let svg_id = ids[&svg_id];
+
+            let page_id = alloc.bump();
+            let content_id = alloc.bump();
+            let svg_name = Name(b"D");
+            let size = tree.size();
+            let (width, height) = (size.width(), size.height());
+            let header_height = HEADER_SIZE + HEADER_GAP;
+
+            // Diagrams are only ever scaled down, so their text stays the same size on every page that fits them
+            let (page_width, page_height, scale) = match config.page_size {
+                Some(page_size) => {
+                    let (short, long) = page_size.size();
+                    let (page_width, page_height) = if width > height {
+                        (long, short)
+                    } else {
+                        (short, long)
+                    };
+                    let scale = ((page_width - 2.0 * MARGIN) / width)
+                        .min((page_height - 2.0 * MARGIN - header_height) / height)
+                        .min(1.0);
+                    (page_width, page_height, scale)
+                }
+                None => (
+                    width + 2.0 * MARGIN,
+                    height + 2.0 * MARGIN + header_height,
+                    1.0,
+                ),
+            };
+
+            let mut page = pdf.page(page_id);
+            page.media_box(Rect::new(0.0, 0.0, page_width, page_height));
+            page.parent(page_tree_id);
+            page.contents(content_id);
+            let mut resources = page.resources();
+            resources.x_objects().pair(svg_name, svg_id);
+            resources.fonts().pair(font_name, font_id);
+            resources.finish();
+            page.finish();
+
+            // The header is at the top left, with the diagram under it (PDF coordinates start at the bottom left)
+            let top = page_height - MARGIN;
+            let mut content = Content::new();
+            content
+                .begin_text()
+                .set_font(font_name, HEADER_SIZE)
+                .next_line(MARGIN, top - HEADER_SIZE)
+                .show(Str(header.as_bytes()))
+                .end_text();
+            content
+                .save_state()
+                .transform([
+                    width * scale,
+                    0.0,
+                    0.0,
+                    height * scale,
+                    MARGIN,
+                    top - header_height - height * scale,
+                ])
+                .x_object(svg_name)
+                .restore_state();
+            pdf.stream(content_id, &content.finish());
+            pdf.extend(&chunk);
+            page_ids.push(page_id);
+        }
+
+        pdf.catalog(catalog_id).pages(page_tree_id);
+        pdf.pages(page_tree_id)
+            .kids(page_ids.iter().copied())
+            .count(page_ids.len() as i32);
+        Ok(pdf.finish())
+    }
+}
+
+impl Backend for PdfBackend {
+    fn extension(&self) -> &'static str {
+        "pdf"
+    }
+
+    fn render(
+        &self,
+        grammar: &Grammar,
+        config: &Config,
+        out: &mut dyn io::Write,
+    ) -> Result<(), Error> {
+        let options = vector::parse_options();
+        let pdf = if config.paginate {
+            self.render_pages(grammar, config, &options)?
+        } else {
+            let tree = vector::tree(grammar, config, &options)?;
+            svg2pdf::to_pdf(&tree, ConversionOptions::default(), PageOptions::default())
+                .map_err(|err| Error::Render(err.to_string()))?
+        };
+
+        out.write_all(&pdf)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        backend::{render_test, Format},
+        config::PageSize,
+    };
+
+    const GRAMMAR: &str = r#"
+        string = { "\"" ~ (!"\"" ~ ANY)* ~ "\"" }
+        pair = { string ~ ":" ~ "<&>" }
+    "#;
+
+    /// Returns true if the PDF contains `text`
+    fn contains(pdf: &[u8], text: &str) -> bool {
+        pdf.windows(text.len())
+            .any(|window| window == text.as_bytes())
+    }
+
+    #[test]
+    fn single_page() {
+        let pdf = render_test(GRAMMAR, &Config::default(), Format::Pdf);
+        assert!(pdf.starts_with(b"%PDF-"));
+        assert!(contains(&pdf, "/Count 1"));
+    }
+
+    #[test]
+    fn page_per_rule() {
+        let config = Config {
+            paginate: true,
+            ..Config::default()
+        };
+        let pdf = render_test(GRAMMAR, &config, Format::Pdf);
+        assert!(pdf.starts_with(b"%PDF-"));
+        assert!(contains(&pdf, "/Count 2"));
+    }
+
+    #[test]
+    fn headers_and_page_size() {
+        let config = Config {
+            paginate: true,
+            page_size: Some(PageSize::Letter),
+            numbering: true,
+            ..Config::default()
+        };
+        let pdf = render_test(GRAMMAR, &config, Format::Pdf);
+        assert!(contains(&pdf, "(1. string) Tj"));
+        assert!(contains(&pdf, "(2. pair) Tj"));
+        // Wide diagrams are laid out on landscape pages
+        let pdf = String::from_utf8_lossy(&pdf);
+        assert_eq!(pdf.matches("/MediaBox [0 0 792 612]").count(), 2);
+    }
+}
@@ -0,0 +1,91 @@
+use std::io;
+
+use railroad::{
+    render::{self, FitTo},
+    Node,
+};
+
+use crate::{
+    backend::{single_rule, Backend},
+    config::Config,
+    diagram,
+    ir::Grammar,
+    Error,
+};
+
+/// The box a thumbnail is scaled to fit in, in pixels
+const THUMBNAIL_SIZE: (f64, f64) = (640.0, 320.0);
+
+/// Rasterizes the diagram of a grammar into a PNG as wide as `raster_width` gives for the diagram's width and height,
+/// scaled down to the config's maximum number of pixels
+fn to_png(
+    grammar: &Grammar,
+    config: &Config,
+    raster_width: impl Fn(i64, i64) -> u32,
+) -> Result<Vec<u8>, Error> {
+    // The regular stylesheets use CSS that resvg can't render
+    let diagram =
+        diagram::make_diagram(grammar, config, config.theme.render_safe_stylesheet(), None)?;
+
+    let (width, height) = (diagram.width(), diagram.height());
+    let raster_width = config.clamp_raster_width(raster_width(width, height), width, height);
+    render::to_png(&diagram.to_string(), &FitTo::MaxWidth(raster_width))
+        .map_err(|err| Error::Render(format!("{err:?}")))
+}
+
+pub(crate) struct PngBackend;
+
+impl Backend for PngBackend {
+    fn extension(&self) -> &'static str {
+        "png"
+    }
+
+    fn render(
+        &self,
+        grammar: &Grammar,
+        config: &Config,
+        out: &mut dyn io::Write,
+    ) -> Result<(), Error> {
+        let png = to_png(grammar, config, |width, _| config.raster_size.width(width))?;
+        out.write_all(&png)?;
+        Ok(())
+    }
+}
+
+/// A small overview image of the entry rule (the start rule, or else the first rule), for README headers
+pub(crate) struct ThumbnailBackend;
+
+impl Backend for ThumbnailBackend {
+    fn extension(&self) -> &'static str {
+        "png"
+    }
+
+    fn render(
+        &self,
+        grammar: &Grammar,
+        config: &Config,
+        out: &mut dyn io::Write,
+    ) -> Result<(), Error> {
+        let rule = config
+            .start_rule
+            .as_ref()
+            .and_then(|start| grammar.rules.iter().find(|rule| &rule.name == start))
+            .or(grammar.rules.first())
+            .ok_or_else(|| Error::Render("the grammar has no rules to draw".to_string()))?;
+        let rule_config = Config {
+            title: None,
+            warnings: Vec::new(),
+            legend: false,
+            ..config.clone()
+        };
+
+        // Every thumbnail fills the same box, whatever the size of the rule
+        let (box_width, box_height) = THUMBNAIL_SIZE;
+        let png = to_png(&single_rule(rule), &rule_config, |width, height| {
+            let scale = (box_width / width as f64).min(box_height / height as f64);
+            (width as f64 * scale).round().max(1.0) as u32
+        })?;
+        out.write_all(&png)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,198 @@
+use std::io;
+
+use crate::{
+    backend::Backend,
+    config::Config,
+    diagram,
+    ir::{Expr, Grammar},
+    Error,
+};
+
+/// The width a call is kept on one line within
+const WIDTH: usize = 100;
+
+/// A JavaScript expression building part of a diagram with the railroad-diagrams functions
+enum Js {
+    /// A string, number or object literal
+    Literal(String),
+    Call(&'static str, Vec<Js>),
+}
+
+impl Js {
+    fn string(text: &str) -> Js {
+        // JSON strings are valid JavaScript strings
+        Js::Literal(serde_json::to_string(text).unwrap_or_default())
+    }
+
+    fn new(expr: &Expr, config: &Config) -> Js {
+        let all = |exprs: &[Expr]| exprs.iter().map(|expr| Js::new(expr, config)).collect();
+
+        match expr {
+            Expr::Choice { choices } => {
+                let mut args = vec![Js::Literal("0".to_string())];
+                args.extend(all(choices));
+                Js::Call("Choice", args)
+            }
+            Expr::Sequence { terms } if terms.is_empty() => Js::Call("Skip", Vec::new()),
+            Expr::Sequence { terms } => Js::Call("Sequence", all(terms)),
+            Expr::Ident { name, .. } => {
+                let mut args = vec![Js::string(name)];
+                if let Some(href) = config.link(name) {
+                    args.push(Js::Literal(format!(
+                        "{{href: {}}}",
+                        serde_json::to_string(&href).unwrap_or_default()
+                    )));
+                }
+                Js::Call("NonTerminal", args)
+            }
+            Expr::Str { value, .. } => {
+                Js::Call("Terminal", vec![Js::string(&format!("\"{value}\""))])
+            }
+            Expr::InsensitiveStr { value, .. } => {
+                Js::Call("Terminal", vec![Js::string(&format!("^\"{value}\""))])
+            }
+            Expr::Range { start, end, .. } => {
+                Js::Call("Terminal", vec![Js::string(&format!("'{start}'..'{end}'"))])
+            }
+            Expr::Optional { expr } => Js::Call("Optional", vec![Js::new(expr, config)]),
+            Expr::Repeat { expr } => Js::Call("ZeroOrMore", vec![Js::new(expr, config)]),
+            Expr::RepeatOnce { expr } => Js::Call("OneOrMore", vec![Js::new(expr, config)]),
+            Expr::RepeatRange { expr, min, max } => {
+                let repeat = if *min > 0 { "OneOrMore" } else { "ZeroOrMore" };
+                Js::Call(
+                    "Group",
+                    vec![
+                        Js::Call(repeat, vec![Js::new(expr, config)]),
+                        Js::string(&diagram::repeat_label(*min, *max)),
+                    ],
+                )
+            }
+            Expr::Push { expr } => {
+                Js::Call("Group", vec![Js::new(expr, config), Js::string("PUSH")])
+            }
+            Expr::PeekSlice { start, end, .. } => Js::Call(
+                "NonTerminal",
+                vec![Js::string(&diagram::peek_slice(*start, *end))],
+            ),
+            Expr::PositivePredicate { .. } | Expr::NegativePredicate { .. } => {
+                let (inner, label) = diagram::lookahead(expr);
+                let inner = Js::new(inner, config);
+                match label {
+                    Some(label) => Js::Call("Group", vec![inner, Js::string(label)]),
+                    None => inner,
+                }
+            }
+        }
+    }
+
+    /// The expression on a single line
+    fn inline(&self) -> String {
+        match self {
+            Js::Literal(text) => text.clone(),
+            Js::Call(name, args) => format!(
+                "{name}({})",
+                args.iter().map(Js::inline).collect::<Vec<_>>().join(", ")
+            ),
+        }
+    }
+
+    /// The expression indented by `indent` spaces, with calls that don't fit on a line split over several
+    fn pretty(&self, indent: usize) -> String {
+        let inline = self.inline();
+        match self {
+            Js::Call(name, args) if indent + inline.len() > WIDTH => {
+                let args = args
+                    .iter()
+                    .map(|arg| format!("{}{}", " ".repeat(indent + 2), arg.pretty(indent + 2)))
+                    .collect::<Vec<_>>()
+                    .join(",\n");
+                format!("{name}(\n{args}\n{})", " ".repeat(indent))
+            }
+            _ => inline,
+        }
+    }
+}
+
+pub(crate) struct RailroadJsBackend;
+
+impl Backend for RailroadJsBackend {
+    fn extension(&self) -> &'static str {
+        "js"
+    }
+
+    fn render(
+        &self,
+        grammar: &Grammar,
+        config: &Config,
+        out: &mut dyn io::Write,
+    ) -> Result<(), Error> {
+        writeln!(
+            out,
+            "// Railroad diagrams for the railroad-diagrams library (https://github.com/tabatkins/railroad-diagrams)."
+        )?;
+        writeln!(
+            out,
+            "// With its functions in scope, `rules[\"name\"].addTo(element)` draws the diagram of a rule."
+        )?;
+        if let Some(title) = config.title(grammar) {
+            writeln!(out, "// {title}")?;
+        }
+        writeln!(out, "const rules = {{")?;
+        for rule in &grammar.rules {
+            for doc in &rule.docs {
+                writeln!(out, "  // {}", doc.trim())?;
+            }
+            if let Some(label) = rule.modifier.label() {
+                writeln!(out, "  // {label} rule")?;
+            }
+            let diagram = Js::Call("Diagram", vec![Js::new(&rule.expr, config)]);
+            writeln!(
+                out,
+                "  {}: {},",
+                serde_json::to_string(&rule.name).unwrap_or_default(),
+                diagram.pretty(2)
+            )?;
+        }
+        writeln!(out, "}};")?;
+        for warning in &config.warnings {
+            writeln!(out, "// Warning: {warning}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{render_test, Format};
+
+    #[test]
+    fn golden() {
+        let src = r#"
+            value = { "\"" ~ ("\\" | "</script>")* ~ item{2} ~ !"'" }
+            item = { ^"a" }
+        "#;
+        let config = Config {
+            link_template: Some("https://example.com/{rule}".to_string()),
+            ..Config::default()
+        };
+        let out = String::from_utf8(render_test(src, &config, Format::RailroadJs)).unwrap();
+        assert_eq!(
+            out,
+            r#"// Railroad diagrams for the railroad-diagrams library (https://github.com/tabatkins/railroad-diagrams).
+// With its functions in scope, `rules["name"].addTo(element)` draws the diagram of a rule.
+const rules = {
+  "value": Diagram(
+    Sequence(
+      Terminal("\"\\\"\""),
+      ZeroOrMore(Choice(0, Terminal("\"\\\\\""), Terminal("\"</script>\""))),
+      Group(OneOrMore(NonTerminal("item", {href: "https://example.com/item"})), "Repeat 2 time(s)"),
+      Group(Terminal("\"'\""), "Lookahead: Can't match")
+    )
+  ),
+  "item": Diagram(Terminal("^\"a\"")),
+};
+"#
+        );
+    }
+}
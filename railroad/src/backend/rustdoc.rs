@@ -0,0 +1,104 @@
+use std::io;
+
+use railroad::Node;
+
+use crate::{backend::Backend, config::Config, diagram, ir::Grammar, Error};
+
+/// The stylesheet of diagrams in rustdoc pages, which takes its colors from the variables of the rustdoc theme the
+/// reader picked (falling back to the light theme's colors elsewhere)
+const RUSTDOC_CSS: &str = "
+svg.railroad {
+background-color: var(--code-block-background-color, #f5f5f5);
+border-radius: 6px;
+max-width: 100%;
+height: auto;
+}
+svg.railroad rect.railroad_canvas {
+stroke-width: 0px;
+fill: none;
+}
+svg.railroad path {
+stroke-width: 2px;
+stroke: var(--main-color, black);
+fill: none;
+}
+svg.railroad .continuation circle,
+svg.railroad .continuation-start circle,
+svg.railroad .continuation-end circle {
+fill: var(--main-color, black);
+stroke: none;
+}
+svg.railroad text {
+font: 14px \"Source Code Pro\", monospace;
+text-anchor: middle;
+fill: var(--main-color, black);
+}
+svg.railroad .nonterminal text {
+font-weight: bold;
+}
+svg.railroad a text {
+fill: var(--link-color, #3873ad);
+}
+svg.railroad text.comment {
+font: italic 12px \"Source Code Pro\", monospace;
+}
+svg.railroad rect,
+svg.railroad g.builtin > polygon {
+stroke-width: 2px;
+stroke: var(--main-color, black);
+fill: var(--main-background-color, white);
+}
+svg.railroad g.labeledbox > rect {
+stroke-width: 1px;
+stroke: var(--border-color, grey);
+stroke-dasharray: 5px;
+fill: none;
+}
+";
+
+/// An HTML fragment for including in rustdoc documentation (with `#[doc = include_str!("...")]`), styled to match
+/// the rustdoc theme
+pub(crate) struct RustdocBackend;
+
+impl Backend for RustdocBackend {
+    fn extension(&self) -> &'static str {
+        "html"
+    }
+
+    fn render(
+        &self,
+        grammar: &Grammar,
+        config: &Config,
+        out: &mut dyn io::Write,
+    ) -> Result<(), Error> {
+        // The rustdoc stylesheet replaces the theme, but any extra CSS still comes after it
+        let mut css = vec![RUSTDOC_CSS.to_string()];
+        css.extend(config.css.iter().cloned());
+        let rustdoc_config = Config {
+            css,
+            ..config.clone()
+        };
+        let mut diagram = diagram::make_diagram(grammar, &rustdoc_config, None, None)?;
+        // The diagram is shown at its natural size, unless the page is narrower
+        let (width, height) = (diagram.width(), diagram.height());
+        diagram
+            .attr("width".to_string())
+            .or_insert(width.to_string());
+        diagram
+            .attr("height".to_string())
+            .or_insert(height.to_string());
+
+        writeln!(
+            out,
+            "<div class=\"pest-railroad\" style=\"overflow-x: auto\">"
+        )?;
+        // Rustdoc reads the fragment as Markdown, where a blank line would end the raw HTML block
+        for line in diagram::to_svg(&diagram, &rustdoc_config).lines() {
+            if !line.trim().is_empty() {
+                writeln!(out, "{line}")?;
+            }
+        }
+        writeln!(out, "</div>")?;
+        Ok(())
+    }
+}
@@ -0,0 +1,141 @@
+use std::io;
+
+use railroad::Node;
+
+use crate::{
+    backend::{single_rule, Backend},
+    config::Config,
+    diagram,
+    ir::Grammar,
+    prefix, Error,
+};
+
+/// Splits the stylesheets out of the markup of a diagram, returning the markup without them and the stylesheets
+fn split_styles(markup: &str) -> (String, String) {
+    let (mut rest, mut styles) = (String::new(), String::new());
+    let mut markup = markup;
+    while let Some(start) = markup.find("<style") {
+        let end = markup[start..]
+            .find("</style>")
+            .map_or(markup.len(), |idx| start + idx + "</style>".len());
+        rest.push_str(&markup[..start]);
+        styles.push_str(markup[start..end].trim());
+        styles.push('\n');
+        markup = &markup[end..];
+    }
+    rest.push_str(markup);
+    (rest, styles)
+}
+
+pub(crate) struct SpriteBackend;
+
+impl Backend for SpriteBackend {
+    fn extension(&self) -> &'static str {
+        "svg"
+    }
+
+    fn render(
+        &self,
+        grammar: &Grammar,
+        config: &Config,
+        out: &mut dyn io::Write,
+    ) -> Result<(), Error> {
+        let rule_config = Config {
+            title: None,
+            warnings: Vec::new(),
+            legend: false,
+            id_prefix: None,
+            // The symbols have the ids of the rules instead
+            anchors: false,
+            toc: false,
+            footer: None,
+            ..config.clone()
+        };
+
+        let mut symbols = String::new();
+        let mut stylesheet = None;
+        for rule in &grammar.rules {
+            let diagram = diagram::make_diagram(
+                &single_rule(rule),
+                &rule_config,
+                config.theme.stylesheet(),
+                config.theme.dark_stylesheet(),
+            )?;
+            let svg = diagram.to_string();
+            // The content of the diagram, between its `<svg>` tags
+            let content = svg
+                .find('>')
+                .zip(svg.rfind("</svg>"))
+                .map_or("", |(start, end)| &svg[start + 1..end]);
+
+            // Every diagram has the same stylesheets, which the sprite sheet only needs once
+            let (content, styles) = split_styles(content);
+            stylesheet.get_or_insert(styles);
+            // A symbol is drawn as an `<svg>` with its attributes, so the stylesheets still apply
+            symbols.push_str(&format!(
+                "<symbol id=\"{}\" class=\"railroad\" viewBox=\"0 0 {} {}\">\n<title>{}</title>\n{}</symbol>\n",
+                rule.name,
+                diagram.width(),
+                diagram.height(),
+                rule.name,
+                content.trim_start()
+            ));
+        }
+
+        let mut sprite = String::from(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" xmlns:xlink=\"http://www.w3.org/1999/xlink\">\n",
+        );
+        if let Some(title) = config.title(grammar) {
+            sprite.push_str(&format!(
+                "<title>{}</title>\n",
+                railroad::svg::encode_minimal(title)
+            ));
+        }
+        sprite.push_str(stylesheet.as_deref().unwrap_or_default());
+        sprite.push_str(&symbols);
+        sprite.push_str("</svg>\n");
+
+        let sprite = match &config.id_prefix {
+            Some(prefix) => prefix::prefix_names(&sprite, prefix),
+            None => sprite,
+        };
+        out.write_all(sprite.as_bytes())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{render_test, Format};
+
+    #[test]
+    fn symbol_per_rule() {
+        let src = r#"
+            //! The <quoted> & "escaped" grammar
+            value = { "<&\">" ~ item }
+            item = { "a" }
+        "#;
+        let config = Config {
+            id_prefix: Some("g-".to_string()),
+            ..Config::default()
+        };
+        let out = String::from_utf8(render_test(src, &config, Format::Sprite)).unwrap();
+
+        assert!(out.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(out.contains("<title>The &lt;quoted&gt; &amp; &quot;escaped&quot; grammar</title>"));
+        // The stylesheets are only included once, ahead of the symbols
+        let symbols = out.find("<symbol").unwrap();
+        assert!(out[..symbols].contains("<style"));
+        assert!(!out[symbols..].contains("<style"));
+
+        let ids: Vec<_> = out
+            .split("<symbol id=\"")
+            .skip(1)
+            .map(|rest| &rest[..rest.find('"').unwrap()])
+            .collect();
+        assert_eq!(ids, ["g-value", "g-item"]);
+        assert!(out.contains("&quot;&lt;&amp;\\&quot;&gt;&quot;"));
+        assert!(out.ends_with("</symbol>\n</svg>\n"));
+    }
+}
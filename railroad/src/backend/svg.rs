@@ -0,0 +1,27 @@
+use std::io;
+
+use crate::{backend::Backend, config::Config, diagram, ir::Grammar, Error};
+
+pub(crate) struct SvgBackend;
+
+impl Backend for SvgBackend {
+    fn extension(&self) -> &'static str {
+        "svg"
+    }
+
+    fn render(
+        &self,
+        grammar: &Grammar,
+        config: &Config,
+        out: &mut dyn io::Write,
+    ) -> Result<(), Error> {
+        let diagram = diagram::make_diagram(
+            grammar,
+            config,
+            config.theme.stylesheet(),
+            config.theme.dark_stylesheet(),
+        )?;
+        writeln!(out, "{}", diagram::to_svg(&diagram, config))?;
+        Ok(())
+    }
+}
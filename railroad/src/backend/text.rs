@@ -0,0 +1,399 @@
+use std::{cmp::Ordering, io};
+
+use crate::{
+    backend::Backend,
+    config::Config,
+    diagram::{self, Term},
+    ir::{Expr, Grammar},
+    Error,
+};
+
+/// The box-drawing characters used, and the ASCII characters that replace them when the config asks for plain ASCII
+const ASCII: [(char, char); 7] = [
+    ('─', '-'),
+    ('│', '|'),
+    ('┬', '+'),
+    ('├', '+'),
+    ('┤', '+'),
+    ('╰', '\''),
+    ('╯', '\''),
+];
+
+/// A rectangular block of a text diagram. The line enters on the left and leaves on the right of the same row.
+struct Block {
+    lines: Vec<Vec<char>>,
+    row: usize,
+}
+
+impl Block {
+    fn width(&self) -> usize {
+        self.lines.first().map_or(0, Vec::len)
+    }
+
+    fn height(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// A single line of text, such as a terminal or rule reference
+    fn text(text: &str) -> Block {
+        Block {
+            lines: vec![text.chars().collect()],
+            row: 0,
+        }
+    }
+
+    /// Returns the block widened to `width`, extending its line to the right edge
+    fn pad(mut self, width: usize) -> Block {
+        let row = self.row;
+        for (idx, line) in self.lines.iter_mut().enumerate() {
+            let fill = if idx == row { '─' } else { ' ' };
+            line.resize(width.max(line.len()), fill);
+        }
+        self
+    }
+
+    /// Blocks one after another, with their lines lined up and joined
+    fn sequence(blocks: Vec<Block>) -> Block {
+        let row = blocks.iter().map(|block| block.row).max().unwrap_or(0);
+        let below = blocks
+            .iter()
+            .map(|block| block.height() - block.row)
+            .max()
+            .unwrap_or(1);
+
+        let mut lines = vec![Vec::new(); row + below];
+        for (idx, block) in blocks.iter().enumerate() {
+            let offset = row - block.row;
+            for (line_idx, line) in lines.iter_mut().enumerate() {
+                if idx > 0 {
+                    let join = if line_idx == row { '─' } else { ' ' };
+                    line.extend([join, join]);
+                }
+                match line_idx
+                    .checked_sub(offset)
+                    .and_then(|idx| block.lines.get(idx))
+                {
+                    Some(block_line) => line.extend(block_line),
+                    None => line.extend(std::iter::repeat_n(' ', block.width())),
+                }
+            }
+        }
+        Block { lines, row }
+    }
+
+    /// Branches stacked on top of each other, with rails on either side joining their lines. The line enters and leaves
+    /// through the first branch.
+    fn choice(mut branches: Vec<Block>) -> Block {
+        if branches.len() == 1 {
+            return branches.remove(0);
+        }
+        let width = branches.iter().map(Block::width).max().unwrap_or(0);
+        let last = branches.len() - 1;
+
+        // The rails run from the line of the first branch to the line of the last
+        let mut lines = Vec::new();
+        let mut rail = false;
+        let row = branches[0].row;
+        for (idx, branch) in branches.into_iter().enumerate() {
+            let branch_row = branch.row;
+            for (line_idx, line) in branch.pad(width).lines.into_iter().enumerate() {
+                let (left, right, fill) = if line_idx == branch_row {
+                    rail = idx != last;
+                    match idx {
+                        0 => ('┬', '┬', '─'),
+                        _ if idx == last => ('╰', '╯', '─'),
+                        _ => ('├', '┤', '─'),
+                    }
+                } else if rail {
+                    ('│', '│', ' ')
+                } else {
+                    (' ', ' ', ' ')
+                };
+                let mut full = vec![left, fill];
+                full.extend(line);
+                full.extend([fill, right]);
+                lines.push(full);
+            }
+        }
+        Block { lines, row }
+    }
+
+    /// A block with a line looping back underneath it from its right to its left, labeled with the number of times
+    /// it repeats
+    fn repeat(self, label: &str) -> Block {
+        self.repeat_through(Block::text(label))
+    }
+
+    /// A block with a line looping back underneath it from its right to its left, through another block (such as the
+    /// separator of a list)
+    fn repeat_through(self, back: Block) -> Block {
+        let width = self.width().max(back.width() + 2);
+        let row = self.row;
+        let height = self.height();
+
+        let mut lines = Vec::with_capacity(height + 1);
+        for (idx, line) in self.pad(width).lines.into_iter().enumerate() {
+            let (rail, fill) = match idx.cmp(&row) {
+                Ordering::Less => (' ', ' '),
+                Ordering::Equal => ('┬', '─'),
+                Ordering::Greater => ('│', ' '),
+            };
+            let mut full = vec![rail, fill];
+            full.extend(line);
+            full.extend([fill, rail]);
+            lines.push(full);
+        }
+
+        // The block on the way back is centered on the line back, with the rails running down to that line
+        let before = (width + 2 - back.width()) / 2;
+        let after = width + 2 - back.width() - before;
+        let back_row = back.row;
+        for (idx, line) in back.lines.into_iter().enumerate() {
+            let (left, right, fill) = match idx.cmp(&back_row) {
+                Ordering::Less => ('│', '│', ' '),
+                Ordering::Equal => ('╰', '╯', '─'),
+                Ordering::Greater => (' ', ' ', ' '),
+            };
+            let mut full = vec![left];
+            full.extend(std::iter::repeat_n(fill, before));
+            full.extend(line);
+            full.extend(std::iter::repeat_n(fill, after));
+            full.push(right);
+            lines.push(full);
+        }
+        Block { lines, row }
+    }
+
+    /// A block with text (such as a lookahead operator) written either side of its line
+    fn wrap(self, before: &str, after: &str) -> Block {
+        let (before, after) = (
+            before.chars().collect::<Vec<_>>(),
+            after.chars().collect::<Vec<_>>(),
+        );
+        let row = self.row;
+        let lines = self
+            .lines
+            .into_iter()
+            .enumerate()
+            .map(|(idx, line)| {
+                let (start, end) = if idx == row {
+                    (before.clone(), after.clone())
+                } else {
+                    (vec![' '; before.len()], vec![' '; after.len()])
+                };
+                start.into_iter().chain(line).chain(end).collect()
+            })
+            .collect();
+        Block { lines, row }
+    }
+}
+
+/// Lays out an expression
+fn make_block(expr: &Expr) -> Block {
+    match expr {
+        Expr::Choice { choices } => Block::choice(choices.iter().map(make_block).collect()),
+        Expr::Sequence { terms } if terms.is_empty() => Block::text(""),
+        Expr::Sequence { terms } => make_terms(terms),
+        Expr::Ident { name, .. } => Block::text(name),
+        Expr::Str { value, .. } => Block::text(&format!("\"{value}\"")),
+        Expr::InsensitiveStr { value, .. } => Block::text(&format!("^\"{value}\"")),
+        Expr::Range { start, end, .. } => Block::text(&format!("'{start}'..'{end}'")),
+        Expr::Optional { expr } => make_optional(make_block(expr)),
+        Expr::Repeat { expr } => make_optional(make_block(expr).repeat("<")),
+        Expr::RepeatOnce { expr } => make_block(expr).repeat("<"),
+        Expr::RepeatRange { expr, min, max } => {
+            let label = match (min, max) {
+                (min, Some(max)) if min == max => format!("{{{min}}}"),
+                (min, None) => format!("{{{min},}}"),
+                (0, Some(max)) => format!("{{,{max}}}"),
+                (min, Some(max)) => format!("{{{min},{max}}}"),
+            };
+            let block = make_block(expr).repeat(&label);
+            if *min == 0 {
+                make_optional(block)
+            } else {
+                block
+            }
+        }
+        Expr::PositivePredicate { expr } => make_lookahead("&", expr),
+        Expr::NegativePredicate { expr } => make_lookahead("!", expr),
+        Expr::Push { expr } => make_block(expr).wrap("PUSH(", ")"),
+        Expr::PeekSlice { start, end, .. } => Block::text(&diagram::peek_slice(*start, *end)),
+    }
+}
+
+/// Lays out the terms of a sequence, combined the same way as in the diagrams
+fn make_terms(terms: &[Expr]) -> Block {
+    Block::sequence(
+        diagram::combine_terms(terms)
+            .into_iter()
+            .map(|term| match term {
+                Term::Loop { body, separator } => {
+                    let back = match separator {
+                        [] => Block::text("<"),
+                        separator => make_body(separator),
+                    };
+                    make_body(body).repeat_through(back)
+                }
+                Term::Single(expr) => make_block(expr),
+            })
+            .collect(),
+    )
+}
+
+/// Lays out some terms of a sequence as a single block
+fn make_body(terms: &[Expr]) -> Block {
+    match terms {
+        [term] => make_block(term),
+        terms => make_terms(terms),
+    }
+}
+
+/// A block with a line skipping over it
+fn make_optional(block: Block) -> Block {
+    Block::choice(vec![Block::text(""), block])
+}
+
+/// Lays out a lookahead in pest notation, as it matches nothing itself
+fn make_lookahead(op: &str, expr: &Expr) -> Block {
+    let block = make_block(expr);
+    if block.height() == 1 {
+        block.wrap(op, "")
+    } else {
+        block.wrap(&format!("{op}("), ")")
+    }
+}
+
+pub(crate) struct TextBackend;
+
+impl Backend for TextBackend {
+    fn extension(&self) -> &'static str {
+        "txt"
+    }
+
+    fn render(
+        &self,
+        grammar: &Grammar,
+        config: &Config,
+        out: &mut dyn io::Write,
+    ) -> Result<(), Error> {
+        let mut text = String::new();
+        if let Some(title) = config.title(grammar) {
+            text.push_str(&format!(
+                "{title}\n{}\n\n",
+                "=".repeat(title.chars().count())
+            ));
+        }
+
+        for rule in &grammar.rules {
+            text.push_str(&config.label(rule));
+            if let Some(label) = rule.modifier.label() {
+                text.push_str(&format!(" ({label})"));
+            }
+            text.push('\n');
+            for doc in &rule.docs {
+                text.push_str(&format!("  {}\n", doc.trim()));
+            }
+
+            let block = Block::sequence(vec![
+                Block::text("├"),
+                make_block(&rule.expr),
+                Block::text("┤"),
+            ]);
+            for line in block.lines {
+                let line = line
+                    .into_iter()
+                    .map(|c| match ASCII.iter().find(|(fancy, _)| *fancy == c) {
+                        Some(&(_, plain)) if config.ascii => plain,
+                        _ => c,
+                    })
+                    .collect::<String>();
+                text.push_str(line.trim_end());
+                text.push('\n');
+            }
+            text.push('\n');
+        }
+
+        if !config.warnings.is_empty() {
+            text.push_str("Warnings:\n");
+            for warning in &config.warnings {
+                text.push_str(&format!("- {warning}\n"));
+            }
+        }
+
+        out.write_all(text.as_bytes())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(source: &str, config: &Config) -> String {
+        let (grammar, _) = Grammar::parse(source).unwrap();
+        let mut out = Vec::new();
+        TextBackend.render(&grammar, config, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn sequences_and_options() {
+        assert_eq!(
+            render("a = { \"x\" ~ b? }", &Config::default()),
+            "a\n\
+             ├──\"x\"──┬───┬──┤\n\
+             \x20       ╰─b─╯\n\n"
+        );
+    }
+
+    #[test]
+    fn choices_with_modifier_and_docs() {
+        assert_eq!(
+            render(
+                "/// Either letter\nb = _{ \"y\" | \"z\" }",
+                &Config::default()
+            ),
+            "b (silent)\n\
+             \x20 Either letter\n\
+             ├──┬─\"y\"─┬──┤\n\
+             \x20  ╰─\"z\"─╯\n\n"
+        );
+    }
+
+    #[test]
+    fn repeats_in_ascii() {
+        let config = Config {
+            ascii: true,
+            ..Config::default()
+        };
+        assert_eq!(
+            render("c = { \"x\"* }", &config),
+            "c\n\
+             +--+---------+--+\n\
+             \x20  '-+-\"x\"-+-'\n\
+             \x20    '--<--'\n\n"
+        );
+    }
+
+    #[test]
+    fn separated_lists_are_one_loop() {
+        let config = Config::default();
+        let expected = "a\n\
+                        ├──┬─x─────┬──┤\n\
+                        \x20  ╰──\",\"──╯\n\n";
+        assert_eq!(render("a = { x ~ (\",\" ~ x)* }", &config), expected);
+        assert_eq!(render("a = { (x ~ \",\")* ~ x }", &config), expected);
+    }
+
+    #[test]
+    fn title_and_numbering() {
+        let config = Config {
+            title: Some("Letters".to_string()),
+            numbering: true,
+            ..Config::default()
+        };
+        assert!(render("a = { \"x\" }\nb = { \"y\" }", &config)
+            .starts_with("Letters\n=======\n\n1. a\n├──\"x\"──┤\n\n2. b\n"));
+    }
+}
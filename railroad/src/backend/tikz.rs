@@ -0,0 +1,375 @@
+use std::io;
+
+use crate::{
+    backend::Backend,
+    config::Config,
+    diagram,
+    ir::{Expr, Grammar},
+    Error,
+};
+
+// The layout is measured in the width of a character of the monospace font, which the pictures use as their unit in
+// both directions, so the boxes fit their text without TeX having to measure it
+
+/// The horizontal space between the terms of a sequence
+const GAP: f64 = 2.0;
+/// The vertical space between the branches of a choice (and a repeated term and its loop)
+const V_GAP: f64 = 1.0;
+/// The horizontal space on either side of a choice or loop for its rails to curve in
+const RAIL: f64 = 2.0;
+/// Half the height of a box
+const BOX: f64 = 1.2;
+/// The radius of the curves of the rails
+const RADIUS: f64 = 0.8;
+/// The space above a loop's label (or the loop, without one) and below the picture content
+const LABEL: f64 = 1.8;
+
+/// The layout of an expression. The line enters on the left and leaves on the right, at height 0.
+enum Layout {
+    /// A string or character range, in a rounded box
+    Terminal(String),
+    /// A rule reference, in a square box
+    NonTerminal(String),
+    /// Nothing, for the empty branch of an optional term
+    Empty,
+    Sequence(Vec<Layout>),
+    /// Branches stacked under the first, which the line runs straight through
+    Choice(Vec<Layout>),
+    /// A term with a line looping back under it, labeled with the number of repetitions (if bounded)
+    Loop(Box<Layout>, Option<String>),
+    /// A term in a dashed box, labeled with the lookahead operator (or `PUSH`)
+    Lookahead(&'static str, Box<Layout>),
+}
+
+impl Layout {
+    fn new(expr: &Expr) -> Layout {
+        match expr {
+            Expr::Choice { choices } => Layout::Choice(choices.iter().map(Layout::new).collect()),
+            Expr::Sequence { terms } => Layout::Sequence(terms.iter().map(Layout::new).collect()),
+            Expr::Ident { name, .. } => Layout::NonTerminal(name.clone()),
+            Expr::Str { value, .. } => Layout::Terminal(format!("\"{value}\"")),
+            Expr::InsensitiveStr { value, .. } => Layout::Terminal(format!("^\"{value}\"")),
+            Expr::Range { start, end, .. } => Layout::Terminal(format!("'{start}'..'{end}'")),
+            Expr::Optional { expr } => Layout::optional(Layout::new(expr)),
+            Expr::Repeat { expr } => {
+                Layout::optional(Layout::Loop(Box::new(Layout::new(expr)), None))
+            }
+            Expr::RepeatOnce { expr } => Layout::Loop(Box::new(Layout::new(expr)), None),
+            Expr::RepeatRange { expr, min, max } => {
+                let label = match max {
+                    Some(max) if min == max => format!("{min} times"),
+                    None => format!("{min} or more times"),
+                    Some(max) => format!("{min} to {max} times"),
+                };
+                let repeat = Layout::Loop(Box::new(Layout::new(expr)), Some(label));
+                if *min == 0 {
+                    Layout::optional(repeat)
+                } else {
+                    repeat
+                }
+            }
+            Expr::PositivePredicate { expr } => Layout::Lookahead("&", Box::new(Layout::new(expr))),
+            Expr::NegativePredicate { expr } => Layout::Lookahead("!", Box::new(Layout::new(expr))),
+            Expr::Push { expr } => Layout::Lookahead("PUSH", Box::new(Layout::new(expr))),
+            Expr::PeekSlice { start, end, .. } => {
+                Layout::NonTerminal(diagram::peek_slice(*start, *end))
+            }
+        }
+    }
+
+    fn optional(layout: Layout) -> Layout {
+        Layout::Choice(vec![Layout::Empty, layout])
+    }
+
+    fn width(&self) -> f64 {
+        match self {
+            Layout::Terminal(text) | Layout::NonTerminal(text) => text.chars().count() as f64 + 2.0,
+            Layout::Empty => 0.0,
+            Layout::Sequence(terms) => {
+                terms.iter().map(Layout::width).sum::<f64>()
+                    + GAP * terms.len().saturating_sub(1) as f64
+            }
+            Layout::Choice(branches) => {
+                branches.iter().map(Layout::width).fold(0.0, f64::max) + 2.0 * RAIL
+            }
+            Layout::Loop(inner, _) => inner.width() + 2.0 * RAIL,
+            Layout::Lookahead(_, inner) => inner.width() + 2.0,
+        }
+    }
+
+    /// The height above the line
+    fn up(&self) -> f64 {
+        match self {
+            Layout::Terminal(_) | Layout::NonTerminal(_) => BOX,
+            Layout::Empty => 0.0,
+            Layout::Sequence(terms) => terms.iter().map(Layout::up).fold(0.0, f64::max),
+            Layout::Choice(branches) => branches.first().map_or(0.0, Layout::up),
+            Layout::Loop(inner, _) => inner.up(),
+            // Room for the operator above the box
+            Layout::Lookahead(_, inner) => inner.up() + 0.5 + LABEL,
+        }
+    }
+
+    /// The height below the line
+    fn down(&self) -> f64 {
+        match self {
+            Layout::Terminal(_) | Layout::NonTerminal(_) => BOX,
+            Layout::Empty => 0.0,
+            Layout::Sequence(terms) => terms.iter().map(Layout::down).fold(0.0, f64::max),
+            Layout::Choice(branches) => {
+                branches.first().map_or(0.0, Layout::down)
+                    + branches
+                        .iter()
+                        .skip(1)
+                        .map(|branch| V_GAP + branch.up() + branch.down())
+                        .sum::<f64>()
+            }
+            Layout::Loop(inner, label) => {
+                inner.down() + V_GAP + if label.is_some() { LABEL } else { 0.0 }
+            }
+            Layout::Lookahead(_, inner) => inner.down() + 0.5,
+        }
+    }
+
+    /// Writes the TikZ commands drawing the layout with its line entering at `(x, y)`
+    fn draw(&self, x: f64, y: f64, out: &mut dyn io::Write) -> io::Result<()> {
+        let width = self.width();
+        match self {
+            Layout::Terminal(text) | Layout::NonTerminal(text) => {
+                let corners = match self {
+                    Layout::Terminal(_) => format!("[rounded corners={BOX}]"),
+                    _ => String::new(),
+                };
+                writeln!(
+                    out,
+                    "\\draw{corners} {} rectangle {};",
+                    pt(x, y - BOX),
+                    pt(x + width, y + BOX)
+                )?;
+                writeln!(
+                    out,
+                    "\\node at {} {{{}}};",
+                    pt(x + width / 2.0, y),
+                    escape(text)
+                )?;
+            }
+            Layout::Empty => {}
+            Layout::Sequence(terms) => {
+                let mut x = x;
+                for (idx, term) in terms.iter().enumerate() {
+                    if idx > 0 {
+                        writeln!(out, "\\draw {} -- {};", pt(x, y), pt(x + GAP, y))?;
+                        x += GAP;
+                    }
+                    term.draw(x, y, out)?;
+                    x += term.width();
+                }
+            }
+            Layout::Choice(branches) => {
+                let (left, right) = (x + RAIL, x + width - RAIL);
+                let mut branch_y = y;
+                for (idx, branch) in branches.iter().enumerate() {
+                    let end = left + branch.width();
+                    if idx == 0 {
+                        writeln!(out, "\\draw {} -- {};", pt(x, y), pt(left, y))?;
+                        writeln!(out, "\\draw {} -- {};", pt(end, y), pt(x + width, y))?;
+                    } else {
+                        branch_y -= branches[idx - 1].down() + V_GAP + branch.up();
+                        // Curve down from the line to the branch, and back up again after it
+                        writeln!(
+                            out,
+                            "\\draw[rounded corners={RADIUS}] {} -| {} -- {};",
+                            pt(x, y),
+                            pt(x + RAIL / 2.0, branch_y),
+                            pt(left, branch_y)
+                        )?;
+                        writeln!(
+                            out,
+                            "\\draw[rounded corners={RADIUS}] {} -| {} -- {};",
+                            pt(end, branch_y),
+                            pt(right + RAIL / 2.0, y),
+                            pt(x + width, y)
+                        )?;
+                    }
+                    branch.draw(left, branch_y, out)?;
+                }
+            }
+            Layout::Loop(inner, label) => {
+                let (left, right) = (x + RAIL, x + width - RAIL);
+                let loop_y = y - inner.down() - V_GAP;
+                writeln!(out, "\\draw {} -- {};", pt(x, y), pt(left, y))?;
+                writeln!(out, "\\draw {} -- {};", pt(right, y), pt(x + width, y))?;
+                writeln!(
+                    out,
+                    "\\draw[rounded corners={RADIUS}] {} -| {} -| {} -- {};",
+                    pt(right, y),
+                    pt(right + RAIL / 2.0, loop_y),
+                    pt(x + RAIL / 2.0, y),
+                    pt(left, y)
+                )?;
+                if let Some(label) = label {
+                    writeln!(
+                        out,
+                        "\\node[below, font=\\footnotesize] at {} {{{}}};",
+                        pt(x + width / 2.0, loop_y),
+                        escape(label)
+                    )?;
+                }
+                inner.draw(left, y, out)?;
+            }
+            Layout::Lookahead(op, inner) => {
+                let (top, bottom) = (y + inner.up() + 0.5, y - inner.down() - 0.5);
+                writeln!(out, "\\draw {} -- {};", pt(x, y), pt(x + 1.0, y))?;
+                writeln!(
+                    out,
+                    "\\draw {} -- {};",
+                    pt(x + 1.0 + inner.width(), y),
+                    pt(x + width, y)
+                )?;
+                writeln!(
+                    out,
+                    "\\draw[dashed] {} rectangle {};",
+                    pt(x + 0.5, bottom),
+                    pt(x + width - 0.5, top)
+                )?;
+                writeln!(
+                    out,
+                    "\\node[above right] at {} {{{}}};",
+                    pt(x + 0.5, top),
+                    escape(op)
+                )?;
+                inner.draw(x + 1.0, y, out)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A TikZ coordinate, rounded so the sums of the layout don't leave long fractions
+fn pt(x: f64, y: f64) -> String {
+    let num = |value: f64| {
+        let text = format!("{value:.2}");
+        let text = text.trim_end_matches('0').trim_end_matches('.');
+        match text {
+            "-0" => "0".to_string(),
+            text => text.to_string(),
+        }
+    };
+    format!("({}, {})", num(x), num(y))
+}
+
+/// Escapes text for LaTeX
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\textbackslash{}"),
+            '^' => escaped.push_str("\\textasciicircum{}"),
+            '~' => escaped.push_str("\\textasciitilde{}"),
+            '{' | '}' | '$' | '&' | '#' | '_' | '%' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+pub(crate) struct TikzBackend;
+
+impl Backend for TikzBackend {
+    fn extension(&self) -> &'static str {
+        "tex"
+    }
+
+    fn render(
+        &self,
+        grammar: &Grammar,
+        config: &Config,
+        out: &mut dyn io::Write,
+    ) -> Result<(), Error> {
+        writeln!(
+            out,
+            "% Railroad diagrams for \\input into a LaTeX document that uses the tikz package"
+        )?;
+        if let Some(title) = config.title(grammar) {
+            writeln!(out, "% {title}")?;
+        }
+
+        for rule in &grammar.rules {
+            writeln!(out)?;
+            for doc in &rule.docs {
+                writeln!(out, "% {}", doc.trim())?;
+            }
+            // The unit is the width of a character of the typewriter font the text is set in
+            writeln!(
+                out,
+                "\\begin{{tikzpicture}}[x=0.525em, y=0.525em, line width=0.8pt, font=\\ttfamily, baseline=0pt]"
+            )?;
+
+            let layout = Layout::new(&rule.expr);
+            let width = layout.width();
+            writeln!(
+                out,
+                "\\node[above right, font=\\ttfamily\\bfseries] at {} {{{}}};",
+                pt(0.0, layout.up() + 0.5),
+                escape(&config.label(rule))
+            )?;
+            // The bars at either end mark the start and end of the rule
+            writeln!(out, "\\draw (0, -1) -- (0, 1) (0, 0) -- (2, 0);")?;
+            layout.draw(2.0, 0.0, out)?;
+            let end = width + 4.0;
+            writeln!(
+                out,
+                "\\draw {} -- {} {} -- {};",
+                pt(width + 2.0, 0.0),
+                pt(end, 0.0),
+                pt(end, -1.0),
+                pt(end, 1.0)
+            )?;
+            // Leave room below the lowest line
+            writeln!(out, "\\path {};", pt(0.0, -layout.down() - LABEL))?;
+            writeln!(out, "\\end{{tikzpicture}}")?;
+            writeln!(out, "\\par\\medskip")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{render_test, Format};
+
+    #[test]
+    fn golden() {
+        let src = r#"
+            /// Special characters
+            my_rule = { "\\" ~ "{$&#_%}" ~ ^"~" }
+        "#;
+        let out = String::from_utf8(render_test(src, &Config::default(), Format::Tikz)).unwrap();
+        assert_eq!(
+            out,
+            r#"% Railroad diagrams for \input into a LaTeX document that uses the tikz package
+
+% Special characters
+\begin{tikzpicture}[x=0.525em, y=0.525em, line width=0.8pt, font=\ttfamily, baseline=0pt]
+\node[above right, font=\ttfamily\bfseries] at (0, 1.7) {my\_rule};
+\draw (0, -1) -- (0, 1) (0, 0) -- (2, 0);
+\draw[rounded corners=1.2] (2, -1.2) rectangle (8, 1.2);
+\node at (5, 0) {"\textbackslash{}\textbackslash{}"};
+\draw (8, 0) -- (10, 0);
+\draw[rounded corners=1.2] (10, -1.2) rectangle (21, 1.2);
+\node at (15.5, 0) {"\{\$\&\#\_\%\}"};
+\draw (21, 0) -- (23, 0);
+\draw[rounded corners=1.2] (23, -1.2) rectangle (29, 1.2);
+\node at (26, 0) {\textasciicircum{}"\textasciitilde{}"};
+\draw (29, 0) -- (31, 0) (31, -1) -- (31, 1);
+\path (0, -3);
+\end{tikzpicture}
+\par\medskip
+"#
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use std::io;
+
+use railroad::Node;
+
+use crate::{
+    backend::{single_rule, Backend},
+    config::Config,
+    diagram,
+    ir::Grammar,
+    Error,
+};
+
+/// The size of a CSS pixel (the unit of the SVG diagrams) in Typst points
+const PX: f64 = 0.75;
+
+/// Shows a rule: its name, doc comments and diagram, kept together on one page
+const SHOW_RULE: &str = "#let show-rule(name, docs, diagram) = block(breakable: false, {
+  strong(raw(name))
+  parbreak()
+  if docs != \"\" {
+    docs
+    parbreak()
+  }
+  diagram
+})";
+
+/// Quotes text as a Typst string
+fn string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+pub(crate) struct TypstBackend;
+
+impl Backend for TypstBackend {
+    fn extension(&self) -> &'static str {
+        "typ"
+    }
+
+    fn render(
+        &self,
+        grammar: &Grammar,
+        config: &Config,
+        out: &mut dyn io::Write,
+    ) -> Result<(), Error> {
+        let rule_config = Config {
+            title: None,
+            warnings: Vec::new(),
+            legend: false,
+            ..config.clone()
+        };
+
+        writeln!(
+            out,
+            "// Railroad diagrams of a pest grammar. `#include` this file to show"
+        )?;
+        writeln!(
+            out,
+            "// every rule, or `#import` it to place single rules with `#rules.at(\"name\")`."
+        )?;
+        writeln!(out)?;
+        writeln!(out, "#let rules = (")?;
+        for rule in &grammar.rules {
+            // Like resvg, Typst only supports a subset of CSS
+            let diagram = diagram::make_diagram(
+                &single_rule(rule),
+                &rule_config,
+                rule_config.theme.render_safe_stylesheet(),
+                None,
+            )?;
+            // Each image is a document of its own, so any fonts are embedded in all of them
+            let svg = diagram::to_svg(&diagram, &rule_config);
+            writeln!(
+                out,
+                "  {}: image(bytes({}), format: \"svg\", width: {}pt),",
+                string(&rule.name),
+                string(&svg),
+                diagram.width() as f64 * PX
+            )?;
+        }
+        writeln!(out, ")")?;
+        writeln!(out)?;
+        writeln!(out, "{SHOW_RULE}")?;
+
+        if let Some(title) = config.title(grammar) {
+            writeln!(out)?;
+            writeln!(out, "#heading({})", string(title))?;
+        }
+        for rule in &grammar.rules {
+            let docs = rule
+                .docs
+                .iter()
+                .map(|doc| doc.trim())
+                .collect::<Vec<_>>()
+                .join(" ");
+            writeln!(out)?;
+            writeln!(
+                out,
+                "#show-rule({}, {}, rules.at({}))",
+                string(&config.label(rule)),
+                string(&docs),
+                string(&rule.name)
+            )?;
+        }
+
+        if !config.warnings.is_empty() {
+            writeln!(out)?;
+            writeln!(out, "#strong[Warnings]")?;
+            writeln!(out)?;
+            for warning in &config.warnings {
+                writeln!(out, "- #{}", string(&warning.to_string()))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{render_test, Format};
+
+    #[test]
+    fn rules_and_escaping() {
+        let src = r#"
+            //! The "quoted" grammar
+            /// Has a "quote" and a \ backslash
+            value = { "\"" ~ "\\" }
+        "#;
+        let out = String::from_utf8(render_test(src, &Config::default(), Format::Typst)).unwrap();
+
+        // The SVG diagram is quoted onto a single line, with its own quotes escaped
+        let image = out
+            .lines()
+            .find(|line| line.starts_with("  \"value\": image(bytes(\"<svg"))
+            .unwrap();
+        assert!(image.contains("xmlns=\\\"http://www.w3.org/2000/svg\\\""));
+        assert!(image.ends_with("\"), format: \"svg\", width: 211.5pt),"));
+
+        assert!(out.contains("\n#heading(\"The \\\"quoted\\\" grammar\")\n"));
+        assert!(out.contains(
+            "\n#show-rule(\"value\", \"Has a \\\"quote\\\" and a \\\\ backslash\", rules.at(\"value\"))\n"
+        ));
+    }
+}
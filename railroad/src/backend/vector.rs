@@ -0,0 +1,42 @@
+//! Parsing of diagrams with usvg, for the backends converting them into other vector formats
+
+use usvg::fontdb;
+
+use crate::{config::Config, diagram, ir::Grammar, Error};
+
+/// The options for parsing diagrams, with the system's fonts loaded
+pub(super) fn parse_options() -> usvg::Options<'static> {
+    let mut options = usvg::Options::default();
+    let db = options.fontdb_mut();
+    db.load_system_fonts();
+
+    // The generic monospace family names a font that isn't installed on many systems, so fall back to any
+    // monospace font
+    let query = fontdb::Query {
+        families: &[fontdb::Family::Monospace],
+        ..fontdb::Query::default()
+    };
+    if db.query(&query).is_none() {
+        let family = db
+            .faces()
+            .find(|face| face.monospaced)
+            .map(|face| face.families[0].0.clone());
+        if let Some(family) = family {
+            db.set_monospace_family(family);
+        }
+    }
+    options
+}
+
+/// Parses the SVG diagram of a grammar for conversion
+pub(super) fn tree(
+    grammar: &Grammar,
+    config: &Config,
+    options: &usvg::Options,
+) -> Result<usvg::Tree, Error> {
+    // Like resvg, usvg only supports a subset of CSS
+    let diagram =
+        diagram::make_diagram(grammar, config, config.theme.render_safe_stylesheet(), None)?;
+    usvg::Tree::from_str(&diagram.to_string(), options)
+        .map_err(|err| Error::Render(err.to_string()))
+}
@@ -0,0 +1,193 @@
+use std::io;
+
+use crate::{
+    backend::Backend,
+    config::Config,
+    ir::{Expr, Grammar, Modifier},
+    Error,
+};
+
+/// Escapes text for XML content and (double quoted) attribute values. Control characters aren't allowed in XML 1.0
+/// documents at all, so they're replaced.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c.is_control() => escaped.push('\u{FFFD}'),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// The name of a modifier, as in the JSON output
+fn modifier_name(modifier: Modifier) -> &'static str {
+    match modifier {
+        Modifier::Normal => "normal",
+        Modifier::Silent => "silent",
+        Modifier::Atomic => "atomic",
+        Modifier::CompoundAtomic => "compound_atomic",
+        Modifier::NonAtomic => "non_atomic",
+    }
+}
+
+/// Appends the element of an expression, with the same names as the `type` tags of the JSON output
+fn write_expr(expr: &Expr, indent: usize, out: &mut String) {
+    let pad = "  ".repeat(indent);
+    let (name, attrs) = match expr {
+        Expr::Choice { .. } => ("choice", String::new()),
+        Expr::Sequence { .. } => ("sequence", String::new()),
+        Expr::Ident { name, .. } => ("ident", format!(" name=\"{}\"", escape(name))),
+        Expr::Str { value, .. } => ("string", format!(" value=\"{}\"", escape(value))),
+        Expr::InsensitiveStr { value, .. } => (
+            "insensitive_string",
+            format!(" value=\"{}\"", escape(value)),
+        ),
+        Expr::Range { start, end, .. } => (
+            "range",
+            format!(" start=\"{}\" end=\"{}\"", escape(start), escape(end)),
+        ),
+        Expr::Optional { .. } => ("optional", String::new()),
+        Expr::Repeat { .. } => ("repeat", String::new()),
+        Expr::RepeatOnce { .. } => ("repeat_once", String::new()),
+        // An unbounded repeat has no max attribute
+        Expr::RepeatRange { min, max, .. } => (
+            "repeat_range",
+            match max {
+                Some(max) => format!(" min=\"{min}\" max=\"{max}\""),
+                None => format!(" min=\"{min}\""),
+            },
+        ),
+        Expr::PositivePredicate { .. } => ("positive_predicate", String::new()),
+        Expr::NegativePredicate { .. } => ("negative_predicate", String::new()),
+        Expr::Push { .. } => ("push", String::new()),
+        // A missing index has no attribute
+        Expr::PeekSlice { start, end, .. } => (
+            "peek_slice",
+            [("start", start), ("end", end)]
+                .into_iter()
+                .filter_map(|(name, index)| index.map(|index| format!(" {name}=\"{index}\"")))
+                .collect(),
+        ),
+    };
+
+    let children = expr.children();
+    if children.is_empty() {
+        out.push_str(&format!("{pad}<{name}{attrs}/>\n"));
+    } else {
+        out.push_str(&format!("{pad}<{name}{attrs}>\n"));
+        for child in children {
+            write_expr(child, indent + 1, out);
+        }
+        out.push_str(&format!("{pad}</{name}>\n"));
+    }
+}
+
+/// The grammar IR as XML, for XML tooling such as XSLT. It holds the same details as the JSON output.
+pub(crate) struct XmlBackend;
+
+impl Backend for XmlBackend {
+    fn extension(&self) -> &'static str {
+        "xml"
+    }
+
+    fn render(
+        &self,
+        grammar: &Grammar,
+        _config: &Config,
+        out: &mut dyn io::Write,
+    ) -> Result<(), Error> {
+        let mut xml = String::new();
+        xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str("<grammar>\n");
+        for doc in &grammar.docs {
+            xml.push_str(&format!("  <doc>{}</doc>\n", escape(doc)));
+        }
+        for rule in &grammar.rules {
+            // Factored rules have no line or number
+            let line = rule
+                .line
+                .map(|line| format!(" line=\"{line}\""))
+                .unwrap_or_default();
+            let number = rule
+                .number
+                .map(|number| format!(" number=\"{number}\""))
+                .unwrap_or_default();
+            xml.push_str(&format!(
+                "  <rule name=\"{}\" modifier=\"{}\"{line}{number}>\n",
+                escape(&rule.name),
+                modifier_name(rule.modifier),
+            ));
+            for doc in &rule.docs {
+                xml.push_str(&format!("    <doc>{}</doc>\n", escape(doc)));
+            }
+            xml.push_str(&format!("    <source>{}</source>\n", escape(&rule.source)));
+            write_expr(&rule.expr, 2, &mut xml);
+            xml.push_str("  </rule>\n");
+        }
+        xml.push_str("</grammar>\n");
+
+        out.write_all(xml.as_bytes())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{render_test, Format};
+
+    #[test]
+    fn golden() {
+        let src = r#"
+            //! The <"quoted"> & escaped grammar
+            /// A <value>
+            value = @{ "\"<&>" ~ ('a'..'z')? ~ item{2,} }
+            item = _{ ^"x" | !"\u{01}" ~ ANY }
+        "#;
+        let out = String::from_utf8(render_test(src, &Config::default(), Format::Xml)).unwrap();
+        assert_eq!(
+            out,
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<grammar>
+  <doc>The &lt;&quot;quoted&quot;&gt; &amp; escaped grammar</doc>
+  <rule name="value" modifier="atomic" line="4" number="1">
+    <doc>A &lt;value&gt;</doc>
+    <source>value = @{ &quot;\&quot;&lt;&amp;&gt;&quot; ~ ('a'..'z')? ~ item{2,} }</source>
+    <sequence>
+      <string value="\&quot;&lt;&amp;&gt;"/>
+      <optional>
+        <range start="a" end="z"/>
+      </optional>
+      <repeat_range min="2">
+        <ident name="item"/>
+      </repeat_range>
+    </sequence>
+  </rule>
+  <rule name="item" modifier="silent" line="5" number="2">
+    <source>item = _{ ^&quot;x&quot; | !&quot;\u{01}&quot; ~ ANY }</source>
+    <choice>
+      <insensitive_string value="x"/>
+      <sequence>
+        <negative_predicate>
+          <string value="\u{01}"/>
+        </negative_predicate>
+        <ident name="ANY"/>
+      </sequence>
+    </choice>
+  </rule>
+</grammar>
+"#
+        );
+    }
+
+    #[test]
+    fn control_characters_are_replaced() {
+        assert_eq!(escape("a\u{1}\tb\u{7f}"), "a\u{FFFD}\tb\u{FFFD}");
+    }
+}
@@ -0,0 +1,963 @@
+//! Options controlling what is rendered and how
+
+use std::{
+    collections::{HashMap, HashSet},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use railroad::Stylesheet;
+
+use crate::{
+    deps::DependencyGraph,
+    ir::{Expr, Grammar, GrammarRule, Span},
+    lint, Warning,
+};
+
+/// Options for rendering a grammar. The defaults render every rule.
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    /// Only render rules whose names match one of these patterns (all rules if empty). Patterns support the `*`
+    /// (any run of characters) and `?` (any single character) wildcards.
+    pub include: Vec<String>,
+    /// Only render this rule and the rules reachable from it
+    pub start_rule: Option<String>,
+    /// The stylesheet embedded in the diagram
+    pub theme: Theme,
+    /// Additional CSS embedded after the theme's stylesheet (in order), to customize its appearance
+    pub css: Vec<String>,
+    /// The title of the diagram (or page). Defaults to the first grammar doc comment line.
+    pub title: Option<String>,
+    /// The name of the grammar (such as the stem of its file name), for formats that declare one (ANTLR). Defaults to
+    /// `Grammar`.
+    pub name: Option<String>,
+    /// The file name of the grammar, which `line_refs` refer to
+    pub file_name: Option<String>,
+    /// Draw the rules whose names match one of these patterns (which support the same wildcards as `include`), and the
+    /// references to them, with an accent color
+    pub highlight: Vec<String>,
+    /// Draw the rules whose names match one of these patterns in place of each reference to them (instead of as a
+    /// nonterminal box), leaving out their own diagrams. Recursive references are left as they are.
+    pub inline: Vec<String>,
+    /// Draw the rules whose body is a single terminal (such as `lparen = { "(" }`) in place of each reference to them,
+    /// like the rules matching `inline`
+    pub inline_trivial: InlineTrivial,
+    /// Draw the rules referenced by each rule in place of the references, and the rules they reference in turn, up to
+    /// this many levels deep, so each diagram is self-contained. Deeper (and recursive) references are left as boxes,
+    /// linked to the diagram of their rule (see `anchors`) unless there is a link template.
+    pub expand_depth: Option<usize>,
+    /// Factor the complex sub-expressions that appear more than once in a rule out into rules of their own (named after
+    /// the rule, as in `expr_1`), drawn once after it and referenced in each place they appeared
+    pub factor_repeated: bool,
+    /// Make the diagram trace the path under the cursor when viewed in a browser, highlighting the branch of each
+    /// choice it is in and dimming the alternatives
+    pub interactive: bool,
+    /// Color the boxes of each rule by its modifier (silent, atomic, compound atomic, non-atomic or normal), so the
+    /// diagram shows how each rule treats whitespace and tokens at a glance
+    pub modifier_colors: bool,
+    /// Draw pest's stack operations (`PUSH`, `POP`, `PEEK`, `DROP`, `POP_ALL`, `PEEK_ALL` and `PEEK` slices) with a
+    /// stack glyph and a color of their own, and what each does as its tooltip, so grammars that rely on the stack stay
+    /// legible
+    pub stack_ops: bool,
+    /// Add a legend explaining the notation (with a sample of each kind of box, loop and frame) after the last rule
+    pub legend: bool,
+    /// How the frames around lookahead are drawn
+    pub lookahead_style: LookaheadStyle,
+    /// Draw every rule of an HTML page in a single diagram, instead of a diagram of its own for each rule
+    pub combined: bool,
+    /// Give the diagram of each rule an `id` of the rule name (after any `id_prefix`), so links such as
+    /// `grammar.html#string` can point at it. Rule names are identifiers, which are valid ids as they are.
+    pub anchors: bool,
+    /// Add a table of contents linking to the diagram of each rule (HTML only). In a combined diagram, this implies
+    /// `anchors`.
+    pub toc: bool,
+    /// Show the source of each rule (syntax highlighted, in a collapsible block) below its diagram (HTML only). Each
+    /// rule is drawn as a diagram of its own even if `combined` is set.
+    pub include_source: bool,
+    /// Add a box to the table of contents that filters the rules by name and doc comments (HTML only). This implies
+    /// `toc`, and draws each rule as a diagram of its own (even if `combined` is set) so the filtered out ones can be
+    /// hidden.
+    pub search: bool,
+    /// Let readers click a reference to a rule to expand the rule's diagram under the diagram it's in, and so drill into
+    /// the grammar top-down (HTML only). Each rule is drawn as a diagram of its own even if `combined` is set.
+    pub expandable: bool,
+    /// Mark each rule, terminal and rule reference of the diagram with the byte range of its source in the grammar file
+    /// (in `data-source-start` and `data-source-end` attributes), so editor integrations can jump from the diagram to
+    /// the grammar. The parts of inlined rules point into the source of those rules.
+    pub source_map: bool,
+    /// Put the diagram of each rule on a page of its own under a header with the rule's name (PDF only)
+    pub paginate: bool,
+    /// The paper size of the pages of paginated output, which each diagram is scaled down to fit (on a landscape page
+    /// if it's wider than it is tall). Each page is sized to fit its diagram if not given.
+    pub page_size: Option<PageSize>,
+    /// Refer to the diagram of each rule as an image at the path made by replacing `{rule}` in this template with the
+    /// rule name, instead of inlining it (Markdown only)
+    pub image_template: Option<String>,
+    /// Draw text diagrams with plain ASCII characters instead of box-drawing ones (text only)
+    pub ascii: bool,
+    /// The font family of the diagram text (a monospace font by default)
+    pub font: Option<String>,
+    /// Embed the font (`font`, or else the system's default monospace font) in the diagram, so it looks the same on
+    /// machines without it installed. Only regular, bold and italic faces are embedded.
+    pub embed_fonts: bool,
+    /// The font of the terminals' text, overriding `font` and the theme's
+    pub terminal_font: TextFont,
+    /// The font of the text of rule (and built-in rule) references
+    pub nonterminal_font: TextFont,
+    /// The font of the comments: rule names, doc comments and labels
+    pub comment_font: TextFont,
+    /// Wrap sequences wider than this many pixels onto stacked rows, joined by lines running back to the start of the
+    /// next row, so long rules don't make extremely wide diagrams
+    pub max_width: Option<i64>,
+    /// Cut the labels of terminals longer than this many characters short with an ellipsis, showing the whole terminal
+    /// as a tooltip, so long literals don't make wide diagrams
+    pub max_label_length: Option<usize>,
+    /// Lay choices of more than this many alternatives out as columns of at most this many side by side, so choices
+    /// of dozens of keywords don't make extremely tall diagrams
+    pub max_choice_alternatives: Option<usize>,
+    /// Draw the diagrams right to left (starting on the right, with the terms of sequences in reverse and the arrows
+    /// pointing left), for documentation in right-to-left languages. Sequences aren't wrapped onto rows by `max_width`.
+    pub rtl: bool,
+    /// How the start and end of each rule's diagram are drawn
+    pub end_style: EndStyle,
+    /// Draw a rule ending with `EOI` with "end of input" written under its end marker, instead of a box for `EOI` just
+    /// before it. Rules drawn without markers (`EndStyle::None`) keep the box.
+    pub merge_eoi: bool,
+    /// The space (in pixels) around the diagram [default: 10]
+    pub margin: Option<i64>,
+    /// The vertical space (in pixels) between rules, which can be reduced to pack grammars of many small rules more
+    /// tightly [default: 12]
+    pub rule_spacing: Option<i64>,
+    /// The size of raster (PNG) output
+    pub raster_size: RasterSize,
+    /// Scale raster (PNG) output down, if needed, so it has at most this many pixels
+    pub max_pixels: Option<u64>,
+    /// Leave out references to pest's built-in rules (such as `ASCII_DIGIT`), drawing a plain line in their place so
+    /// the choices and repetitions around them keep their shape
+    pub hide_builtins: bool,
+    /// Leave out references to the rules whose names match this pattern (which supports the same wildcards as
+    /// `include`, as in `ASCII_*`) in the same way, along with the diagrams of any such rules the grammar defines
+    pub hide_pattern: Option<String>,
+    /// Link each reference to a rule to the URL made by replacing `{rule}` in this template with the rule name (e.g.
+    /// `https://docs.example.com/grammar/{rule}`)
+    pub link_template: Option<String>,
+    /// Prefix the name of each rule with its number (its position in the whole grammar, so rendering only some rules
+    /// doesn't renumber them)
+    pub numbering: bool,
+    /// Append the grammar file name and line of each rule (such as `grammar.pest:42`, or just the line without a
+    /// `file_name`) to its name, for jumping from the diagram back to the definition
+    pub line_refs: bool,
+    /// Note under the name of each rule that allows whitespace and comments between its elements (because the grammar
+    /// defines `WHITESPACE` or `COMMENT` and the rule isn't atomic) that it does
+    pub implicit_notes: bool,
+    /// Warnings (such as those found when parsing the grammar) listed in a block at the bottom of the diagram, so
+    /// readers can see which parts of the grammar aren't fully shown
+    pub warnings: Vec<Warning>,
+    /// A line of small text (such as where and when the diagram was generated) under the diagram. See `expand_footer`
+    /// for filling in the details.
+    pub footer: Option<String>,
+    /// Prefix every id and class of the SVG (including those used by the stylesheets) with this string, so several
+    /// diagrams can be inlined into one page without their ids colliding or their styles leaking into each other
+    pub id_prefix: Option<String>,
+    /// The names of the rules defined by the grammar being drawn, which are never drawn as built-ins, whatever their
+    /// names. The rendering functions fill this in from the whole grammar they are given (before `select`), so it
+    /// doesn't need to be set.
+    pub defined: HashSet<String>,
+}
+
+/// Replaces the references to the given rules by their expressions (recursively). `stack` holds the rules currently
+/// being expanded, so recursive references are left alone rather than expanded forever.
+fn inline_refs<'a>(expr: &mut Expr, rules: &HashMap<&'a str, Expr>, stack: &mut Vec<&'a str>) {
+    let Expr::Ident { name, .. } = expr else {
+        for child in expr.children_mut() {
+            inline_refs(child, rules, stack);
+        }
+        return;
+    };
+
+    if let Some((&name, rule_expr)) = rules.get_key_value(name.as_str()) {
+        if !stack.contains(&name) {
+            *expr = rule_expr.clone();
+            stack.push(name);
+            inline_refs(expr, rules, stack);
+            stack.pop();
+        }
+    }
+}
+
+/// Replaces the references to other rules by their expressions, and the references in those in turn, `depth` levels
+/// deep. `stack` holds the rules currently being expanded, so recursive references are left alone.
+fn expand_refs<'a>(
+    expr: &mut Expr,
+    rules: &HashMap<&'a str, &'a Expr>,
+    depth: usize,
+    stack: &mut Vec<&'a str>,
+) {
+    let Expr::Ident { name, .. } = expr else {
+        for child in expr.children_mut() {
+            expand_refs(child, rules, depth, stack);
+        }
+        return;
+    };
+
+    if depth == 0 {
+        return;
+    }
+    if let Some((&name, &rule_expr)) = rules.get_key_value(name.as_str()) {
+        if !stack.contains(&name) {
+            *expr = rule_expr.clone();
+            stack.push(name);
+            expand_refs(expr, rules, depth - 1, stack);
+            stack.pop();
+        }
+    }
+}
+
+/// The fewest terminals and references a sub-expression has to have to be factored out of a rule, so only complex ones
+/// are
+const MIN_FACTORED_SIZE: usize = 3;
+
+/// The number of terminals and references in an expression
+fn expr_size(expr: &Expr) -> usize {
+    let mut size = 0;
+    expr.walk(&mut |expr| size += usize::from(expr.span().is_some()));
+    size
+}
+
+/// The source range from the first to the last terminal or reference of an expression
+fn expr_extent(expr: &Expr) -> Span {
+    let mut extent: Option<Span> = None;
+    expr.walk(&mut |expr| {
+        if let Some(span) = expr.span() {
+            extent = Some(match extent {
+                Some(extent) => Span {
+                    start: extent.start.min(span.start),
+                    end: extent.end.max(span.end),
+                },
+                None => span,
+            });
+        }
+    });
+    extent.unwrap_or_default()
+}
+
+/// The largest complex sub-expression appearing more than once in an expression (the first one, if several are as
+/// large)
+fn largest_repeated(expr: &Expr) -> Option<Expr> {
+    let mut seen: Vec<(&Expr, usize)> = Vec::new();
+    for child in expr.children() {
+        child.walk(&mut |expr| {
+            if expr_size(expr) < MIN_FACTORED_SIZE {
+                return;
+            }
+            match seen.iter_mut().find(|(seen, _)| seen.same_structure(expr)) {
+                Some((_, count)) => *count += 1,
+                None => seen.push((expr, 1)),
+            }
+        });
+    }
+    seen.into_iter()
+        .rev()
+        .filter(|&(_, count)| count > 1)
+        .map(|(expr, _)| expr)
+        .max_by_key(|expr| expr_size(expr))
+        .cloned()
+}
+
+/// Replaces each appearance of `target` in an expression by a reference to the rule `name`
+fn replace_expr(expr: &mut Expr, target: &Expr, name: &str) {
+    if expr.same_structure(target) {
+        *expr = Expr::Ident {
+            name: name.to_string(),
+            span: expr_extent(expr),
+            recursive: false,
+        };
+        return;
+    }
+    for child in expr.children_mut() {
+        replace_expr(child, target, name);
+    }
+}
+
+/// Factors the complex sub-expressions appearing more than once in a rule out into rules of their own, named after the
+/// rule (skipping the names in `taken`). Returns the rule followed by the rules factored out of it, which have no line
+/// or number.
+fn factor_repeated(mut rule: GrammarRule, taken: &HashSet<&str>) -> Vec<GrammarRule> {
+    let mut parts = Vec::new();
+    let mut number = 0;
+    while let Some(repeated) = largest_repeated(&rule.expr) {
+        let name = loop {
+            number += 1;
+            let name = format!("{}_{number}", rule.name);
+            if !taken.contains(name.as_str()) {
+                break name;
+            }
+        };
+        replace_expr(&mut rule.expr, &repeated, &name);
+        // Not in the source, so the rule has no line or number of its own, only the span of what it replaces
+        parts.push(GrammarRule {
+            name,
+            modifier: rule.modifier,
+            docs: vec![format!("Repeated in `{}`", rule.name)],
+            span: expr_extent(&repeated),
+            expr: repeated,
+            line: None,
+            number: None,
+            source: String::new(),
+            implicit: Vec::new(),
+        });
+    }
+    parts.insert(0, rule);
+    parts
+}
+
+/// Returns true if the expression is a single terminal
+fn is_trivial(expr: &Expr) -> bool {
+    matches!(
+        expr,
+        Expr::Str { .. } | Expr::InsensitiveStr { .. } | Expr::Range { .. }
+    )
+}
+
+/// Replaces the references to the rules for which `hidden` returns true with empty sequences, which are drawn as
+/// plain lines, so the choices and repetitions around them keep their shape. Sequences drop the terms left empty.
+fn hide_refs(expr: &mut Expr, hidden: &impl Fn(&str) -> bool) {
+    match expr {
+        Expr::Ident { name, .. } if hidden(name) => *expr = Expr::Sequence { terms: Vec::new() },
+        Expr::Sequence { terms } => {
+            for term in terms.iter_mut() {
+                hide_refs(term, hidden);
+            }
+            terms.retain(|term| !matches!(term, Expr::Sequence { terms } if terms.is_empty()));
+            if terms.len() == 1 {
+                // Panic safety: There is exactly one term left
+                *expr = terms.pop().expect("single term");
+            }
+        }
+        _ => {
+            for child in expr.children_mut() {
+                hide_refs(child, hidden);
+            }
+        }
+    }
+}
+
+/// How raster output is sized relative to the diagram's natural (SVG) size
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RasterSize {
+    /// Scale the diagram by this factor (`2.0` for high density displays)
+    Scale(f64),
+    /// Scale the diagram to this width in pixels, keeping its aspect ratio
+    Width(u32),
+}
+
+impl Default for RasterSize {
+    fn default() -> Self {
+        RasterSize::Scale(1.0)
+    }
+}
+
+impl RasterSize {
+    /// The width (in pixels) of a raster image of a diagram `width` pixels wide
+    pub fn width(self, width: i64) -> u32 {
+        match self {
+            RasterSize::Scale(scale) => (width as f64 * scale).round().max(1.0) as u32,
+            RasterSize::Width(width) => width.max(1),
+        }
+    }
+}
+
+/// Whether (and how) rules whose body is a single terminal are drawn in place of the references to them
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InlineTrivial {
+    /// Draw them as rules of their own, like any other
+    #[default]
+    Off,
+    /// Draw the terminal in place of each reference
+    Terminal,
+    /// Draw the terminal in place of each reference, followed by the rule's name
+    Named,
+}
+
+/// How the frames around lookahead (predicates) are drawn
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LookaheadStyle {
+    /// A box labeled with what the lookahead means (such as "Lookahead: Must match")
+    #[default]
+    Labeled,
+    /// A light dashed box with a small `&` or `!` badge, and the label as its tooltip
+    Badge,
+}
+
+/// The markers at the start and end of each rule's diagram
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EndStyle {
+    /// Small circles
+    #[default]
+    Simple,
+    /// Double bars, as in the diagrams of the W3C and SQL specifications
+    Full,
+    /// No markers, leaving the lines open for diagrams meant to be embedded in other diagrams
+    None,
+}
+
+/// The font of one kind of diagram text. The parts left unset keep those of the theme (and `font`). Boxes are sized
+/// for the theme's text, so text much larger than it overflows them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TextFont {
+    /// The font family, which isn't embedded by `embed_fonts`
+    pub family: Option<String>,
+    /// The font size in pixels
+    pub size: Option<f64>,
+    /// The font weight, from 1 to 1000 (400 is normal and 700 bold)
+    pub weight: Option<u16>,
+}
+
+/// A paper size for PDF pages
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PageSize {
+    /// ISO A4 (210 x 297 mm)
+    A4,
+    /// US Letter (8.5 x 11 in)
+    Letter,
+}
+
+impl PageSize {
+    /// The width and height of a portrait page, in points
+    pub fn size(self) -> (f32, f32) {
+        match self {
+            PageSize::A4 => (595.0, 842.0),
+            PageSize::Letter => (612.0, 792.0),
+        }
+    }
+}
+
+/// The built-in diagram stylesheets
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Theme {
+    /// Dark lines and text on a light background
+    #[default]
+    Light,
+    /// Light lines and text on a dark background
+    Dark,
+    /// The Rust Reference's `Rust` theme
+    Rust,
+    /// The Rust Reference's `Coal` theme
+    Coal,
+    /// The Rust Reference's `Navy` theme
+    Navy,
+    /// The Rust Reference's `Ayu` theme
+    Ayu,
+    /// Black lines and text on white, telling the kinds of boxes apart by their fill and strokes (hollow terminals,
+    /// filled rule references and dashed built-in rules) rather than colors, for printing
+    Monochrome,
+    /// Black text and thick black lines on white with no tinted fills, and the accents of the options darkened, so
+    /// everything meets the WCAG AA contrast ratios
+    HighContrast,
+    /// The light theme, switching to the dark theme when the viewer prefers a dark color scheme (raster output is
+    /// always light)
+    Auto,
+    /// No stylesheet at all, leaving the appearance to the stylesheet of the embedding page
+    None,
+}
+
+impl Theme {
+    /// The stylesheet for this theme (or `None` if no CSS should be emitted)
+    pub fn stylesheet(self) -> Option<Stylesheet> {
+        match self {
+            Theme::Light | Theme::Auto => Some(Stylesheet::Light),
+            Theme::Dark => Some(Stylesheet::Dark),
+            Theme::Rust => Some(Stylesheet::Rust),
+            Theme::Coal => Some(Stylesheet::Coal),
+            Theme::Navy => Some(Stylesheet::Navy),
+            Theme::Ayu => Some(Stylesheet::Ayu),
+            // Drawn over by the monochrome styling, in CSS raster output supports too
+            Theme::Monochrome | Theme::HighContrast => Some(Stylesheet::LightRendersafe),
+            Theme::None => None,
+        }
+    }
+
+    /// The stylesheet applied on top of `stylesheet` when the viewer prefers a dark color scheme, if the theme follows
+    /// the viewer's preference
+    pub fn dark_stylesheet(self) -> Option<Stylesheet> {
+        match self {
+            Theme::Auto => Some(Stylesheet::Dark),
+            _ => None,
+        }
+    }
+
+    /// The stylesheet for this theme restricted to the CSS that resvg supports, for raster output
+    pub fn render_safe_stylesheet(self) -> Option<Stylesheet> {
+        match self {
+            Theme::Light | Theme::Auto => Some(Stylesheet::LightRendersafe),
+            Theme::Dark => Some(Stylesheet::DarkRendersafe),
+            _ => self.stylesheet(),
+        }
+    }
+}
+
+impl Config {
+    /// Returns true if the rule matches the include filter of this config
+    pub fn includes(&self, rule: &GrammarRule) -> bool {
+        self.include.is_empty()
+            || self
+                .include
+                .iter()
+                .any(|pattern| glob_match(pattern, &rule.name))
+    }
+
+    /// Returns true if references to the rule should be replaced by its expression
+    pub fn inlines(&self, rule: &GrammarRule) -> bool {
+        (self.inline_trivial != InlineTrivial::Off && is_trivial(&rule.expr))
+            || self
+                .inline
+                .iter()
+                .any(|pattern| glob_match(pattern, &rule.name))
+    }
+
+    /// Returns true if the rule should be drawn highlighted
+    pub fn highlights(&self, rule: &GrammarRule) -> bool {
+        self.highlights_name(&rule.name)
+    }
+
+    /// Returns true if the rule with this name (and references to it) should be drawn highlighted
+    pub fn highlights_name(&self, name: &str) -> bool {
+        self.highlight
+            .iter()
+            .any(|pattern| glob_match(pattern, name))
+    }
+
+    /// Returns true if the name of a rule matches the hide pattern
+    pub fn hides(&self, name: &str) -> bool {
+        self.hide_pattern
+            .as_ref()
+            .is_some_and(|pattern| glob_match(pattern, name))
+    }
+
+    /// Clamps the width (in pixels) of a raster image of a diagram `width` by `height` pixels, so the image has at most
+    /// `max_pixels` pixels
+    pub fn clamp_raster_width(&self, raster_width: u32, width: i64, height: i64) -> u32 {
+        let Some(max_pixels) = self.max_pixels else {
+            return raster_width;
+        };
+        let aspect = height.max(1) as f64 / width.max(1) as f64;
+        let max_width = (max_pixels as f64 / aspect).sqrt().floor().max(1.0) as u32;
+        raster_width.min(max_width)
+    }
+
+    /// A copy of the config for drawing the rules of the grammar, which knows the rules the grammar defines
+    pub(crate) fn for_grammar(&self, grammar: &Grammar) -> Config {
+        Config {
+            defined: grammar.rules.iter().map(|rule| rule.name.clone()).collect(),
+            ..self.clone()
+        }
+    }
+
+    /// Returns true if a reference to the rule is to one of pest's built-in rules rather than one the grammar defines
+    pub(crate) fn is_builtin(&self, rule: &str) -> bool {
+        lint::is_builtin(rule) && !self.defined.contains(rule)
+    }
+
+    /// The URL that references to the rule link to, if there is a link template. References left unexpanded by
+    /// `expand_depth` (or to the rules made by `factor_repeated`) link to the diagram of their rule otherwise.
+    pub fn link(&self, rule: &str) -> Option<String> {
+        match &self.link_template {
+            Some(template) => Some(template.replace("{rule}", rule)),
+            // Builtins have no diagrams to link to
+            None if self.is_builtin(rule) => None,
+            None => {
+                (self.expand_depth.is_some() || self.factor_repeated).then(|| format!("#{rule}"))
+            }
+        }
+    }
+
+    /// The path of the image of the rule's diagram, if there is an image template
+    pub fn image(&self, rule: &str) -> Option<String> {
+        self.image_template
+            .as_ref()
+            .map(|template| template.replace("{rule}", rule))
+    }
+
+    /// The label of a rule: its name, prefixed with its number if numbering is on (factored rules have no number)
+    pub fn label(&self, rule: &GrammarRule) -> String {
+        match rule.number {
+            Some(number) if self.numbering => format!("{number}. {}", rule.name),
+            _ => rule.name.clone(),
+        }
+    }
+
+    /// The id (or class) `name` is given in the SVG, after any prefix
+    pub fn id(&self, name: &str) -> String {
+        format!("{}{name}", self.id_prefix.as_deref().unwrap_or_default())
+    }
+
+    /// The title to use for the grammar, if there is one
+    pub fn title<'a>(&'a self, grammar: &'a Grammar) -> Option<&'a str> {
+        self.title
+            .as_deref()
+            .or(grammar.docs.first().map(String::as_str))
+    }
+
+    /// The description of the grammar: its doc comments, less the line used as the title (if there are any others)
+    pub fn description(&self, grammar: &Grammar) -> Option<String> {
+        let skip = usize::from(self.title.is_none());
+        let description = grammar.docs[skip.min(grammar.docs.len())..].join("\n");
+        let description = description.trim();
+        (!description.is_empty()).then(|| description.to_string())
+    }
+
+    /// Returns a copy of the grammar containing only the rules selected by this config
+    pub fn select(&self, grammar: &Grammar) -> Grammar {
+        let reachable = self
+            .start_rule
+            .as_ref()
+            .map(|start| DependencyGraph::new(grammar).reachable(start));
+
+        let mut expanded = HashMap::new();
+        if self.expand_depth.is_some() {
+            for rule in &grammar.rules {
+                expanded.entry(rule.name.as_str()).or_insert(&rule.expr);
+            }
+        }
+
+        let taken = grammar
+            .rules
+            .iter()
+            .map(|rule| rule.name.as_str())
+            .collect::<HashSet<_>>();
+
+        let mut inlined = HashMap::new();
+        for rule in grammar.rules.iter().filter(|rule| self.inlines(rule)) {
+            let mut expr = rule.expr.clone();
+            if self.inline_trivial == InlineTrivial::Named {
+                if let Expr::Str { inlined_from, .. }
+                | Expr::InsensitiveStr { inlined_from, .. }
+                | Expr::Range { inlined_from, .. } = &mut expr
+                {
+                    *inlined_from = Some(rule.name.clone());
+                }
+            }
+            // Pest doesn't allow duplicate rules, but if there are any the first one wins
+            inlined.entry(rule.name.as_str()).or_insert(expr);
+        }
+
+        // A rule defined by the grammar is never hidden as a built-in, whatever its name
+        let defined: HashSet<_> = grammar
+            .rules
+            .iter()
+            .map(|rule| rule.name.as_str())
+            .collect();
+        let hidden = |name: &str| {
+            (self.hide_builtins && lint::is_builtin(name) && !defined.contains(name))
+                || self.hides(name)
+        };
+
+        Grammar {
+            docs: grammar.docs.clone(),
+            rules: grammar
+                .rules
+                .iter()
+                .filter(|rule| self.includes(rule) && !self.inlines(rule))
+                .filter(|rule| !self.hides(&rule.name))
+                .filter(|rule| {
+                    reachable
+                        .as_ref()
+                        .is_none_or(|names| names.contains(&rule.name))
+                })
+                .map(|rule| {
+                    let mut rule = rule.clone();
+                    inline_refs(&mut rule.expr, &inlined, &mut Vec::new());
+                    if let Some(depth) = self.expand_depth {
+                        expand_refs(
+                            &mut rule.expr,
+                            &expanded,
+                            depth,
+                            &mut vec![rule.name.as_str()],
+                        );
+                    }
+                    hide_refs(&mut rule.expr, &hidden);
+                    rule
+                })
+                .flat_map(|rule| {
+                    if self.factor_repeated {
+                        factor_repeated(rule, &taken)
+                    } else {
+                        vec![rule]
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Fills in the placeholders of a footer template: `{file}` (the file name of the grammar, or `grammar` without one),
+/// `{hash}` (a checksum of the grammar's rules and doc comments, which changes whenever they do), `{date}` (today's UTC
+/// date, or that of the `SOURCE_DATE_EPOCH` environment variable for reproducible builds) and `{version}` (the version
+/// of this crate)
+pub fn expand_footer(template: &str, grammar: &Grammar, file_name: Option<&str>) -> String {
+    let epoch = std::env::var("SOURCE_DATE_EPOCH").ok();
+    fill_footer(template, grammar, file_name, timestamp(epoch.as_deref()))
+}
+
+/// Fills in the placeholders of a footer template as `expand_footer` does, dating it at `secs` since the Unix epoch
+fn fill_footer(template: &str, grammar: &Grammar, file_name: Option<&str>, secs: u64) -> String {
+    let mut hasher = crc32fast::Hasher::new();
+    for text in grammar
+        .docs
+        .iter()
+        .chain(grammar.rules.iter().map(|rule| &rule.source))
+    {
+        hasher.update(text.as_bytes());
+        hasher.update(b"\n");
+    }
+    template
+        .replace("{file}", file_name.unwrap_or("grammar"))
+        .replace("{hash}", &format!("{:08x}", hasher.finalize()))
+        .replace("{date}", &date(secs))
+        .replace("{version}", env!("CARGO_PKG_VERSION"))
+}
+
+/// The seconds since the Unix epoch of a `SOURCE_DATE_EPOCH` value, or of now if there is no valid one
+fn timestamp(source_date_epoch: Option<&str>) -> u64 {
+    source_date_epoch
+        .and_then(|epoch| epoch.trim().parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|since| since.as_secs())
+                .unwrap_or_default()
+        })
+}
+
+/// The UTC date of a count of seconds since the Unix epoch as `YYYY-MM-DD`
+fn date(secs: u64) -> String {
+    // The civil date of a count of days since 1970-01-01 (Howard Hinnant's `civil_from_days`)
+    let days = (secs / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Matches `text` against a pattern containing `*` and `?` wildcards
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` seen and the text position it was tried at, for backtracking
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                // Let the last star swallow one more character and try again
+                Some((star_p, star_t)) => {
+                    star = Some((star_p, star_t + 1));
+                    p = star_p + 1;
+                    t = star_t + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    // Any remaining pattern must be stars
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::Span;
+
+    #[test]
+    fn glob_match_wildcards() {
+        assert!(glob_match("expr", "expr"));
+        assert!(!glob_match("expr", "exprs"));
+        assert!(glob_match("expr_*", "expr_list"));
+        assert!(glob_match("expr_*", "expr_"));
+        assert!(glob_match("*_list", "expr_list"));
+        assert!(glob_match("e?pr", "expr"));
+        assert!(!glob_match("e?pr", "epr"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("?", ""));
+    }
+
+    #[test]
+    fn glob_match_backtracks() {
+        assert!(glob_match("*a*b", "xaxxab"));
+        assert!(glob_match("a*b*c", "abbbc"));
+        assert!(!glob_match("a*b*c", "abbbcd"));
+        assert!(glob_match("*é", "café"));
+    }
+
+    /// The rules of a grammar selected by a config
+    fn select(config: &Config, src: &str) -> Vec<GrammarRule> {
+        config.select(&Grammar::parse(src).unwrap().0).rules
+    }
+
+    fn str(value: &str) -> Expr {
+        Expr::Str {
+            value: value.to_string(),
+            span: Span::default(),
+            inlined_from: None,
+        }
+    }
+
+    const EMPTY: Expr = Expr::Sequence { terms: Vec::new() };
+
+    #[test]
+    fn hidden_builtins_keep_structure() {
+        let config = Config {
+            hide_builtins: true,
+            ..Config::default()
+        };
+        let rules = select(
+            &config,
+            r#"value = { ("a" | ASCII_DIGIT)* ~ ASCII_ALPHA ~ "b" ~ ANY+ }"#,
+        );
+        assert!(rules[0].expr.same_structure(&Expr::Sequence {
+            terms: vec![
+                Expr::Repeat {
+                    expr: Box::new(Expr::Choice {
+                        choices: vec![str("a"), EMPTY],
+                    }),
+                },
+                str("b"),
+                Expr::RepeatOnce {
+                    expr: Box::new(EMPTY),
+                },
+            ],
+        }));
+    }
+
+    #[test]
+    fn defined_rules_are_never_hidden_as_builtins() {
+        let config = Config {
+            hide_builtins: true,
+            ..Config::default()
+        };
+        let rules = select(
+            &config,
+            r#"value = { NUMBER ~ ASCII_DIGIT } NUMBER = { "1" }"#,
+        );
+        let names: Vec<_> = rules.iter().map(|rule| rule.name.as_str()).collect();
+        assert_eq!(names, ["value", "NUMBER"]);
+        assert!(rules[0].expr.same_structure(&Expr::Ident {
+            name: "NUMBER".to_string(),
+            span: Span::default(),
+            recursive: false,
+        }));
+    }
+
+    #[test]
+    fn hide_pattern_hides_matching_rules() {
+        let config = Config {
+            hide_pattern: Some("tok_*".to_string()),
+            ..Config::default()
+        };
+        let rules = select(
+            &config,
+            r#"value = { tok_a ~ "x" | tok_b } tok_a = { "a" } tok_b = { "b" }"#,
+        );
+        let names: Vec<_> = rules.iter().map(|rule| rule.name.as_str()).collect();
+        assert_eq!(names, ["value"]);
+        assert!(rules[0].expr.same_structure(&Expr::Choice {
+            choices: vec![str("x"), EMPTY],
+        }));
+    }
+
+    /// The first rule of a grammar
+    fn rule(source: &str) -> GrammarRule {
+        Grammar::parse(source).unwrap().0.rules.remove(0)
+    }
+
+    #[test]
+    fn factor_repeated_sub_expressions() {
+        let source = "range = { (\"-\"? ~ ASCII_DIGIT+ ~ (\".\" ~ ASCII_DIGIT+)?) ~ \"..\" ~ \
+                      (\"-\"? ~ ASCII_DIGIT+ ~ (\".\" ~ ASCII_DIGIT+)?) }";
+        let taken = HashSet::from(["range", "range_1"]);
+        let rules = factor_repeated(rule(source), &taken);
+
+        let names: Vec<_> = rules.iter().map(|rule| rule.name.as_str()).collect();
+        assert_eq!(names, ["range", "range_2"]);
+        assert!(rules[0]
+            .expr
+            .same_structure(&rule("r = { range_2 ~ \"..\" ~ range_2 }").expr));
+        assert!(rules[1]
+            .expr
+            .same_structure(&rule("r = { \"-\"? ~ ASCII_DIGIT+ ~ (\".\" ~ ASCII_DIGIT+)? }").expr));
+        assert_eq!(rules[1].docs, ["Repeated in `range`"]);
+        assert_eq!(rules[1].line, None);
+        assert_eq!(rules[1].number, None);
+        assert_eq!(rules[1].span, Span { start: 11, end: 51 });
+    }
+
+    #[test]
+    fn factor_repeated_leaves_small_repeats() {
+        let pair = rule("pair = { key ~ \"=\" ~ key }");
+        let rules = factor_repeated(pair.clone(), &HashSet::new());
+        assert_eq!(rules.len(), 1);
+        assert!(rules[0].expr.same_structure(&pair.expr));
+    }
+
+    #[test]
+    fn date_of_timestamps() {
+        assert_eq!(date(0), "1970-01-01");
+        assert_eq!(date(86_399), "1970-01-01");
+        assert_eq!(date(951_782_400), "2000-02-29");
+        assert_eq!(date(951_868_800), "2000-03-01");
+        assert_eq!(date(1_709_164_800), "2024-02-29");
+        assert_eq!(date(1_735_689_599), "2024-12-31");
+        assert_eq!(date(1_735_689_600), "2025-01-01");
+    }
+
+    #[test]
+    fn timestamp_of_source_date_epoch() {
+        assert_eq!(timestamp(Some("1709164800")), 1_709_164_800);
+        assert_eq!(timestamp(Some(" 1709164800\n")), 1_709_164_800);
+        // Without a valid value, it's now
+        assert!(timestamp(Some("yesterday")) > 1_709_164_800);
+        assert!(timestamp(None) > 1_709_164_800);
+    }
+
+    #[test]
+    fn fill_footer_placeholders() {
+        let grammar = Grammar::parse("a = { \"a\" }").unwrap().0;
+        assert_eq!(
+            fill_footer(
+                "{file} {hash} {date} {version}",
+                &grammar,
+                Some("a.pest"),
+                1_709_164_800
+            ),
+            format!("a.pest c9c1639e 2024-02-29 {}", env!("CARGO_PKG_VERSION"))
+        );
+        assert_eq!(fill_footer("{file}", &grammar, None, 0), "grammar");
+    }
+
+    #[test]
+    fn fill_footer_hash_covers_docs() {
+        let hash = |source| fill_footer("{hash}", &Grammar::parse(source).unwrap().0, None, 0);
+        assert_ne!(hash("a = { \"a\" }"), hash("//! Docs\na = { \"a\" }"));
+        assert_ne!(hash("a = { \"a\" }"), hash("a = { \"b\" }"));
+    }
+}
@@ -0,0 +1,85 @@
+//! Rule dependency (reference) analysis
+
+use std::collections::{HashMap, HashSet};
+
+use crate::ir::{Expr, Grammar};
+
+/// The rule reference graph of a grammar
+#[derive(Clone, Debug)]
+pub struct DependencyGraph {
+    /// Rule names in source order, each with the identifiers it references (in order of first reference). References
+    /// to built-ins and undefined rules are included.
+    rules: Vec<(String, Vec<String>)>,
+    /// Lookup of rule name to its index in `rules`
+    index: HashMap<String, usize>,
+}
+
+impl DependencyGraph {
+    /// Builds the dependency graph of a grammar
+    pub fn new(grammar: &Grammar) -> Self {
+        let rules: Vec<_> = grammar
+            .rules
+            .iter()
+            .map(|rule| {
+                let mut refs: Vec<String> = Vec::new();
+                rule.expr.walk(&mut |expr| {
+                    if let Expr::Ident { name, .. } = expr {
+                        if !refs.contains(name) {
+                            refs.push(name.clone());
+                        }
+                    }
+                });
+                (rule.name.clone(), refs)
+            })
+            .collect();
+
+        let index = rules
+            .iter()
+            .enumerate()
+            .map(|(idx, (name, _))| (name.clone(), idx))
+            .collect();
+
+        Self { rules, index }
+    }
+
+    /// Returns true if the grammar defines a rule with this name
+    pub fn contains(&self, rule: &str) -> bool {
+        self.index.contains_key(rule)
+    }
+
+    /// The identifiers referenced by a rule (empty if the rule doesn't exist)
+    pub fn references(&self, rule: &str) -> &[String] {
+        self.index
+            .get(rule)
+            .map(|&idx| self.rules[idx].1.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// The rules (in source order) that reference `rule`
+    pub fn referenced_by<'a>(&'a self, rule: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.iter()
+            .filter(move |(_, refs)| refs.iter().any(|name| name == rule))
+            .map(|(name, _)| name)
+    }
+
+    /// Iterates over each rule (in source order) along with the identifiers it references
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &[String])> {
+        self.rules
+            .iter()
+            .map(|(name, refs)| (name.as_str(), refs.as_slice()))
+    }
+
+    /// The names of every rule reachable from `start` (including `start` itself, if it exists)
+    pub fn reachable(&self, start: &str) -> HashSet<String> {
+        let mut seen = HashSet::new();
+        let mut pending = vec![start];
+
+        while let Some(rule) = pending.pop() {
+            if self.contains(rule) && seen.insert(rule.to_string()) {
+                pending.extend(self.references(rule).iter().map(String::as_str));
+            }
+        }
+
+        seen
+    }
+}
@@ -0,0 +1,145 @@
+//! Selection and embedding of the font used by diagram text
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use fontdb::{Database, Family, Query, Style, Weight};
+
+use crate::{
+    config::{Config, TextFont},
+    Error,
+};
+
+/// The faces embedded for each font: regular text, bold (nonterminals) and italic (comments)
+const FACES: [(Weight, Style); 3] = [
+    (Weight::NORMAL, Style::Normal),
+    (Weight::BOLD, Style::Normal),
+    (Weight::NORMAL, Style::Italic),
+];
+
+/// The CSS selecting (and, if requested, embedding) the font of the config, if it has one
+pub(crate) fn font_css(config: &Config) -> Result<Option<String>, Error> {
+    if !config.embed_fonts {
+        return Ok(config.font.as_deref().map(family_css));
+    }
+
+    let mut db = Database::new();
+    db.load_system_fonts();
+
+    let family = match &config.font {
+        Some(name) => name.clone(),
+        None => monospace_family(&db).ok_or_else(|| {
+            Error::Render(
+                "Unable to find a monospace font to embed (pick one with the font option)".into(),
+            )
+        })?,
+    };
+    let mut embedded = Vec::new();
+    let mut css = String::new();
+
+    for (weight, style) in FACES {
+        let query = Query {
+            families: &[Family::Name(&family)],
+            weight,
+            style,
+            ..Query::default()
+        };
+        // A missing bold or italic face falls back to the closest one, which may already be embedded
+        let Some(id) = db.query(&query).filter(|id| !embedded.contains(id)) else {
+            continue;
+        };
+        embedded.push(id);
+
+        // Panic safety: The ID was just returned by the database
+        let face = db.face(id).expect("queried face");
+        // Browsers can't load a face out of a font collection
+        if face.index != 0 {
+            continue;
+        }
+        let Some(data) = db.with_face_data(id, |data, _| data.to_vec()) else {
+            continue;
+        };
+        if data.starts_with(b"ttcf") {
+            continue;
+        }
+
+        let format = if data.starts_with(b"OTTO") {
+            "otf"
+        } else {
+            "ttf"
+        };
+        let style = match face.style {
+            Style::Normal => "normal",
+            Style::Italic => "italic",
+            Style::Oblique => "oblique",
+        };
+        css.push_str(&format!(
+            "@font-face {{\nfont-family: {};\nfont-weight: {};\nfont-style: {style};\n\
+             src: url(data:font/{format};base64,{});\n}}\n",
+            quote(&family),
+            face.weight.0,
+            STANDARD.encode(&data)
+        ));
+    }
+
+    if css.is_empty() {
+        return Err(Error::Render(format!(
+            "Unable to find an embeddable font named '{family}'"
+        )));
+    }
+    css.push_str(&family_css(&family));
+    Ok(Some(css))
+}
+
+/// The family of the system's default monospace font, or else of any installed monospace font
+fn monospace_family(db: &Database) -> Option<String> {
+    let id = db
+        .query(&Query {
+            families: &[Family::Monospace],
+            ..Query::default()
+        })
+        .or_else(|| db.faces().find(|face| face.monospaced).map(|face| face.id))?;
+    db.face(id).map(|face| face.families[0].0.clone())
+}
+
+/// The CSS setting the font family of all diagram text
+fn family_css(family: &str) -> String {
+    format!(
+        "svg.railroad text,\nsvg.railroad text.comment {{\nfont-family: {}, monospace;\n}}\n",
+        quote(family)
+    )
+}
+
+/// The CSS setting the fonts of the kinds of diagram text the config sets them for, if any
+pub(crate) fn text_css(config: &Config) -> Option<String> {
+    let kinds = [
+        ("svg.railroad .terminal text", &config.terminal_font),
+        (
+            "svg.railroad .nonterminal text,\nsvg.railroad .builtin text",
+            &config.nonterminal_font,
+        ),
+        ("svg.railroad text.comment", &config.comment_font),
+    ];
+    let css = kinds
+        .into_iter()
+        .filter(|(_, font)| **font != TextFont::default())
+        .map(|(selector, font)| {
+            let mut css = format!("{selector} {{\n");
+            if let Some(family) = &font.family {
+                css.push_str(&format!("font-family: {}, monospace;\n", quote(family)));
+            }
+            if let Some(size) = font.size {
+                css.push_str(&format!("font-size: {size}px;\n"));
+            }
+            if let Some(weight) = font.weight {
+                css.push_str(&format!("font-weight: {weight};\n"));
+            }
+            css.push_str("}\n");
+            css
+        })
+        .collect::<String>();
+    (!css.is_empty()).then_some(css)
+}
+
+/// A family name as a CSS string (dropping any characters that would need escaping)
+fn quote(family: &str) -> String {
+    format!("\"{}\"", family.replace(['"', '\\', '<', '&'], ""))
+}
@@ -0,0 +1,741 @@
+//! An intermediate representation (IR) of a pest grammar. It is decoupled from the pest parse tree so that every
+//! backend works from the same, already validated, structure.
+
+use std::mem;
+
+use pest::{iterators::Pairs, Parser};
+use serde::Serialize;
+
+use crate::{Error, PestParser, Rule, Warning};
+
+/// A parsed pest grammar
+#[derive(Clone, Debug, Serialize)]
+pub struct Grammar {
+    /// The grammar level (`//!`) doc comments
+    pub docs: Vec<String>,
+    /// The grammar rules in source order
+    pub rules: Vec<GrammarRule>,
+}
+
+/// A single rule of a grammar
+#[derive(Clone, Debug, Serialize)]
+pub struct GrammarRule {
+    /// The rule identifier
+    pub name: String,
+    /// The rule modifier (silent, atomic, etc.)
+    pub modifier: Modifier,
+    /// The line (`///`) doc comments directly preceding the rule
+    pub docs: Vec<String>,
+    /// The rule body
+    pub expr: Expr,
+    /// The (1-based) source line of the rule identifier (`None` for the rules made by factoring sub-expressions out of
+    /// a rule, which aren't in the source)
+    pub line: Option<usize>,
+    /// The (1-based) position of the rule in its grammar, which stays the same when only some rules are rendered
+    /// (`None` for the rules made by factoring sub-expressions out of a rule)
+    pub number: Option<usize>,
+    /// The source text of the rule, from its identifier to its closing brace
+    pub source: String,
+    /// The byte range of `source` in the grammar file (of the sub-expression it replaces, for factored rules)
+    #[serde(skip)]
+    pub span: Span,
+    /// The rules pest implicitly matches between the elements of this rule: `WHITESPACE` and `COMMENT`, if the grammar
+    /// defines them and this rule isn't atomic (or one of them)
+    pub implicit: Vec<String>,
+}
+
+/// A byte range of the grammar source
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl From<pest::Span<'_>> for Span {
+    fn from(span: pest::Span) -> Self {
+        Span {
+            start: span.start(),
+            end: span.end(),
+        }
+    }
+}
+
+/// The modifier of a grammar rule
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Modifier {
+    #[default]
+    Normal,
+    Silent,
+    Atomic,
+    CompoundAtomic,
+    NonAtomic,
+}
+
+impl Modifier {
+    /// A human readable name of the modifier (or `None` for normal rules)
+    pub fn label(self) -> Option<&'static str> {
+        match self {
+            Modifier::Normal => None,
+            Modifier::Silent => Some("silent"),
+            Modifier::Atomic => Some("atomic"),
+            Modifier::CompoundAtomic => Some("compound atomic"),
+            Modifier::NonAtomic => Some("non-atomic"),
+        }
+    }
+}
+
+/// A rule expression
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Expr {
+    /// Ordered choice (`a | b`)
+    Choice { choices: Vec<Expr> },
+    /// Sequence (`a ~ b`). An empty sequence only occurs when every term was unsupported.
+    Sequence { terms: Vec<Expr> },
+    /// A reference to another rule (or a built-in)
+    Ident {
+        name: String,
+        #[serde(skip)]
+        span: Span,
+        /// Whether this references the rule whose diagram it is drawn in, which is only known when drawing
+        #[serde(skip)]
+        recursive: bool,
+    },
+    /// A string literal, with escapes left as written
+    #[serde(rename = "string")]
+    Str {
+        value: String,
+        #[serde(skip)]
+        span: Span,
+        /// The trivial rule (whose body is only this terminal) a reference to which this replaces, when drawn with the
+        /// rule's name
+        #[serde(skip)]
+        inlined_from: Option<String>,
+    },
+    /// A case insensitive string literal (`^"..."`), with escapes left as written
+    #[serde(rename = "insensitive_string")]
+    InsensitiveStr {
+        value: String,
+        #[serde(skip)]
+        span: Span,
+        #[serde(skip)]
+        inlined_from: Option<String>,
+    },
+    /// A character range (`'a'..'z'`), with escapes left as written
+    Range {
+        start: String,
+        end: String,
+        #[serde(skip)]
+        span: Span,
+        #[serde(skip)]
+        inlined_from: Option<String>,
+    },
+    /// Optional (`a?`)
+    Optional { expr: Box<Expr> },
+    /// Zero or more (`a*`)
+    Repeat { expr: Box<Expr> },
+    /// One or more (`a+`)
+    RepeatOnce { expr: Box<Expr> },
+    /// Bounded repetition (`a{n}`, `a{n,}`, `a{,m}` and `a{n,m}`). A `max` of `None` means unbounded.
+    RepeatRange {
+        expr: Box<Expr>,
+        min: u32,
+        max: Option<u32>,
+    },
+    /// Positive lookahead (`&a`)
+    PositivePredicate { expr: Box<Expr> },
+    /// Negative lookahead (`!a`)
+    NegativePredicate { expr: Box<Expr> },
+    /// Matching an expression and pushing what it matched onto the stack (`PUSH(a)`)
+    Push { expr: Box<Expr> },
+    /// Matching a slice of the stack (`PEEK[start..end]`), from the bottom for positive indices and from the top for
+    /// negative ones. A missing `start` or `end` means the bottom or the top of the stack.
+    PeekSlice {
+        start: Option<i32>,
+        end: Option<i32>,
+        #[serde(skip)]
+        span: Span,
+    },
+}
+
+impl Expr {
+    /// The inner expression of wrapping expressions (optional, repeats, predicates and pushes)
+    pub fn inner(&self) -> Option<&Expr> {
+        match self {
+            Expr::Optional { expr }
+            | Expr::Repeat { expr }
+            | Expr::RepeatOnce { expr }
+            | Expr::RepeatRange { expr, .. }
+            | Expr::PositivePredicate { expr }
+            | Expr::NegativePredicate { expr }
+            | Expr::Push { expr } => Some(expr),
+            _ => None,
+        }
+    }
+
+    /// Where a terminal or rule reference is in the grammar source (`None` for other expressions, which aren't drawn as
+    /// a box of their own)
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Expr::Ident { span, .. }
+            | Expr::Str { span, .. }
+            | Expr::InsensitiveStr { span, .. }
+            | Expr::Range { span, .. }
+            | Expr::PeekSlice { span, .. } => Some(*span),
+            _ => None,
+        }
+    }
+
+    /// The direct child expressions of this expression
+    pub fn children(&self) -> &[Expr] {
+        match self {
+            Expr::Choice { choices } => choices,
+            Expr::Sequence { terms } => terms,
+            _ => self.inner().map(std::slice::from_ref).unwrap_or_default(),
+        }
+    }
+
+    /// The direct child expressions of this expression, mutably
+    pub fn children_mut(&mut self) -> &mut [Expr] {
+        match self {
+            Expr::Choice { choices } => choices,
+            Expr::Sequence { terms } => terms,
+            Expr::Optional { expr }
+            | Expr::Repeat { expr }
+            | Expr::RepeatOnce { expr }
+            | Expr::RepeatRange { expr, .. }
+            | Expr::PositivePredicate { expr }
+            | Expr::NegativePredicate { expr }
+            | Expr::Push { expr } => std::slice::from_mut(expr),
+            Expr::Ident { .. }
+            | Expr::Str { .. }
+            | Expr::InsensitiveStr { .. }
+            | Expr::Range { .. }
+            | Expr::PeekSlice { .. } => &mut [],
+        }
+    }
+
+    /// Returns true if the two expressions are the same apart from their spans, so the same expression written in two
+    /// places of the grammar is recognized as such (`==` compares the spans too)
+    pub fn same_structure(&self, other: &Expr) -> bool {
+        match (self, other) {
+            (
+                Expr::Ident {
+                    name, recursive, ..
+                },
+                Expr::Ident {
+                    name: other_name,
+                    recursive: other_recursive,
+                    ..
+                },
+            ) => name == other_name && recursive == other_recursive,
+            (
+                Expr::Str {
+                    value,
+                    inlined_from,
+                    ..
+                },
+                Expr::Str {
+                    value: other_value,
+                    inlined_from: other_inlined_from,
+                    ..
+                },
+            )
+            | (
+                Expr::InsensitiveStr {
+                    value,
+                    inlined_from,
+                    ..
+                },
+                Expr::InsensitiveStr {
+                    value: other_value,
+                    inlined_from: other_inlined_from,
+                    ..
+                },
+            ) => value == other_value && inlined_from == other_inlined_from,
+            (
+                Expr::Range {
+                    start,
+                    end,
+                    inlined_from,
+                    ..
+                },
+                Expr::Range {
+                    start: other_start,
+                    end: other_end,
+                    inlined_from: other_inlined_from,
+                    ..
+                },
+            ) => start == other_start && end == other_end && inlined_from == other_inlined_from,
+            (
+                Expr::PeekSlice { start, end, .. },
+                Expr::PeekSlice {
+                    start: other_start,
+                    end: other_end,
+                    ..
+                },
+            ) => start == other_start && end == other_end,
+            (
+                Expr::RepeatRange { expr, min, max },
+                Expr::RepeatRange {
+                    expr: other_expr,
+                    min: other_min,
+                    max: other_max,
+                },
+            ) => min == other_min && max == other_max && expr.same_structure(other_expr),
+            _ => {
+                mem::discriminant(self) == mem::discriminant(other)
+                    && same_structures(self.children(), other.children())
+            }
+        }
+    }
+
+    /// Calls `f` on this expression and then on every nested expression (depth first, in source order)
+    pub fn walk<'a>(&'a self, f: &mut impl FnMut(&'a Expr)) {
+        f(self);
+        for child in self.children() {
+            child.walk(f);
+        }
+    }
+}
+
+/// Returns true if the two lists of expressions are the same apart from their spans (see [`Expr::same_structure`])
+pub(crate) fn same_structures(exprs: &[Expr], others: &[Expr]) -> bool {
+    exprs.len() == others.len()
+        && exprs
+            .iter()
+            .zip(others)
+            .all(|(expr, other)| expr.same_structure(other))
+}
+
+impl Grammar {
+    /// Parses a pest grammar. It also returns a list of unsupported warnings for the pest rules that aren't supported
+    /// (these are left out of the IR).
+    pub fn parse(input: &str) -> Result<(Grammar, Vec<Warning>), Error> {
+        let mut unsupported_warnings = Vec::new();
+
+        let pairs = PestParser::parse(Rule::grammar_rules, input)?;
+
+        let mut grammar = Grammar {
+            docs: Vec::new(),
+            rules: Vec::with_capacity(pairs.len()),
+        };
+        // Line docs are attached to the rule that follows them
+        let mut line_docs = Vec::new();
+
+        // Loop over all top level elements
+        for pair in pairs {
+            match pair.as_rule() {
+                Rule::grammar_rule => {
+                    let source = pair.as_str();
+                    let span = pair.as_span().into();
+                    let mut rule_pairs = pair.into_inner();
+
+                    // Panic safety: We know that the first element is either a line doc or an identifier from grammar
+                    let first_pair = rule_pairs.next().expect("line doc or identifier");
+
+                    match first_pair.as_rule() {
+                        Rule::line_doc => {
+                            line_docs.push(doc_text(first_pair.into_inner()));
+                        }
+                        Rule::identifier => {
+                            let (line, _) = first_pair.line_col();
+                            let (modifier, expr, warnings) = make_rule(rule_pairs);
+                            unsupported_warnings.extend(warnings);
+
+                            grammar.rules.push(GrammarRule {
+                                name: first_pair.as_str().into(),
+                                modifier,
+                                docs: mem::take(&mut line_docs),
+                                expr,
+                                line: Some(line),
+                                number: Some(grammar.rules.len() + 1),
+                                source: source.into(),
+                                span,
+                                implicit: Vec::new(),
+                            });
+                        }
+                        rule => unreachable!("Unexpected first rule in grammar rule: {rule:?}"),
+                    }
+                }
+                Rule::grammar_doc => {
+                    grammar.docs.push(doc_text(pair.into_inner()));
+                }
+                Rule::EOI => {
+                    // No op - nothing to do
+                }
+                rule => unreachable!("Unexpected rule in top level grammar: {rule:?}"),
+            }
+        }
+
+        let implicit: Vec<String> = ["WHITESPACE", "COMMENT"]
+            .into_iter()
+            .filter(|&name| grammar.rules.iter().any(|rule| rule.name == name))
+            .map(String::from)
+            .collect();
+        for rule in &mut grammar.rules {
+            let atomic = matches!(rule.modifier, Modifier::Atomic | Modifier::CompoundAtomic);
+            if !atomic && !matches!(rule.name.as_str(), "WHITESPACE" | "COMMENT") {
+                rule.implicit = implicit.clone();
+            }
+        }
+
+        Ok((grammar, unsupported_warnings))
+    }
+}
+
+fn doc_text(mut pairs: Pairs<Rule>) -> String {
+    // Panic safety: Doc comments always contain an inner doc from grammar
+    pairs.next().expect("inner doc").as_str().into()
+}
+
+fn make_rule(pairs: Pairs<Rule>) -> (Modifier, Expr, Vec<Warning>) {
+    let mut unsupported_warnings = Vec::new();
+    let mut modifier = Modifier::Normal;
+    let mut rule_expr = None;
+
+    for pair in pairs {
+        match pair.as_rule() {
+            Rule::assignment_operator | Rule::opening_brace | Rule::closing_brace => {
+                // No op - nothing to do
+            }
+            Rule::silent_modifier => modifier = Modifier::Silent,
+            Rule::atomic_modifier => modifier = Modifier::Atomic,
+            Rule::compound_atomic_modifier => modifier = Modifier::CompoundAtomic,
+            Rule::non_atomic_modifier => modifier = Modifier::NonAtomic,
+            Rule::expression => {
+                let (expr, warnings) = make_expr(pair.into_inner());
+                unsupported_warnings.extend(warnings);
+                rule_expr = Some(expr);
+            }
+            rule => unreachable!("Unexpected rule in grammar rule: {rule:?}"),
+        }
+    }
+
+    // Panic safety: Every grammar rule has an expression from grammar
+    let expr = rule_expr.expect("rule expression");
+    (modifier, expr, unsupported_warnings)
+}
+
+fn make_repeat(pairs: Pairs<Rule>, expr: Expr) -> Expr {
+    let mut comma_seen = false;
+    let mut min_repeat = None;
+    let mut max_repeat = None;
+
+    for repeat in pairs {
+        match repeat.as_rule() {
+            Rule::opening_brace | Rule::closing_brace => {
+                // No op - nothing to do
+            }
+            Rule::number => {
+                // Panic safety: Guaranteed to be numbers from grammar
+                let number = repeat.as_str().parse().expect("number");
+                if comma_seen {
+                    max_repeat = Some(number);
+                } else {
+                    min_repeat = Some(number);
+                }
+            }
+            Rule::comma => {
+                comma_seen = true;
+            }
+            rule => unreachable!("Unexpected rule in repeat: {rule:?}"),
+        }
+    }
+
+    let (min, max) = match (min_repeat, comma_seen) {
+        // repeat_exact - max same as min
+        (Some(min), false) => (min, Some(min)),
+        // repeat_min/repeat_min_max - max unbounded if not given
+        (Some(min), true) => (min, max_repeat),
+        // repeat_max - min is 0
+        (None, true) => (0, max_repeat),
+        (None, false) => unreachable!("Min and max not set"),
+    };
+
+    Expr::RepeatRange {
+        expr: Box::new(expr),
+        min,
+        max,
+    }
+}
+
+fn make_expr(pairs: Pairs<Rule>) -> (Expr, Vec<Warning>) {
+    let mut unsupported_warnings = Vec::new();
+
+    // Rule choices (or those without a choice operator this will be a single element)
+    let mut choices: Vec<Vec<Expr>> = Vec::new();
+    // Current choice
+    let mut curr_choice: Vec<Expr> = Vec::new();
+
+    for pair in pairs {
+        match pair.as_rule() {
+            Rule::term => {
+                let (term, warnings) = make_term(pair.into_inner());
+                unsupported_warnings.extend(warnings);
+
+                // Term would only not be populated if an unsupported rule was encountered
+                if let Some(term) = term {
+                    curr_choice.push(term);
+                }
+            }
+            Rule::sequence_operator => {
+                // No op - nothing to do
+            }
+            // A leading choice operator is purely cosmetic
+            Rule::choice_operator if choices.is_empty() && curr_choice.is_empty() => {}
+            Rule::choice_operator => {
+                // Store the current sequence and start a new one
+                choices.push(mem::take(&mut curr_choice))
+            }
+            rule => unreachable!("Unexpected rule in expression: {rule:?}"),
+        }
+    }
+
+    // Ensure that the last sequence is stored
+    if !curr_choice.is_empty() {
+        choices.push(curr_choice);
+    }
+
+    // Perform a custom flatten of our choices
+    let mut choices: Vec<_> = choices
+        .into_iter()
+        .map(|mut terms| {
+            // If we only have one element, return it directly, otherwise wrap in a sequence
+            if terms.len() == 1 {
+                terms.remove(0)
+            } else {
+                Expr::Sequence { terms }
+            }
+        })
+        .collect();
+
+    // If we only have one choice, return it directly
+    match choices.len() {
+        0 => (Expr::Sequence { terms: Vec::new() }, unsupported_warnings),
+        1 => (choices.remove(0), unsupported_warnings),
+        _ => (Expr::Choice { choices }, unsupported_warnings),
+    }
+}
+
+fn make_term(pairs: Pairs<Rule>) -> (Option<Expr>, Vec<Warning>) {
+    let mut unsupported_warnings = Vec::new();
+
+    // We might have a prefix and/or postfix operator, so store the term until we are sure
+    let mut term: Option<Expr> = None;
+    // Prefix operators in source order (they apply after all postfix operators)
+    let mut predicates = Vec::new();
+
+    for term_pair in pairs {
+        match term_pair.as_rule() {
+            Rule::identifier => {
+                term = Some(Expr::Ident {
+                    name: term_pair.as_str().into(),
+                    span: term_pair.as_span().into(),
+                    recursive: false,
+                });
+            }
+            Rule::string => {
+                term = Some(Expr::Str {
+                    span: term_pair.as_span().into(),
+                    value: string_value(term_pair.into_inner()),
+                    inlined_from: None,
+                });
+            }
+            Rule::insensitive_string => {
+                let span = term_pair.as_span().into();
+                // Panic safety: Insensitive strings always wrap a string from grammar
+                let string = term_pair.into_inner().next().expect("string");
+                term = Some(Expr::InsensitiveStr {
+                    value: string_value(string.into_inner()),
+                    span,
+                    inlined_from: None,
+                });
+            }
+            Rule::range => {
+                let span = term_pair.as_span().into();
+                let mut chars = term_pair
+                    .into_inner()
+                    .filter(|pair| pair.as_rule() == Rule::character)
+                    .map(|pair| string_value(pair.into_inner()));
+
+                // Panic safety: Ranges always have a start and end character from grammar
+                term = Some(Expr::Range {
+                    start: chars.next().expect("range start"),
+                    end: chars.next().expect("range end"),
+                    span,
+                    inlined_from: None,
+                });
+            }
+            Rule::_push => {
+                // Panic safety: A push always wraps an expression from grammar
+                let expression = term_pair
+                    .into_inner()
+                    .find(|pair| pair.as_rule() == Rule::expression)
+                    .expect("expression");
+                let (expr, warnings) = make_expr(expression.into_inner());
+                unsupported_warnings.extend(warnings);
+                term = Some(Expr::Push {
+                    expr: Box::new(expr),
+                });
+            }
+            Rule::peek_slice => {
+                let span = term_pair.as_span().into();
+                let (mut start, mut end) = (None, None);
+                let mut after_range = false;
+                let mut supported = true;
+                for pair in term_pair.into_inner() {
+                    match pair.as_rule() {
+                        Rule::range_operator => after_range = true,
+                        Rule::integer => {
+                            // The grammar allows any number of digits, but pest only supports indices that fit an i32
+                            let Ok(index) = pair.as_str().parse() else {
+                                let (line, column) = pair.line_col();
+                                unsupported_warnings.push(Warning {
+                                    code: "unsupported",
+                                    message: format!(
+                                        "Unsupported stack slice index: {}",
+                                        pair.as_str()
+                                    ),
+                                    line,
+                                    column,
+                                });
+                                supported = false;
+                                continue;
+                            };
+                            if after_range {
+                                end = Some(index);
+                            } else {
+                                start = Some(index);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                if supported {
+                    term = Some(Expr::PeekSlice { start, end, span });
+                }
+            }
+            Rule::opening_paren | Rule::closing_paren => {
+                // No op - nothing to do
+            }
+            Rule::expression => {
+                let (expr, warnings) = make_expr(term_pair.into_inner());
+                unsupported_warnings.extend(warnings);
+                term = Some(expr);
+            }
+            Rule::repeat_operator => {
+                term = term.map(|expr| Expr::Repeat {
+                    expr: Box::new(expr),
+                });
+            }
+            Rule::repeat_once_operator => {
+                term = term.map(|expr| Expr::RepeatOnce {
+                    expr: Box::new(expr),
+                });
+            }
+            Rule::optional_operator => {
+                term = term.map(|expr| Expr::Optional {
+                    expr: Box::new(expr),
+                });
+            }
+            Rule::positive_predicate_operator | Rule::negative_predicate_operator => {
+                predicates.push(term_pair.as_rule());
+            }
+            Rule::repeat_exact | Rule::repeat_min | Rule::repeat_max | Rule::repeat_min_max => {
+                term = term.map(|expr| make_repeat(term_pair.into_inner(), expr));
+            }
+            _ => {
+                let (line, column) = term_pair.line_col();
+                unsupported_warnings.push(Warning {
+                    code: "unsupported",
+                    message: format!("Unsupported rule in term: {:?}", term_pair.as_rule()),
+                    line,
+                    column,
+                });
+            }
+        }
+    }
+
+    // Term would only not be populated if an unsupported rule was encountered. The predicate closest to the term is
+    // applied first.
+    let term = term.map(|term| {
+        predicates.into_iter().rev().fold(term, |expr, rule| {
+            let expr = Box::new(expr);
+            if rule == Rule::positive_predicate_operator {
+                Expr::PositivePredicate { expr }
+            } else {
+                Expr::NegativePredicate { expr }
+            }
+        })
+    });
+
+    (term, unsupported_warnings)
+}
+
+/// The inner (still escaped) text of a string or character
+fn string_value(pairs: Pairs<Rule>) -> String {
+    pairs
+        .filter(|pair| matches!(pair.as_rule(), Rule::inner_str | Rule::inner_chr))
+        .map(|pair| pair.as_str())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The expression of the single rule `value` with the given body, and the warnings of parsing it
+    fn parse_body(body: &str) -> (Expr, Vec<Warning>) {
+        let (mut grammar, warnings) = Grammar::parse(&format!("value = {{ {body} }}")).unwrap();
+        (grammar.rules.remove(0).expr, warnings)
+    }
+
+    /// The bounds of a stack slice on its own
+    fn slice(body: &str) -> (Option<i32>, Option<i32>) {
+        match parse_body(body) {
+            (Expr::PeekSlice { start, end, .. }, warnings) if warnings.is_empty() => (start, end),
+            other => panic!("not a stack slice: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn push_wraps_its_expression() {
+        let (expr, warnings) = parse_body(r#"PUSH("a" ~ b) ~ POP"#);
+        assert!(warnings.is_empty());
+        let Expr::Sequence { terms } = expr else {
+            panic!("not a sequence: {expr:?}");
+        };
+        let Expr::Push { expr: pushed } = &terms[0] else {
+            panic!("not a push: {:?}", terms[0]);
+        };
+        assert!(matches!(&**pushed, Expr::Sequence { terms } if terms.len() == 2));
+        assert!(matches!(&terms[1], Expr::Ident { name, .. } if name == "POP"));
+    }
+
+    #[test]
+    fn peek_slice_bounds() {
+        assert_eq!(slice("PEEK[1..2]"), (Some(1), Some(2)));
+        assert_eq!(slice("PEEK[0..-1]"), (Some(0), Some(-1)));
+        assert_eq!(slice("PEEK[-2..-1]"), (Some(-2), Some(-1)));
+    }
+
+    #[test]
+    fn peek_slice_open_bounds() {
+        assert_eq!(slice("PEEK[2..]"), (Some(2), None));
+        assert_eq!(slice("PEEK[..-1]"), (None, Some(-1)));
+        assert_eq!(slice("PEEK[..]"), (None, None));
+    }
+
+    #[test]
+    fn peek_slice_out_of_range_bounds_are_unsupported() {
+        let (expr, warnings) = parse_body(r#""a" ~ PEEK[99999999999..]"#);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code, "unsupported");
+        assert!(warnings[0].message.contains("99999999999"));
+        assert_eq!((warnings[0].line, warnings[0].column), (1, 22));
+        // The slice is left out of the diagram
+        assert!(matches!(expr, Expr::Str { value, .. } if value == "a"));
+    }
+}
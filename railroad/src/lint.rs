@@ -0,0 +1,441 @@
+//! Semantic checks of a grammar, beyond what is needed to parse it
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    deps::DependencyGraph,
+    ir::{Expr, Grammar},
+    Warning,
+};
+
+/// How the problems found by a lint are treated
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LintLevel {
+    /// Ignored
+    Allow,
+    /// Reported, but not a failure
+    Warn,
+    /// Reported as an error
+    Deny,
+}
+
+/// A kind of problem that can be found in a grammar
+#[derive(Clone, Copy, Debug)]
+pub struct Lint {
+    /// The code of the warnings produced by the lint
+    pub code: &'static str,
+    pub description: &'static str,
+    pub default_level: LintLevel,
+}
+
+/// Every lint, including the `unsupported` warnings produced while parsing
+pub const LINTS: &[Lint] = &[
+    Lint {
+        code: "unsupported",
+        description: "A construct that can't be drawn (it is left out of the diagram)",
+        default_level: LintLevel::Warn,
+    },
+    Lint {
+        code: "undefined-rule",
+        description: "A reference to a rule that isn't defined (or built in)",
+        default_level: LintLevel::Deny,
+    },
+    Lint {
+        code: "duplicate-rule",
+        description: "A rule defined more than once",
+        default_level: LintLevel::Deny,
+    },
+    Lint {
+        code: "left-recursion",
+        description: "A rule that can reference itself without consuming any input",
+        default_level: LintLevel::Deny,
+    },
+    Lint {
+        code: "infinite-repeat",
+        description:
+            "An unbounded repetition of an expression that can match without consuming any input",
+        default_level: LintLevel::Deny,
+    },
+    Lint {
+        code: "unused-rule",
+        description: "A rule that isn't referenced by any other rule (entry point rules never are)",
+        default_level: LintLevel::Allow,
+    },
+];
+
+impl Lint {
+    /// Looks up a lint by its code
+    pub fn find(code: &str) -> Option<&'static Lint> {
+        LINTS.iter().find(|lint| lint.code == code)
+    }
+}
+
+/// Runs every lint over the grammar, returning the problems found in source order
+pub fn lint(grammar: &Grammar) -> Vec<Warning> {
+    let graph = DependencyGraph::new(grammar);
+    let nullable = nullable_rules(grammar);
+    let mut warnings = Vec::new();
+
+    // Rules carry no column, but they start their line in all but the most unusual formatting. Only factored rules
+    // lack a line, and those are never linted.
+    let mut warn = |code, message, line: Option<usize>| {
+        warnings.push(Warning {
+            code,
+            message,
+            line: line.unwrap_or_default(),
+            column: 1,
+        })
+    };
+
+    let mut defined = HashMap::new();
+    for rule in &grammar.rules {
+        if let Some(line) = defined.insert(rule.name.as_str(), rule.line) {
+            warn(
+                "duplicate-rule",
+                format!(
+                    "Rule '{}' is already defined on line {}",
+                    rule.name,
+                    line.unwrap_or_default()
+                ),
+                rule.line,
+            );
+            // Keep pointing at the first definition
+            defined.insert(rule.name.as_str(), line);
+        }
+
+        // Not taken from the dependency graph, which only holds the last definition of duplicate rules
+        let mut undefined = Vec::new();
+        rule.expr.walk(&mut |expr| {
+            if let Expr::Ident { name, .. } = expr {
+                if !graph.contains(name) && !is_builtin(name) && !undefined.contains(&name) {
+                    undefined.push(name);
+                }
+            }
+        });
+        for name in undefined {
+            warn(
+                "undefined-rule",
+                format!("Rule '{}' references undefined rule '{name}'", rule.name),
+                rule.line,
+            );
+        }
+
+        if graph.referenced_by(&rule.name).next().is_none() {
+            warn(
+                "unused-rule",
+                format!("Rule '{}' is never referenced", rule.name),
+                rule.line,
+            );
+        }
+
+        rule.expr.walk(&mut |expr| {
+            let inner = match expr {
+                Expr::Repeat { expr }
+                | Expr::RepeatOnce { expr }
+                | Expr::RepeatRange {
+                    expr, max: None, ..
+                } => expr,
+                _ => return,
+            };
+            if is_nullable(inner, &nullable) {
+                warn(
+                    "infinite-repeat",
+                    format!(
+                        "Rule '{}' repeats an expression that can match without consuming input, so the repetition \
+                         never ends",
+                        rule.name
+                    ),
+                    rule.line,
+                );
+            }
+        });
+    }
+
+    for (path, line) in left_recursion(grammar, &nullable) {
+        warn(
+            "left-recursion",
+            format!(
+                "Rule '{}' is left recursive ({})",
+                path[0],
+                path.join(" -> ")
+            ),
+            line,
+        );
+    }
+
+    warnings.sort_by_key(|warning| (warning.line, warning.column));
+    warnings
+}
+
+/// The rules pest provides besides the Unicode properties
+const BUILTINS: &[&str] = &[
+    "ANY",
+    "EOI",
+    "SOI",
+    "PEEK",
+    "PEEK_ALL",
+    "POP",
+    "POP_ALL",
+    "DROP",
+    "ASCII_DIGIT",
+    "ASCII_NONZERO_DIGIT",
+    "ASCII_BIN_DIGIT",
+    "ASCII_OCT_DIGIT",
+    "ASCII_HEX_DIGIT",
+    "ASCII_ALPHA_LOWER",
+    "ASCII_ALPHA_UPPER",
+    "ASCII_ALPHA",
+    "ASCII_ALPHANUMERIC",
+    "ASCII",
+    "NEWLINE",
+];
+
+/// Returns true if a rule name is one of pest's built-in rules: a named built-in, or the rule of a Unicode property
+/// (such as `LETTER`)
+pub(crate) fn is_builtin(name: &str) -> bool {
+    BUILTINS.contains(&name)
+        || pest::unicode::unicode_property_names().any(|property| property == name)
+}
+
+/// Finds the rules that can match without consuming any input
+fn nullable_rules(grammar: &Grammar) -> HashSet<&str> {
+    let mut nullable = HashSet::new();
+
+    // Each pass can only add rules, so this ends once a pass adds none
+    loop {
+        let mut changed = false;
+        for rule in &grammar.rules {
+            if !nullable.contains(rule.name.as_str()) && is_nullable(&rule.expr, &nullable) {
+                nullable.insert(rule.name.as_str());
+                changed = true;
+            }
+        }
+        if !changed {
+            break nullable;
+        }
+    }
+}
+
+/// Returns true if the expression can match without consuming any input. Built-in rules are assumed to consume input.
+fn is_nullable(expr: &Expr, nullable: &HashSet<&str>) -> bool {
+    match expr {
+        Expr::Choice { choices } => choices.iter().any(|expr| is_nullable(expr, nullable)),
+        // An empty sequence only contained unsupported constructs, so nothing is known about it
+        Expr::Sequence { terms } if terms.is_empty() => false,
+        Expr::Sequence { terms } => terms.iter().all(|expr| is_nullable(expr, nullable)),
+        Expr::Ident { name, .. } => nullable.contains(name.as_str()),
+        Expr::Str { value, .. } | Expr::InsensitiveStr { value, .. } => value.is_empty(),
+        // Like the other stack operations, which are built-in rules
+        Expr::Range { .. } | Expr::PeekSlice { .. } => false,
+        Expr::Optional { .. }
+        | Expr::Repeat { .. }
+        | Expr::PositivePredicate { .. }
+        | Expr::NegativePredicate { .. } => true,
+        Expr::RepeatRange { min: 0, .. } => true,
+        Expr::RepeatOnce { expr } | Expr::RepeatRange { expr, .. } | Expr::Push { expr } => {
+            is_nullable(expr, nullable)
+        }
+    }
+}
+
+/// Collects the rules that can be referenced by the expression before it has consumed any input
+fn leftmost_refs<'a>(expr: &'a Expr, nullable: &HashSet<&str>, refs: &mut Vec<&'a str>) {
+    match expr {
+        Expr::Choice { choices } => {
+            for expr in choices {
+                leftmost_refs(expr, nullable, refs);
+            }
+        }
+        Expr::Sequence { terms } => {
+            for expr in terms {
+                leftmost_refs(expr, nullable, refs);
+                if !is_nullable(expr, nullable) {
+                    break;
+                }
+            }
+        }
+        Expr::Ident { name, .. } => refs.push(name),
+        Expr::Str { .. } | Expr::InsensitiveStr { .. } | Expr::Range { .. } => {}
+        _ => {
+            for expr in expr.children() {
+                leftmost_refs(expr, nullable, refs);
+            }
+        }
+    }
+}
+
+/// Finds the left recursive cycles of the grammar, returning the rules of each cycle (starting and ending with the
+/// same rule) along with the line of its first rule. Each cycle is only reported for its first rule in source order.
+fn left_recursion<'a>(
+    grammar: &'a Grammar,
+    nullable: &HashSet<&str>,
+) -> Vec<(Vec<&'a str>, Option<usize>)> {
+    let order: HashMap<&str, usize> = grammar
+        .rules
+        .iter()
+        .enumerate()
+        .rev()
+        .map(|(idx, rule)| (rule.name.as_str(), idx))
+        .collect();
+    let leftmost: HashMap<&str, Vec<&str>> = grammar
+        .rules
+        .iter()
+        .map(|rule| {
+            let mut refs = Vec::new();
+            leftmost_refs(&rule.expr, nullable, &mut refs);
+            (rule.name.as_str(), refs)
+        })
+        .collect();
+
+    let mut cycles = Vec::new();
+    for (idx, rule) in grammar.rules.iter().enumerate() {
+        let start = rule.name.as_str();
+        if order[start] != idx {
+            // A duplicate definition
+            continue;
+        }
+
+        // Breadth first, so the shortest cycle is found
+        let mut parents: HashMap<&str, &str> = HashMap::new();
+        let mut pending = VecDeque::from([start]);
+        let mut found = false;
+        while let Some(name) = pending.pop_front() {
+            for &next in leftmost.get(name).into_iter().flatten() {
+                if next == start {
+                    parents.insert(start, name);
+                    found = true;
+                    break;
+                }
+                if leftmost.contains_key(next) && !parents.contains_key(next) {
+                    parents.insert(next, name);
+                    pending.push_back(next);
+                }
+            }
+            if found {
+                break;
+            }
+        }
+        if !found {
+            continue;
+        }
+
+        let mut path = vec![start];
+        let mut name = parents[start];
+        while name != start {
+            path.push(name);
+            name = parents[name];
+        }
+        path.push(start);
+        // The parents lead backwards through the cycle
+        path.reverse();
+
+        // Only report the cycle for its first rule
+        if path.iter().all(|name| order[name] >= idx) {
+            cycles.push((path, rule.line));
+        }
+    }
+
+    cycles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The codes and lines of the warnings for a grammar, leaving out the unused entry point rules
+    fn warnings(source: &str) -> Vec<(&'static str, usize)> {
+        let (grammar, _) = Grammar::parse(source).unwrap();
+        lint(&grammar)
+            .into_iter()
+            .filter(|warning| warning.code != "unused-rule" || warning.line > 1)
+            .map(|warning| (warning.code, warning.line))
+            .collect()
+    }
+
+    #[test]
+    fn clean_grammar() {
+        assert_eq!(
+            warnings("a = { b ~ EOI }\nb = { ASCII_DIGIT+ ~ LETTER* }"),
+            []
+        );
+    }
+
+    #[test]
+    fn duplicate_rule() {
+        let (grammar, _) = Grammar::parse("a = { b }\nb = { \"x\" }\nb = { \"y\" }").unwrap();
+        let warnings = lint(&grammar);
+        let duplicate = warnings
+            .iter()
+            .find(|warning| warning.code == "duplicate-rule")
+            .unwrap();
+        assert_eq!(duplicate.line, 3);
+        assert_eq!(duplicate.message, "Rule 'b' is already defined on line 2");
+    }
+
+    #[test]
+    fn undefined_rule() {
+        assert_eq!(
+            warnings("a = { b ~ c ~ b ~ ANY ~ PEEK_ALL }\nb = { \"x\" }"),
+            [("undefined-rule", 1)]
+        );
+    }
+
+    #[test]
+    fn unused_rule() {
+        assert_eq!(
+            warnings("a = { b }\nb = { \"x\" }\nc = { \"y\" }"),
+            [("unused-rule", 3)]
+        );
+    }
+
+    #[test]
+    fn infinite_repeat() {
+        assert_eq!(
+            warnings("a = { (b ~ \"x\"?)* ~ c+ ~ d{1,} ~ \"y\"{0,} }\nb = { \"z\"* }\nc = { \"w\" }\nd = { \"\" }"),
+            [("infinite-repeat", 1), ("infinite-repeat", 1)]
+        );
+    }
+
+    #[test]
+    fn left_recursion() {
+        let (grammar, _) =
+            Grammar::parse("a = { b? ~ c }\nb = { \"x\" }\nc = { d | \"y\" }\nd = { a ~ \"z\" }")
+                .unwrap();
+        let warnings = lint(&grammar);
+        let recursion: Vec<_> = warnings
+            .iter()
+            .filter(|warning| warning.code == "left-recursion")
+            .map(|warning| (warning.message.as_str(), warning.line))
+            .collect();
+        assert_eq!(
+            recursion,
+            [("Rule 'a' is left recursive (a -> c -> d -> a)", 1)]
+        );
+    }
+
+    #[test]
+    fn recursion_after_input_is_fine() {
+        assert_eq!(warnings("a = { \"(\" ~ a ~ \")\" | \"x\" }"), []);
+    }
+
+    #[test]
+    fn nullable() {
+        let (grammar, _) =
+            Grammar::parse("a = { b ~ c? }\nb = { \"\" | \"x\" }\nc = { \"y\" }\nd = { PUSH(c) }")
+                .unwrap();
+        let mut nullable: Vec<_> = nullable_rules(&grammar).into_iter().collect();
+        nullable.sort();
+        assert_eq!(nullable, ["a", "b"]);
+    }
+
+    #[test]
+    fn builtins() {
+        assert!(is_builtin("ANY"));
+        assert!(is_builtin("LETTER"));
+        assert!(!is_builtin("letter"));
+        assert!(!is_builtin("PUSH"));
+    }
+}
@@ -0,0 +1,173 @@
+//! Namespacing of the ids and classes in rendered SVG, so several diagrams can be inlined into one page
+
+/// The attributes holding ids or classes, with the text preceding their value
+const NAME_ATTRIBUTES: [&str; 3] = [" class=\"", " id=\"", "href=\"#"];
+
+/// Returns true if the character can start a CSS identifier (or an id)
+fn starts_name(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_' || c == '-'
+}
+
+/// Prefixes every id and class in an SVG document with `prefix`, along with the selectors of its stylesheets and its
+/// links to fragments (`#id`) so they still match
+pub(crate) fn prefix_names(svg: &str, prefix: &str) -> String {
+    let mut out = String::with_capacity(svg.len());
+    let mut rest = svg;
+
+    while let Some(start) = rest.find("<style") {
+        let body = rest[start..]
+            .find('>')
+            .map_or(rest.len(), |idx| start + idx + 1);
+        let end = rest[body..]
+            .find("</style>")
+            .map_or(rest.len(), |idx| body + idx);
+        out.push_str(&prefix_markup(&rest[..body], prefix));
+        out.push_str(&prefix_css(&rest[body..end], prefix));
+        rest = &rest[end..];
+    }
+    out.push_str(&prefix_markup(rest, prefix));
+    out
+}
+
+/// Prefixes the values of the id and class attributes (and fragment links) in markup
+fn prefix_markup(markup: &str, prefix: &str) -> String {
+    let mut out = String::with_capacity(markup.len());
+    let mut rest = markup;
+
+    // Attribute values never contain quotes (they're escaped), so each value ends at the next one
+    while let Some((start, attr)) = NAME_ATTRIBUTES
+        .iter()
+        .filter_map(|attr| rest.find(attr).map(|idx| (idx, attr)))
+        .min()
+    {
+        let value = start + attr.len();
+        let end = rest[value..]
+            .find('"')
+            .map_or(rest.len(), |idx| value + idx);
+        out.push_str(&rest[..value]);
+        let names = rest[value..end]
+            .split(' ')
+            .map(|name| {
+                if name.is_empty() {
+                    String::new()
+                } else {
+                    format!("{prefix}{name}")
+                }
+            })
+            .collect::<Vec<_>>();
+        out.push_str(&names.join(" "));
+        rest = &rest[end..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Prefixes the class (`.name`) and id (`#name`) selectors of a stylesheet, leaving declarations (where `.` and `#`
+/// start numbers and colors) alone
+fn prefix_css(css: &str, prefix: &str) -> String {
+    let mut out = String::with_capacity(css.len());
+    // Whether each open block holds rules (as in `@media`) rather than declarations
+    let mut blocks: Vec<bool> = Vec::new();
+    // The text since the end of the last block, which is the prelude of the next one
+    let mut prelude = String::new();
+    let mut in_comment = false;
+    let mut chars = css.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        out.push(c);
+        if in_comment {
+            if c == '*' && chars.peek() == Some(&'/') {
+                out.push('/');
+                chars.next();
+                in_comment = false;
+            }
+            continue;
+        }
+
+        let in_selector = blocks.last().is_none_or(|&rules| rules);
+        match c {
+            '/' if chars.peek() == Some(&'*') => {
+                out.push('*');
+                chars.next();
+                in_comment = true;
+            }
+            '{' => {
+                let at_rule = prelude.trim_start().starts_with('@');
+                // Only conditional at-rules (`@media`, `@supports`...) hold rules, `@font-face` holds declarations
+                blocks.push(
+                    in_selector && at_rule && !prelude.trim_start().starts_with("@font-face"),
+                );
+                prelude.clear();
+            }
+            '}' => {
+                blocks.pop();
+                prelude.clear();
+            }
+            ';' => prelude.clear(),
+            '.' | '#' if in_selector && chars.peek().is_some_and(|&next| starts_name(next)) => {
+                out.push_str(prefix);
+                prelude.push(c);
+            }
+            _ => prelude.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefix_css_selectors() {
+        assert_eq!(
+            prefix_css("g.rule > .ref-a, #title:hover { fill: #fff; }", "p-"),
+            "g.p-rule > .p-ref-a, #p-title:hover { fill: #fff; }"
+        );
+    }
+
+    #[test]
+    fn prefix_css_leaves_declarations() {
+        assert_eq!(
+            prefix_css(
+                ".a { stroke-width: .5px; margin: 0.5em; color: #abc; }",
+                "p-"
+            ),
+            ".p-a { stroke-width: .5px; margin: 0.5em; color: #abc; }"
+        );
+    }
+
+    #[test]
+    fn prefix_css_at_rules() {
+        assert_eq!(
+            prefix_css(
+                "@media (prefers-color-scheme: dark) { .a { fill: #000; } }",
+                "p-"
+            ),
+            "@media (prefers-color-scheme: dark) { .p-a { fill: #000; } }"
+        );
+        assert_eq!(
+            prefix_css("@font-face { src: url(#font); } .a { fill: #000; }", "p-"),
+            "@font-face { src: url(#font); } .p-a { fill: #000; }"
+        );
+    }
+
+    #[test]
+    fn prefix_css_skips_comments() {
+        assert_eq!(
+            prefix_css("/* .a { } */ .b { }", "p-"),
+            "/* .a { } */ .p-b { }"
+        );
+    }
+
+    #[test]
+    fn prefix_names_markup_and_styles() {
+        assert_eq!(
+            prefix_names(
+                "<svg class=\"railroad rule\"><style>.rule { }</style><a href=\"#top\" id=\"x\"/></svg>",
+                "p-"
+            ),
+            "<svg class=\"p-railroad p-rule\"><style>.p-rule { }</style><a href=\"#p-top\" id=\"p-x\"/></svg>"
+        );
+    }
+}
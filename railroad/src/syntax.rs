@@ -0,0 +1,75 @@
+//! Syntax highlighting of pest source as HTML
+
+use railroad::svg;
+
+use crate::lint;
+
+/// The stylesheet for the classes of highlighted source
+pub(crate) const SYNTAX_CSS: &str = "
+.source pre { background: hsl(30, 20%, 95%); padding: 0.5em 1em; overflow-x: auto; }
+.pest-comment { color: hsl(120, 25%, 35%); font-style: italic; }
+.pest-string { color: hsl(15, 70%, 40%); }
+.pest-builtin { color: hsl(270, 50%, 45%); }
+.pest-number { color: hsl(200, 70%, 35%); }
+.pest-op { color: hsl(0, 0%, 45%); }
+";
+
+/// The characters drawn as operators (including modifiers, braces and parentheses)
+const OPERATORS: &str = "=@_$!&~|*+?{}()[]^-.#:,";
+
+/// Returns the HTML for pest source, with each token wrapped in a `span` with a class for its kind (`pest-comment`,
+/// `pest-string`, `pest-builtin`, `pest-rule`, `pest-number` or `pest-op`)
+pub(crate) fn highlight(src: &str) -> String {
+    let mut out = String::with_capacity(src.len() * 2);
+    let mut rest = src;
+
+    while let Some(c) = rest.chars().next() {
+        let (len, class) = if rest.starts_with("//") {
+            (rest.find('\n').unwrap_or(rest.len()), Some("comment"))
+        } else if c == '"' || c == '\'' {
+            (string_len(rest, c), Some("string"))
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            let class = match &rest[..len] {
+                // The silent modifier
+                "_" => "op",
+                name if lint::is_builtin(name) => "builtin",
+                _ => "rule",
+            };
+            (len, Some(class))
+        } else if c.is_ascii_digit() {
+            let len = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            (len, Some("number"))
+        } else if OPERATORS.contains(c) {
+            (1, Some("op"))
+        } else {
+            (c.len_utf8(), None)
+        };
+
+        let text = svg::encode_minimal(&rest[..len]);
+        match class {
+            Some(class) => out.push_str(&format!("<span class=\"pest-{class}\">{text}</span>")),
+            None => out.push_str(&text),
+        }
+        rest = &rest[len..];
+    }
+    out
+}
+
+/// The length of the string (or character) literal at the start of `src`, including its quotes
+fn string_len(src: &str, quote: char) -> usize {
+    let mut escaped = false;
+    for (idx, c) in src.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            _ if c == quote => return idx + 1,
+            _ => {}
+        }
+    }
+    src.len()
+}
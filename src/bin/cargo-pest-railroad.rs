@@ -0,0 +1,33 @@
+//! The `cargo pest-railroad` subcommand, which renders every grammar of the current crate into `target/doc/railroad`.
+//! It runs the `crate` command of `pest_railroad_gen`, which is installed alongside it.
+
+use std::{
+    env,
+    ffi::OsString,
+    process::{Command, ExitCode},
+};
+
+fn main() -> ExitCode {
+    let mut args: Vec<OsString> = env::args_os().skip(1).collect();
+    // Cargo passes the name of the subcommand first, but this may also be run directly
+    if args.first().is_some_and(|arg| arg == "pest-railroad") {
+        args.remove(0);
+    }
+
+    let exe = match env::current_exe() {
+        Ok(exe) => exe.with_file_name(format!("pest_railroad_gen{}", env::consts::EXE_SUFFIX)),
+        Err(err) => {
+            eprintln!("Error: Unable to locate pest_railroad_gen: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match Command::new(&exe).arg("crate").args(args).status() {
+        // A missing code means the process was killed by a signal
+        Ok(status) => ExitCode::from(status.code().map_or(1, |code| code as u8)),
+        Err(err) => {
+            eprintln!("Error: Unable to run {}: {err}", exe.display());
+            ExitCode::FAILURE
+        }
+    }
+}
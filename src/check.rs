@@ -0,0 +1,122 @@
+use std::{collections::HashMap, error::Error, path::Path, path::PathBuf};
+
+use clap::Args;
+use pest_railroad::{Grammar, Lint, LintLevel, LINTS};
+
+use crate::{
+    diagnostics::Reporter,
+    exit::{self, Failure},
+    render::ConfigArgs,
+};
+
+#[derive(Args)]
+pub struct CheckArgs {
+    /// The pest grammar files to check (`-` reads one from stdin)
+    #[arg(required = true)]
+    pub inputs: Vec<PathBuf>,
+
+    /// Ignore these comma separated lints
+    #[arg(short = 'A', long, value_delimiter = ',', value_name = "LINTS")]
+    allow: Vec<String>,
+
+    /// Report these comma separated lints as warnings
+    #[arg(short = 'W', long, value_delimiter = ',', value_name = "LINTS")]
+    warn: Vec<String>,
+
+    /// Report these comma separated lints as errors (failing the check)
+    #[arg(short = 'D', long, value_delimiter = ',', value_name = "LINTS")]
+    deny: Vec<String>,
+
+    #[command(flatten)]
+    pub config: ConfigArgs,
+}
+
+impl CheckArgs {
+    /// The level of each lint for a grammar file: the lint's default, overridden by the project config file and then
+    /// the command line
+    fn levels(&self, input: &Path) -> Result<HashMap<&'static str, LintLevel>, Box<dyn Error>> {
+        let config = self.config.load(input)?.unwrap_or_default();
+        let flags = [
+            (&self.allow, LintLevel::Allow),
+            (&self.warn, LintLevel::Warn),
+            (&self.deny, LintLevel::Deny),
+        ];
+        let overrides = config
+            .lints
+            .iter()
+            .map(|(code, &level)| (code, level))
+            .chain(
+                flags
+                    .into_iter()
+                    .flat_map(|(codes, level)| codes.iter().map(move |code| (code, level))),
+            );
+        levels(overrides)
+    }
+}
+
+/// The level of each lint: the lint's default, overridden by the given levels in order
+pub fn levels<'a>(
+    overrides: impl IntoIterator<Item = (&'a String, LintLevel)>,
+) -> Result<HashMap<&'static str, LintLevel>, Box<dyn Error>> {
+    let mut levels: HashMap<_, _> = LINTS
+        .iter()
+        .map(|lint| (lint.code, lint.default_level))
+        .collect();
+
+    for (code, level) in overrides {
+        let lint = Lint::find(code).ok_or_else(|| format!("Unknown lint '{code}'"))?;
+        levels.insert(lint.code, level);
+    }
+    Ok(levels)
+}
+
+/// Checks each grammar file, reporting every problem found. Fails if any file can't be parsed or has a denied lint,
+/// with the lowest exit code of the failures (so errors take precedence over lint failures).
+pub fn check(args: &CheckArgs, reporter: &Reporter) -> Result<(), Box<dyn Error>> {
+    let mut failed = 0;
+    let mut code = exit::LINT;
+
+    for input in &args.inputs {
+        match check_file(args, input, reporter) {
+            Ok(true) => {}
+            Ok(false) => failed += 1,
+            Err(err) => {
+                reporter.error(Some(input), &*err);
+                failed += 1;
+                code = code.min(exit::code(&*err));
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(Failure {
+            message: format!("{failed} grammar file(s) failed checks"),
+            code,
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Checks a single grammar file, returning false if it has a denied lint
+fn check_file(args: &CheckArgs, input: &Path, reporter: &Reporter) -> Result<bool, Box<dyn Error>> {
+    let levels = args.levels(input)?;
+    let src = crate::input::read(input)?;
+    let (grammar, mut warnings) = Grammar::parse(&src)?;
+
+    warnings.extend(pest_railroad::lint(&grammar));
+    warnings.sort_by_key(|warning| (warning.line, warning.column));
+
+    let mut passed = true;
+    for warning in &warnings {
+        match levels[warning.code] {
+            LintLevel::Allow => {}
+            LintLevel::Warn => reporter.warning(input, warning),
+            LintLevel::Deny => {
+                reporter.denied(input, warning);
+                passed = false;
+            }
+        }
+    }
+    Ok(passed)
+}
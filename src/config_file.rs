@@ -0,0 +1,162 @@
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use pest_railroad::LintLevel;
+use serde::Deserialize;
+
+use crate::render::{
+    parse_id_prefix, EndStyleName, InlineTrivialName, LookaheadStyleName, OutputFormat,
+    PageSizeName, TextFontArg, ThemeName,
+};
+
+/// The file name searched for (upward from the grammar file) when no config file is given
+pub const CONFIG_FILE_NAME: &str = "pest-railroad.toml";
+
+/// Project wide defaults from a `pest-railroad.toml` file. Command line flags take precedence over these.
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ProjectConfig {
+    pub format: Option<OutputFormat>,
+    pub theme: Option<ThemeName>,
+    /// Relative to the directory containing the config file
+    pub css: Vec<PathBuf>,
+    pub footer: Option<String>,
+    pub rules: Vec<String>,
+    pub start_rule: Option<String>,
+    pub inline: Vec<String>,
+    pub inline_trivial: Option<InlineTrivialName>,
+    pub expand_depth: Option<usize>,
+    pub factor_repeated: bool,
+    pub highlight: Vec<String>,
+    pub hide_builtins: bool,
+    pub hide_pattern: Option<String>,
+    pub link_template: Option<String>,
+    pub numbering: bool,
+    pub line_refs: bool,
+    pub implicit_notes: bool,
+    pub id_prefix: Option<String>,
+    pub interactive: bool,
+    pub modifier_colors: bool,
+    pub legend: bool,
+    pub stack_ops: bool,
+    pub lookahead_style: Option<LookaheadStyleName>,
+    pub combined: bool,
+    pub toc: bool,
+    pub include_source: bool,
+    pub search: bool,
+    pub expandable: bool,
+    pub source_map: bool,
+    pub paginate: bool,
+    pub page_size: Option<PageSizeName>,
+    pub ascii: bool,
+    pub font: Option<String>,
+    pub embed_fonts: bool,
+    pub terminal_font: Option<TextFontArg>,
+    pub nonterminal_font: Option<TextFontArg>,
+    pub comment_font: Option<TextFontArg>,
+    pub max_width: Option<i64>,
+    pub max_label_length: Option<usize>,
+    pub max_choice_alternatives: Option<usize>,
+    pub rtl: bool,
+    pub end_style: Option<EndStyleName>,
+    pub merge_eoi: bool,
+    pub margin: Option<i64>,
+    pub rule_spacing: Option<i64>,
+    /// At most one of `scale`, `dpi` and `width` may be given
+    pub scale: Option<f64>,
+    pub dpi: Option<f64>,
+    pub width: Option<u32>,
+    pub max_pixels: Option<u64>,
+    /// Relative to the directory containing the config file
+    pub output_dir: Option<PathBuf>,
+    pub output_template: Option<String>,
+    pub per_rule: bool,
+    pub strict: bool,
+    pub embed_warnings: bool,
+    /// Lint levels by lint code, used by `check` and strict rendering
+    pub lints: BTreeMap<String, LintLevel>,
+}
+
+impl ProjectConfig {
+    /// Loads a config file, resolving relative paths against the file's directory
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let src = fs::read_to_string(path)?;
+        let mut config: ProjectConfig =
+            toml::from_str(&src).map_err(|err| format!("{}: {err}", path.display()))?;
+
+        let sizes = [
+            config.scale.is_some(),
+            config.dpi.is_some(),
+            config.width.is_some(),
+        ];
+        if sizes.into_iter().filter(|&given| given).count() > 1 {
+            return Err(format!(
+                "{}: only one of scale, dpi and width may be given",
+                path.display()
+            )
+            .into());
+        }
+        if config
+            .scale
+            .or(config.dpi)
+            .is_some_and(|value| value <= 0.0 || !value.is_finite())
+            || config.width == Some(0)
+            || config.max_pixels == Some(0)
+            || config.max_width.is_some_and(|width| width <= 0)
+            || config.max_label_length == Some(0)
+            || config.max_choice_alternatives == Some(0)
+        {
+            return Err(format!(
+                "{}: scale, dpi, width, max-pixels, max-width, max-label-length and \
+                 max-choice-alternatives must be positive",
+                path.display()
+            )
+            .into());
+        }
+        if config.margin.is_some_and(|margin| margin < 0)
+            || config.rule_spacing.is_some_and(|spacing| spacing < 0)
+        {
+            return Err(format!(
+                "{}: margin and rule-spacing can't be negative",
+                path.display()
+            )
+            .into());
+        }
+
+        let fonts = [
+            ("terminal-font", &config.terminal_font),
+            ("nonterminal-font", &config.nonterminal_font),
+            ("comment-font", &config.comment_font),
+        ];
+        for (key, font) in fonts {
+            if let Some(font) = font {
+                font.validate()
+                    .map_err(|err| format!("{}: {key} {err}", path.display()))?;
+            }
+        }
+
+        if let Some(prefix) = &config.id_prefix {
+            parse_id_prefix(prefix)
+                .map_err(|err| format!("{}: id-prefix {err}", path.display()))?;
+        }
+
+        let base = path.parent().unwrap_or(Path::new(""));
+        config.output_dir = config.output_dir.map(|dir| base.join(dir));
+        config.css = config.css.iter().map(|css| base.join(css)).collect();
+        Ok(config)
+    }
+
+    /// Finds the nearest config file in the directory of `input` or any of its ancestors
+    pub fn discover(input: &Path) -> Option<PathBuf> {
+        let input = input.canonicalize().ok()?;
+        input
+            .ancestors()
+            .skip(1)
+            .map(|dir| dir.join(CONFIG_FILE_NAME))
+            .find(|path| path.is_file())
+    }
+}
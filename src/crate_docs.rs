@@ -0,0 +1,233 @@
+use std::{
+    collections::BTreeSet,
+    env,
+    error::Error,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Component, Path, PathBuf},
+    process::Command,
+    time::Instant,
+};
+
+use clap::Args;
+use pest_railroad::{Format, Grammar};
+use serde::Deserialize;
+
+use crate::{
+    diagnostics::Reporter,
+    exit::{self, Failure},
+    jobs::JobsArgs,
+    render::{ConfigArgs, DiagramArgs, OutputFormat},
+};
+
+#[derive(Args)]
+pub struct CrateArgs {
+    /// The manifest of the crate (or workspace) to render the grammars of [default: the one cargo finds from the
+    /// current directory]
+    #[arg(long, value_name = "PATH")]
+    manifest_path: Option<PathBuf>,
+
+    /// The output format [default: svg]
+    #[arg(short, long, value_enum)]
+    format: Option<OutputFormat>,
+
+    #[command(flatten)]
+    pub diagram: DiagramArgs,
+
+    #[command(flatten)]
+    jobs: JobsArgs,
+
+    #[command(flatten)]
+    pub config: ConfigArgs,
+}
+
+/// The parts of the output of `cargo metadata` that are needed
+#[derive(Deserialize)]
+struct Metadata {
+    /// The workspace members (as `--no-deps` is given)
+    packages: Vec<Package>,
+    target_directory: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct Package {
+    name: String,
+    manifest_path: PathBuf,
+}
+
+/// Renders every grammar referenced by a `#[grammar = "..."]` attribute in the `src` directory of each crate in the
+/// workspace into `target/doc/railroad/<crate>/`, in parallel. Fails once every grammar has been tried if any of them
+/// failed.
+pub fn render_crate(args: &CrateArgs, reporter: &Reporter) -> Result<(), Box<dyn Error>> {
+    let metadata = cargo_metadata(args.manifest_path.as_deref())?;
+    let out_dir = metadata.target_directory.join("doc").join("railroad");
+
+    let mut jobs = Vec::new();
+    for package in &metadata.packages {
+        // Panic safety: A manifest path always names a file within the crate directory
+        let root = package.manifest_path.parent().expect("crate directory");
+        let src_dir = root.join("src");
+
+        for grammar in find_grammars(root)? {
+            // Mirror the layout of the source directory, so grammars with the same name don't clash. A grammar outside
+            // the crate is named after its file alone, as joining any other path could leave the output directory.
+            let relative = grammar
+                .strip_prefix(&src_dir)
+                .or_else(|_| grammar.strip_prefix(root))
+                .ok()
+                .filter(|path| {
+                    path.components()
+                        .all(|component| matches!(component, Component::Normal(_)))
+                })
+                .or_else(|| grammar.file_name().map(Path::new))
+                .ok_or_else(|| format!("Grammar path {} has no file name", grammar.display()))?;
+            let output = out_dir.join(&package.name).join(relative);
+            jobs.push((grammar, output));
+        }
+    }
+
+    // Errors are reported as they happen, leaving only their exit codes
+    let codes = args.jobs.run(&jobs, |done, (grammar, output)| {
+        let name = grammar.file_name().unwrap_or(grammar.as_os_str());
+        reporter.progress(done, jobs.len(), &name.to_string_lossy());
+
+        let start = Instant::now();
+        match render_grammar(args, grammar, output, reporter) {
+            Ok(output) => {
+                reporter.status(&format!(
+                    "Rendered {} to {} in {:.2?}",
+                    grammar.display(),
+                    output.display(),
+                    start.elapsed()
+                ));
+                None
+            }
+            Err(err) => {
+                reporter.error(Some(grammar), &*err);
+                Some(exit::code(&*err))
+            }
+        }
+    });
+    let codes = codes.into_iter().flatten().collect::<Vec<_>>();
+    reporter.finish_progress();
+
+    if let Some(&code) = codes.iter().min() {
+        return Err(Failure {
+            message: format!("{} grammar file(s) failed to render", codes.len()),
+            code,
+        }
+        .into());
+    }
+    if jobs.is_empty() {
+        eprintln!("No #[grammar] attributes found");
+    }
+    Ok(())
+}
+
+/// Runs `cargo metadata` for the workspace containing the manifest (or the current directory)
+fn cargo_metadata(manifest_path: Option<&Path>) -> Result<Metadata, Box<dyn Error>> {
+    // Cargo tells its subcommands which cargo is running them
+    let cargo = env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    let mut command = Command::new(cargo);
+    command.args(["metadata", "--no-deps", "--format-version", "1"]);
+    if let Some(manifest_path) = manifest_path {
+        command.arg("--manifest-path").arg(manifest_path);
+    }
+
+    let output = command
+        .output()
+        .map_err(|err| format!("Unable to run cargo metadata: {err}"))?;
+    if !output.status.success() {
+        return Err(format!(
+            "cargo metadata failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    Ok(serde_json::from_slice(&output.stdout)?)
+}
+
+/// Finds the grammar files referenced by the Rust sources of a crate. Like pest, grammar paths are resolved relative to
+/// the `src` directory, falling back to the crate directory.
+fn find_grammars(root: &Path) -> Result<BTreeSet<PathBuf>, Box<dyn Error>> {
+    let src_dir = root.join("src");
+    let mut sources = Vec::new();
+    rust_files(&src_dir, &mut sources)?;
+
+    let mut grammars = BTreeSet::new();
+    for source in sources {
+        let src = fs::read_to_string(&source)?;
+        for path in grammar_attributes(&src) {
+            let in_src = src_dir.join(path);
+            let grammar = if in_src.is_file() {
+                in_src
+            } else {
+                root.join(path)
+            };
+            // Resolve any `..` so the output stays within the output directory
+            grammars.insert(grammar.canonicalize().unwrap_or(grammar));
+        }
+    }
+    Ok(grammars)
+}
+
+/// Collects the `.rs` files in a directory and its subdirectories
+fn rust_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), Box<dyn Error>> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            rust_files(&path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "rs") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// The paths of the `#[grammar = "..."]` attributes in Rust source (inline grammars have no file to render)
+fn grammar_attributes(src: &str) -> Vec<&str> {
+    src.match_indices("#[grammar")
+        .filter_map(|(idx, attr)| {
+            let value = src[idx + attr.len()..]
+                .trim_start()
+                .strip_prefix('=')?
+                .trim_start()
+                .strip_prefix('"')?;
+            value.split('"').next()
+        })
+        .collect()
+}
+
+/// Renders a single grammar next to `output` (which gets the extension of the format), applying the grammar's project
+/// config file. Returns the path written.
+fn render_grammar(
+    args: &CrateArgs,
+    input: &Path,
+    output: &Path,
+    reporter: &Reporter,
+) -> Result<PathBuf, Box<dyn Error>> {
+    let mut diagram = args.diagram.clone();
+    let mut format = args.format;
+    if let Some(config) = args.config.load(input)? {
+        format = format.or(config.format);
+        diagram.apply_config(&config);
+    }
+    let format = Format::from(format.unwrap_or(OutputFormat::Svg));
+
+    let src = fs::read_to_string(input)
+        .map_err(|err| format!("Unable to read {}: {err}", input.display()))?;
+    let (grammar, warnings) = Grammar::parse(&src)?;
+    for warning in &warnings {
+        reporter.warning(input, warning);
+    }
+    let config = diagram.config(&grammar, input)?;
+
+    let output = output.with_extension(format.backend().extension());
+    if let Some(dir) = output.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = BufWriter::new(File::create(&output)?);
+    pest_railroad::render(&grammar, &config, format, &mut file)?;
+    file.flush()?;
+    Ok(output)
+}
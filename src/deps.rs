@@ -0,0 +1,114 @@
+use std::{error::Error, path::PathBuf};
+
+use clap::{Args, ValueEnum};
+use pest_railroad::{DependencyGraph, Grammar};
+use serde::Serialize;
+
+use crate::input;
+
+#[derive(Args)]
+pub struct DepsArgs {
+    /// The pest grammar file to graph the rules of (`-` reads it from stdin)
+    pub input: PathBuf,
+
+    /// The output format
+    #[arg(short, long, value_enum, default_value_t = DepsFormat::Dot)]
+    format: DepsFormat,
+
+    /// Only include this rule and every rule reachable from it
+    #[arg(short, long, value_name = "RULE")]
+    start_rule: Option<String>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum DepsFormat {
+    /// A Graphviz digraph
+    Dot,
+    /// A Mermaid flowchart
+    Mermaid,
+    /// Each rule along with the rules it references
+    Json,
+}
+
+#[derive(Serialize)]
+struct RuleDeps<'a> {
+    name: &'a str,
+    line: Option<usize>,
+    /// The rules referenced by this rule, in order of first reference
+    references: Vec<&'a str>,
+}
+
+/// Prints the rule reference graph. Only references between rules of the grammar are included (built-in and undefined
+/// rules are left out).
+pub fn deps(args: &DepsArgs) -> Result<(), Box<dyn Error>> {
+    let src = input::read(&args.input)?;
+    let (grammar, _) = Grammar::parse(&src)?;
+    let graph = DependencyGraph::new(&grammar);
+
+    let reachable = match &args.start_rule {
+        Some(start_rule) if !graph.contains(start_rule) => {
+            return Err(format!("Start rule '{start_rule}' not found in grammar").into());
+        }
+        Some(start_rule) => Some(graph.reachable(start_rule)),
+        None => None,
+    };
+
+    let rules: Vec<_> = grammar
+        .rules
+        .iter()
+        .filter(|rule| {
+            reachable
+                .as_ref()
+                .is_none_or(|reachable| reachable.contains(&rule.name))
+        })
+        .map(|rule| RuleDeps {
+            name: &rule.name,
+            line: rule.line,
+            references: graph
+                .references(&rule.name)
+                .iter()
+                .filter(|name| graph.contains(name))
+                .map(String::as_str)
+                .collect(),
+        })
+        .collect();
+
+    match args.format {
+        DepsFormat::Dot => print_dot(&rules),
+        DepsFormat::Mermaid => print_mermaid(&rules),
+        DepsFormat::Json => println!("{}", serde_json::to_string_pretty(&rules)?),
+    }
+    Ok(())
+}
+
+fn print_dot(rules: &[RuleDeps]) {
+    println!("digraph grammar {{");
+    // Rule names are identifiers, so they never need escaping
+    for rule in rules {
+        println!("    \"{}\";", rule.name);
+    }
+    for rule in rules {
+        for reference in &rule.references {
+            println!("    \"{}\" -> \"{reference}\";", rule.name);
+        }
+    }
+    println!("}}");
+}
+
+fn print_mermaid(rules: &[RuleDeps]) {
+    // Mermaid reserves some words (such as `end`) that are also valid rule names, so the nodes are numbered and
+    // labelled with the rule name instead
+    let id = |name: &str| rules.iter().position(|rule| rule.name == name);
+
+    println!("flowchart LR");
+    for (idx, rule) in rules.iter().enumerate() {
+        println!("    r{idx}[\"{}\"]", rule.name);
+    }
+    for (idx, rule) in rules.iter().enumerate() {
+        for reference in &rule.references {
+            if let Some(ref_idx) = id(reference) {
+                println!("    r{idx} --> r{ref_idx}");
+            }
+        }
+    }
+}
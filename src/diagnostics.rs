@@ -0,0 +1,226 @@
+use std::{
+    error::Error,
+    fs::File,
+    io::{self, IsTerminal, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use clap::{Args, ValueEnum};
+use pest_railroad::Warning;
+use serde::Serialize;
+
+use crate::input;
+
+#[derive(Args)]
+pub struct DiagnosticArgs {
+    /// How warnings and errors are printed
+    #[arg(long, value_enum, global = true, default_value_t = MessageFormat::Human)]
+    message_format: MessageFormat,
+
+    /// Write warnings and errors to this file instead of stderr
+    #[arg(long, global = true, value_name = "FILE")]
+    message_output: Option<PathBuf>,
+
+    /// The file name to report for a grammar read from stdin (given as `-`), such as the path of an editor buffer
+    #[arg(long, global = true, value_name = "NAME", default_value = "<stdin>")]
+    stdin_filename: PathBuf,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum MessageFormat {
+    /// Plain text messages
+    Human,
+    /// One JSON object per line
+    Json,
+}
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Level {
+    Warning,
+    Error,
+}
+
+/// A warning or error as emitted in the JSON message format
+#[derive(Serialize)]
+struct Diagnostic<'a> {
+    level: Level,
+    code: &'a str,
+    message: &'a str,
+    file: Option<&'a Path>,
+    line: Option<usize>,
+    column: Option<usize>,
+}
+
+/// The width (in characters) of the progress bar
+const PROGRESS_WIDTH: usize = 30;
+
+/// Prints warnings and errors in the requested message format, along with progress and status messages
+pub struct Reporter {
+    format: MessageFormat,
+    out: Mutex<Box<dyn Write + Send>>,
+    /// Reported in place of `-`
+    stdin_filename: PathBuf,
+    /// Progress bars are only drawn on a terminal, as they rely on redrawing the line
+    show_progress: bool,
+    /// The progress bar currently drawn on the last line of stderr (if any). This is locked before `out`.
+    progress: Mutex<Option<String>>,
+}
+
+impl Reporter {
+    pub fn new(args: &DiagnosticArgs) -> io::Result<Self> {
+        let out: Box<dyn Write + Send> = match &args.message_output {
+            Some(path) => Box::new(File::create(path)?),
+            None => Box::new(io::stderr()),
+        };
+
+        Ok(Self {
+            format: args.message_format,
+            out: Mutex::new(out),
+            stdin_filename: args.stdin_filename.clone(),
+            show_progress: io::stderr().is_terminal(),
+            progress: Mutex::new(None),
+        })
+    }
+
+    /// Draws a progress bar showing that `done` of `total` items are done, and that `item` is next
+    pub fn progress(&self, done: usize, total: usize, item: &str) {
+        if !self.show_progress {
+            return;
+        }
+
+        let filled = PROGRESS_WIDTH * done / total.max(1);
+        let line = format!(
+            "[{}{}] {done}/{total} {item}",
+            "=".repeat(filled),
+            " ".repeat(PROGRESS_WIDTH - filled)
+        );
+        // Panic safety: A panic while holding the lock would have already ended the program
+        let mut progress = self.progress.lock().expect("progress lock");
+        let _ = write!(io::stderr(), "\r\x1b[2K{line}");
+        *progress = Some(line);
+    }
+
+    /// Removes the progress bar (if one is drawn)
+    pub fn finish_progress(&self) {
+        // Panic safety: A panic while holding the lock would have already ended the program
+        if self
+            .progress
+            .lock()
+            .expect("progress lock")
+            .take()
+            .is_some()
+        {
+            let _ = write!(io::stderr(), "\r\x1b[2K");
+        }
+    }
+
+    /// Prints a status message, such as a file having been written, keeping any progress bar below it
+    pub fn status(&self, message: &str) {
+        self.above_progress(|| {
+            let _ = writeln!(io::stderr(), "{message}");
+        });
+    }
+
+    /// Runs `print` with the progress bar (if any) cleared, redrawing it afterwards
+    fn above_progress(&self, print: impl FnOnce()) {
+        // Panic safety: A panic while holding the lock would have already ended the program
+        let progress = self.progress.lock().expect("progress lock");
+        if progress.is_some() {
+            let _ = write!(io::stderr(), "\r\x1b[2K");
+        }
+        print();
+        if let Some(line) = &*progress {
+            let _ = write!(io::stderr(), "{line}");
+        }
+    }
+
+    pub fn warning(&self, file: &Path, warning: &Warning) {
+        self.report(Diagnostic {
+            level: Level::Warning,
+            code: warning.code,
+            message: &warning.message,
+            file: Some(file),
+            line: Some(warning.line),
+            column: Some(warning.column),
+        });
+    }
+
+    /// Reports a warning that has been raised to an error (such as a denied lint)
+    pub fn denied(&self, file: &Path, warning: &Warning) {
+        self.report(Diagnostic {
+            level: Level::Error,
+            code: warning.code,
+            message: &warning.message,
+            file: Some(file),
+            line: Some(warning.line),
+            column: Some(warning.column),
+        });
+    }
+
+    pub fn error(&self, file: Option<&Path>, err: &(dyn Error + 'static)) {
+        let lib_err = err.downcast_ref::<pest_railroad::Error>();
+        let code = match lib_err {
+            Some(pest_railroad::Error::Parse(_)) => "parse",
+            Some(pest_railroad::Error::Io(_)) => "io",
+            Some(pest_railroad::Error::Render(_)) => "render",
+            None if err.is::<io::Error>() => "io",
+            None => "error",
+        };
+        let location = lib_err.and_then(pest_railroad::Error::location);
+
+        let message = match (self.format, lib_err) {
+            // JSON consumers get the location separately, so use the single line message
+            (MessageFormat::Json, Some(lib_err)) => lib_err.message(),
+            _ => err.to_string(),
+        };
+
+        self.report(Diagnostic {
+            level: Level::Error,
+            code,
+            message: &message,
+            file,
+            line: location.map(|(line, _)| line),
+            column: location.map(|(_, column)| column),
+        });
+    }
+
+    fn report<'a>(&'a self, mut diag: Diagnostic<'a>) {
+        if diag.file.is_some_and(input::is_stdin) {
+            diag.file = Some(&self.stdin_filename);
+        }
+
+        self.above_progress(|| self.write(&diag));
+    }
+
+    fn write(&self, diag: &Diagnostic) {
+        // Panic safety: A panic while holding the lock would have already ended the program
+        let mut out = self.out.lock().expect("reporter lock");
+
+        // There is nowhere left to report a failure to write a diagnostic, so ignore it
+        let _ = match self.format {
+            MessageFormat::Human => {
+                let level = match diag.level {
+                    Level::Warning => "Warning",
+                    Level::Error => "Error",
+                };
+                match (diag.file, diag.line, diag.column) {
+                    // Parse errors already show their location
+                    (Some(file), Some(line), Some(column)) if diag.code != "parse" => {
+                        writeln!(
+                            out,
+                            "{level}: {} ({}:{line}:{column})",
+                            diag.message,
+                            file.display()
+                        )
+                    }
+                    _ => writeln!(out, "{level}: {}", diag.message),
+                }
+            }
+            MessageFormat::Json => serde_json::to_writer(&mut *out, diag)
+                .map_err(io::Error::from)
+                .and_then(|()| writeln!(out)),
+        };
+    }
+}
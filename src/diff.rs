@@ -0,0 +1,214 @@
+use std::{
+    error::Error,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use clap::Args;
+use pest_railroad::{Grammar, GrammarRule};
+use serde::Serialize;
+
+use crate::{
+    input,
+    render::{ConfigArgs, DiagramArgs, OutputFormat},
+};
+
+#[derive(Args)]
+pub struct DiffArgs {
+    /// The old version of the grammar (`-` reads it from stdin)
+    pub old: PathBuf,
+
+    /// The new version of the grammar (`-` reads it from stdin)
+    pub new: PathBuf,
+
+    /// Print the differences as JSON
+    #[arg(long)]
+    json: bool,
+
+    /// Also write a diagram of the new grammar, with the added and changed rules highlighted, to this file
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// The format of the diagram [default: svg]
+    #[arg(short, long, value_enum, requires = "output")]
+    format: Option<OutputFormat>,
+
+    #[command(flatten)]
+    pub diagram: DiagramArgs,
+
+    #[command(flatten)]
+    pub config: ConfigArgs,
+}
+
+impl DiffArgs {
+    /// Fills in any settings not given on the command line from the project config file of the new grammar (if any)
+    pub fn apply_config(&mut self) -> Result<(), Box<dyn Error>> {
+        if let Some(config) = self.config.load(&self.new)? {
+            self.format = self.format.or(config.format);
+            self.diagram.apply_config(&config);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default, Serialize)]
+struct GrammarDiff<'a> {
+    /// Rules only in the new grammar (in its order)
+    added: Vec<&'a str>,
+    /// Rules only in the old grammar (in its order)
+    removed: Vec<&'a str>,
+    /// Rules in both grammars that differ (in the order of the new grammar)
+    changed: Vec<ChangedRule<'a>>,
+}
+
+#[derive(Serialize)]
+struct ChangedRule<'a> {
+    name: &'a str,
+    /// What changed about the rule: `modifier`, `expression` and/or `docs`
+    changes: Vec<&'static str>,
+}
+
+pub fn diff(args: &DiffArgs) -> Result<(), Box<dyn Error>> {
+    if input::is_stdin(&args.old) && input::is_stdin(&args.new) {
+        return Err("Only one version of the grammar can be read from stdin".into());
+    }
+    let old = parse(&args.old)?;
+    let new = parse(&args.new)?;
+    let diff = compare(&old, &new);
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&diff)?);
+    } else {
+        print_diff(&diff);
+    }
+
+    if let Some(output) = &args.output {
+        let mut config = args.diagram.config(&new, &args.new)?;
+        config.highlight.extend(
+            diff.added
+                .iter()
+                .chain(diff.changed.iter().map(|rule| &rule.name))
+                .map(|name| name.to_string()),
+        );
+        let format = args.format.unwrap_or(OutputFormat::Svg).into();
+
+        if let Some(dir) = output.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = BufWriter::new(File::create(output)?);
+        pest_railroad::render(&new, &config, format, &mut file)?;
+        file.flush()?;
+    }
+    Ok(())
+}
+
+fn parse(path: &Path) -> Result<Grammar, Box<dyn Error>> {
+    let src = input::read(path)?;
+    let (grammar, _) = Grammar::parse(&src)?;
+    Ok(grammar)
+}
+
+/// Compares the rules of two grammars by name
+fn compare<'a>(old: &'a Grammar, new: &'a Grammar) -> GrammarDiff<'a> {
+    let find = |grammar: &'a Grammar, name: &str| -> Option<&'a GrammarRule> {
+        grammar.rules.iter().find(|rule| rule.name == name)
+    };
+    let mut diff = GrammarDiff::default();
+
+    for rule in &new.rules {
+        let Some(old_rule) = find(old, &rule.name) else {
+            diff.added.push(&rule.name);
+            continue;
+        };
+
+        let changes: Vec<_> = [
+            ("modifier", old_rule.modifier != rule.modifier),
+            ("expression", !old_rule.expr.same_structure(&rule.expr)),
+            ("docs", old_rule.docs != rule.docs),
+        ]
+        .into_iter()
+        .filter_map(|(change, changed)| changed.then_some(change))
+        .collect();
+        if !changes.is_empty() {
+            diff.changed.push(ChangedRule {
+                name: &rule.name,
+                changes,
+            });
+        }
+    }
+
+    diff.removed = old
+        .rules
+        .iter()
+        .filter(|rule| find(new, &rule.name).is_none())
+        .map(|rule| rule.name.as_str())
+        .collect();
+    diff
+}
+
+fn print_diff(diff: &GrammarDiff<'_>) {
+    if diff.added.is_empty() && diff.removed.is_empty() && diff.changed.is_empty() {
+        println!("No rules changed");
+        return;
+    }
+
+    for name in &diff.added {
+        println!("+ {name}");
+    }
+    for name in &diff.removed {
+        println!("- {name}");
+    }
+    for rule in &diff.changed {
+        println!("~ {} ({})", rule.name, rule.changes.join(", "));
+    }
+    println!(
+        "{} added, {} removed, {} changed",
+        diff.added.len(),
+        diff.removed.len(),
+        diff.changed.len()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grammar(source: &str) -> Grammar {
+        Grammar::parse(source).unwrap().0
+    }
+
+    #[test]
+    fn added_removed_and_changed_rules() {
+        let old = grammar("a = { \"a\" }\nb = { \"b\" }\n/// C\nc = { \"c\" }\nd = { \"d\" }");
+        let new = grammar("d = @{ \"d\" }\nc = { \"c\" }\ne = { \"e\" }\na = { \"a\" ~ \"b\" }");
+        let diff = compare(&old, &new);
+
+        assert_eq!(diff.added, ["e"]);
+        assert_eq!(diff.removed, ["b"]);
+        let changed: Vec<_> = diff
+            .changed
+            .iter()
+            .map(|rule| (rule.name, rule.changes.clone()))
+            .collect();
+        assert_eq!(
+            changed,
+            [
+                ("d", vec!["modifier"]),
+                ("c", vec!["docs"]),
+                ("a", vec!["expression"])
+            ]
+        );
+    }
+
+    #[test]
+    fn moved_and_reformatted_rules_are_unchanged() {
+        let old = grammar("a = { \"a\" ~ b* }\nb = { \"b\" }");
+        let new = grammar("b = {\"b\"}\n\n\na = {\n    \"a\"\n    ~ b*\n}");
+        let diff = compare(&old, &new);
+
+        assert!(diff.added.is_empty());
+        assert!(diff.removed.is_empty());
+        assert!(diff.changed.is_empty());
+    }
+}
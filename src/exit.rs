@@ -0,0 +1,151 @@
+//! The exit codes of the program (other than 0 for success), which tell build scripts what kind of failure occurred
+
+use std::{error::Error, fmt};
+
+/// A file couldn't be read or written, or the command line or config file was invalid
+pub const ERROR: u8 = 1;
+/// A grammar couldn't be parsed
+pub const PARSE: u8 = 2;
+/// Warnings were produced in strict mode
+pub const STRICT: u8 = 3;
+/// `check` found denied lints
+pub const LINT: u8 = 4;
+
+/// A failure with a specific exit code, whose details have already been reported
+#[derive(Debug)]
+pub struct Failure {
+    pub message: String,
+    pub code: u8,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for Failure {}
+
+/// The exit code for an error
+pub fn code(err: &(dyn Error + 'static)) -> u8 {
+    match (err.downcast_ref::<Failure>(), err.downcast_ref()) {
+        (Some(failure), _) => failure.code,
+        (_, Some(pest_railroad::Error::Parse(_))) => PARSE,
+        _ => ERROR,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use clap::Parser;
+
+    use super::*;
+    use crate::{config_file::CONFIG_FILE_NAME, diagnostics::Reporter, Cli, Command};
+
+    /// The exit code of running a command line on `grammar`, written with an optional config file holding `toml` to a
+    /// temporary directory named after the test (`{}` in the arguments stands for the grammar file)
+    fn exit_code(test: &str, grammar: &str, toml: Option<&str>, args: &[&str]) -> u8 {
+        let dir = env::temp_dir().join("pest_railroad_tests").join(test);
+        fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("grammar.pest");
+        fs::write(&input, grammar).unwrap();
+        let config = dir.join(CONFIG_FILE_NAME);
+        match toml {
+            Some(toml) => fs::write(&config, toml).unwrap(),
+            None => _ = fs::remove_file(&config),
+        }
+
+        let input = input.to_str().unwrap();
+        let output = dir.join("grammar.svg");
+        let messages = dir.join("messages.txt");
+        // Rendering writes the diagram next to the grammar rather than to stdout
+        let output = match args.first() {
+            Some(&"check") => vec![],
+            _ => vec!["-o", output.to_str().unwrap()],
+        };
+        let args = args
+            .iter()
+            .map(|&arg| if arg == "{}" { input } else { arg });
+        let cli = Cli::try_parse_from(
+            ["pest_railroad_gen"]
+                .into_iter()
+                .chain(args)
+                .chain(output)
+                .chain(["--message-output", messages.to_str().unwrap()]),
+        )
+        .unwrap();
+
+        let reporter = Reporter::new(&cli.diagnostics).unwrap();
+        let mut command = cli
+            .command
+            .unwrap_or_else(|| Command::Render(cli.render.unwrap()));
+        let config = match &mut command {
+            Command::Render(args) => args.apply_config(),
+            _ => Ok(()),
+        };
+        match config.and_then(|()| crate::run(&command, &reporter)) {
+            Ok(()) => 0,
+            Err(err) => code(&*err),
+        }
+    }
+
+    const UNUSED_RULE: &str = r#"
+        value = { "a" }
+        unused = { "b" }
+    "#;
+
+    #[test]
+    fn parse_errors_exit_with_parse() {
+        let grammar = "value = { \"a\" ";
+        assert_eq!(
+            exit_code("parse_errors_render", grammar, None, &["{}"]),
+            PARSE
+        );
+        assert_eq!(
+            exit_code("parse_errors_check", grammar, None, &["check", "{}"]),
+            PARSE
+        );
+    }
+
+    #[test]
+    fn strict_warnings_exit_with_strict() {
+        let grammar = "value = { undefined }";
+        assert_eq!(exit_code("strict_warnings", grammar, None, &["{}"]), 0);
+        assert_eq!(
+            exit_code("strict_warnings", grammar, None, &["{}", "--strict"]),
+            STRICT
+        );
+    }
+
+    #[test]
+    fn strict_mode_uses_config_file_lint_levels() {
+        // Unused rules are allowed by default, so only fail in strict mode when the config file warns of them
+        let test = "strict_lint_levels";
+        assert_eq!(exit_code(test, UNUSED_RULE, None, &["{}", "--strict"]), 0);
+        let toml = "strict = true\n[lints]\nunused-rule = \"warn\"";
+        assert_eq!(exit_code(test, UNUSED_RULE, Some(toml), &["{}"]), STRICT);
+
+        let toml = "strict = true\n[lints]\nundefined-rule = \"allow\"";
+        let grammar = "value = { undefined }";
+        assert_eq!(exit_code(test, grammar, Some(toml), &["{}"]), 0);
+    }
+
+    #[test]
+    fn denied_lints_exit_with_lint() {
+        let test = "denied_lints";
+        assert_eq!(exit_code(test, UNUSED_RULE, None, &["check", "{}"]), 0);
+        assert_eq!(
+            exit_code(
+                test,
+                UNUSED_RULE,
+                None,
+                &["check", "{}", "-D", "unused-rule"]
+            ),
+            LINT
+        );
+        let grammar = "value = { undefined }";
+        assert_eq!(exit_code(test, grammar, None, &["check", "{}"]), LINT);
+    }
+}
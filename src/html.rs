@@ -0,0 +1,7 @@
+/// Escapes text for inclusion in HTML (as element content or a quoted attribute value)
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
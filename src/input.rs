@@ -0,0 +1,24 @@
+use std::{
+    fs,
+    io::{self, Read},
+    path::Path,
+};
+
+/// The input path that reads the grammar from stdin
+pub const STDIN: &str = "-";
+
+/// Returns true if the input path refers to stdin rather than a file
+pub fn is_stdin(path: &Path) -> bool {
+    path == Path::new(STDIN)
+}
+
+/// Reads a grammar from the given file, or from stdin if the path is `-`
+pub fn read(path: &Path) -> io::Result<String> {
+    if !is_stdin(path) {
+        return fs::read_to_string(path);
+    }
+
+    let mut src = String::new();
+    io::stdin().read_to_string(&mut src)?;
+    Ok(src)
+}
@@ -0,0 +1,69 @@
+use std::{
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+use clap::Args;
+
+#[derive(Args, Clone, Copy)]
+pub struct JobsArgs {
+    /// The number of diagrams to render at once [default: the number of CPUs]
+    #[arg(short, long, value_name = "N")]
+    jobs: Option<NonZeroUsize>,
+}
+
+impl JobsArgs {
+    /// The number of threads to render on
+    fn threads(self) -> usize {
+        self.jobs
+            .or_else(|| thread::available_parallelism().ok())
+            .map_or(1, NonZeroUsize::get)
+    }
+
+    /// Calls `work` on each item (along with the number of items already finished, for progress reporting), spread
+    /// across the requested number of threads. Returns the results in the order of the items.
+    pub fn run<T: Sync, R: Send>(
+        self,
+        items: &[T],
+        work: impl Fn(usize, &T) -> R + Sync,
+    ) -> Vec<R> {
+        let threads = self.threads().min(items.len());
+        if threads <= 1 {
+            return items
+                .iter()
+                .enumerate()
+                .map(|(done, item)| work(done, item))
+                .collect();
+        }
+
+        let next = AtomicUsize::new(0);
+        let done = AtomicUsize::new(0);
+        let results = Mutex::new(items.iter().map(|_| None).collect::<Vec<_>>());
+        thread::scope(|scope| {
+            for _ in 0..threads {
+                scope.spawn(|| loop {
+                    let idx = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(item) = items.get(idx) else {
+                        break;
+                    };
+                    let result = work(done.load(Ordering::Relaxed), item);
+                    done.fetch_add(1, Ordering::Relaxed);
+                    // Panic safety: A panic while holding the lock would have already ended the program
+                    results.lock().expect("results lock")[idx] = Some(result);
+                });
+            }
+        });
+
+        // Panic safety: Every item was taken by a thread, and the scope only ends once they have all finished
+        results
+            .into_inner()
+            .expect("results lock")
+            .into_iter()
+            .map(|result| result.expect("finished item"))
+            .collect()
+    }
+}
@@ -0,0 +1,95 @@
+use std::{
+    error::Error,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+};
+
+use clap::{Args, ValueEnum};
+use pest_railroad::{Config, Format, Grammar, Theme};
+
+use crate::render::{LookaheadStyleName, ThemeName};
+
+#[derive(Args)]
+pub struct LegendArgs {
+    /// Write the legend to this file instead of stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// The output format [default: svg]
+    #[arg(short, long, value_enum)]
+    format: Option<LegendFormat>,
+
+    /// The diagram stylesheet, which should match the one of the diagrams the legend is shown with [default: light]
+    #[arg(short, long, value_enum)]
+    theme: Option<ThemeName>,
+
+    /// How lookahead is drawn, which should match the diagrams too [default: labeled]
+    #[arg(long, value_enum, value_name = "STYLE")]
+    lookahead_style: Option<LookaheadStyleName>,
+
+    /// Add a sample of the stack operations, for diagrams drawn with --stack-ops
+    #[arg(long)]
+    stack_ops: bool,
+}
+
+/// The formats that draw the legend (the others don't draw diagrams)
+#[derive(Clone, Copy, ValueEnum)]
+enum LegendFormat {
+    /// An SVG diagram
+    Svg,
+    /// A standalone HTML page embedding the diagram
+    Html,
+    /// A PNG image
+    #[cfg(feature = "png")]
+    Png,
+    /// A vector PDF
+    #[cfg(feature = "pdf")]
+    Pdf,
+}
+
+impl From<LegendFormat> for Format {
+    fn from(format: LegendFormat) -> Self {
+        match format {
+            LegendFormat::Svg => Format::Svg,
+            LegendFormat::Html => Format::Html,
+            #[cfg(feature = "png")]
+            LegendFormat::Png => Format::Png,
+            #[cfg(feature = "pdf")]
+            LegendFormat::Pdf => Format::Pdf,
+        }
+    }
+}
+
+/// Renders the legend on its own, for documentation to include once rather than after every diagram
+pub fn legend(args: &LegendArgs) -> Result<(), Box<dyn Error>> {
+    // The legend of a grammar without rules
+    let grammar = Grammar {
+        docs: Vec::new(),
+        rules: Vec::new(),
+    };
+    let config = Config {
+        theme: args.theme.map(Theme::from).unwrap_or_default(),
+        title: Some("Legend".to_string()),
+        legend: true,
+        lookahead_style: args.lookahead_style.map(Into::into).unwrap_or_default(),
+        stack_ops: args.stack_ops,
+        // HTML pages would otherwise draw a diagram for each of the (no) rules
+        combined: true,
+        ..Config::default()
+    };
+    let format = args.format.unwrap_or(LegendFormat::Svg).into();
+
+    match &args.output {
+        Some(output) => {
+            if let Some(dir) = output.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let mut file = BufWriter::new(File::create(output)?);
+            pest_railroad::render(&grammar, &config, format, &mut file)?;
+            file.flush()?;
+        }
+        None => pest_railroad::render(&grammar, &config, format, &mut io::stdout().lock())?,
+    }
+    Ok(())
+}
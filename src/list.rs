@@ -0,0 +1,219 @@
+use std::{error::Error, path::PathBuf};
+
+use clap::Args;
+use pest_railroad::{DependencyGraph, Expr, Grammar, Modifier};
+use serde::Serialize;
+
+use crate::input;
+
+#[derive(Args)]
+pub struct ListArgs {
+    /// The pest grammar file to list the rules of (`-` reads it from stdin)
+    pub input: PathBuf,
+
+    /// Print the rules as JSON instead of a table
+    #[arg(long, conflicts_with_all = ["csv", "tsv"])]
+    json: bool,
+
+    /// Print the rules as CSV with a header row, for spreadsheets
+    #[arg(long, conflicts_with = "tsv")]
+    csv: bool,
+
+    /// Print the rules as tab-separated values with a header row, for spreadsheets
+    #[arg(long)]
+    tsv: bool,
+}
+
+#[derive(Serialize)]
+struct RuleInfo<'a> {
+    name: &'a str,
+    modifier: Modifier,
+    line: Option<usize>,
+    docs: &'a [String],
+    /// The number of rules referencing this rule
+    referenced_by: usize,
+    /// The number of distinct rules (and built-ins) this rule references
+    refers_to: usize,
+    /// The number of terminals (strings and character ranges) in this rule
+    terminals: usize,
+}
+
+pub fn list(args: &ListArgs) -> Result<(), Box<dyn Error>> {
+    let src = input::read(&args.input)?;
+    let (grammar, _) = Grammar::parse(&src)?;
+    let graph = DependencyGraph::new(&grammar);
+
+    let rules: Vec<_> = grammar
+        .rules
+        .iter()
+        .map(|rule| RuleInfo {
+            name: &rule.name,
+            modifier: rule.modifier,
+            line: rule.line,
+            docs: &rule.docs,
+            referenced_by: graph.referenced_by(&rule.name).count(),
+            refers_to: graph.references(&rule.name).len(),
+            terminals: terminals(&rule.expr),
+        })
+        .collect();
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&rules)?);
+    } else if args.csv || args.tsv {
+        print!("{}", delimited(&rules, if args.csv { ',' } else { '\t' }));
+    } else {
+        print_table(&rules);
+    }
+    Ok(())
+}
+
+/// The number of terminals in an expression
+fn terminals(expr: &Expr) -> usize {
+    let mut count = 0;
+    expr.walk(&mut |expr| {
+        if matches!(
+            expr,
+            Expr::Str { .. } | Expr::InsensitiveStr { .. } | Expr::Range { .. }
+        ) {
+            count += 1;
+        }
+    });
+    count
+}
+
+/// The columns of the CSV and TSV output, named like the fields of the JSON output
+const COLUMNS: [&str; 7] = [
+    "name",
+    "modifier",
+    "line",
+    "referenced_by",
+    "refers_to",
+    "terminals",
+    "docs",
+];
+
+/// Every detail of the rules as CSV (with a `,` delimiter) or TSV (with a tab), one row per rule
+fn delimited(rules: &[RuleInfo], delimiter: char) -> String {
+    let field = |text: &str| {
+        if delimiter == ',' {
+            // RFC 4180 quoting, for the fields that need it
+            if text.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", text.replace('"', "\"\""))
+            } else {
+                text.to_string()
+            }
+        } else {
+            // TSV has no quoting, so the delimiters are replaced instead
+            text.replace(['\t', '\n', '\r'], " ")
+        }
+    };
+
+    let mut out = COLUMNS.join(&delimiter.to_string()) + "\n";
+    for rule in rules {
+        let row = [
+            field(rule.name),
+            rule.modifier.label().unwrap_or("normal").to_string(),
+            rule.line.map(|line| line.to_string()).unwrap_or_default(),
+            rule.referenced_by.to_string(),
+            rule.refers_to.to_string(),
+            rule.terminals.to_string(),
+            // Every doc comment line, so each rule stays on one row
+            field(&rule.docs.join(" ")),
+        ];
+        out += &row.join(&delimiter.to_string());
+        out.push('\n');
+    }
+    out
+}
+
+fn print_table(rules: &[RuleInfo]) {
+    let rows: Vec<[String; 4]> = rules
+        .iter()
+        .map(|rule| {
+            [
+                rule.name.to_string(),
+                rule.modifier.label().unwrap_or("normal").to_string(),
+                rule.referenced_by.to_string(),
+                // Only the first line of the docs fits in a table
+                rule.docs.first().cloned().unwrap_or_default(),
+            ]
+        })
+        .collect();
+
+    let header = ["RULE", "MODIFIER", "REFS", "DOC"].map(String::from);
+    let mut widths = header.clone().map(|col| col.len());
+    for row in &rows {
+        for (width, col) in widths.iter_mut().zip(row) {
+            *width = (*width).max(col.chars().count());
+        }
+    }
+
+    for row in [header].iter().chain(&rows) {
+        let line = format!(
+            "{:<w0$}  {:<w1$}  {:>w2$}  {}",
+            row[0],
+            row[1],
+            row[2],
+            row[3],
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2],
+        );
+        println!("{}", line.trim_end());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A listed rule with the given name and docs
+    fn rule<'a>(name: &'a str, docs: &'a [String]) -> RuleInfo<'a> {
+        RuleInfo {
+            name,
+            modifier: Modifier::Atomic,
+            line: Some(3),
+            docs,
+            referenced_by: 2,
+            refers_to: 1,
+            terminals: 4,
+        }
+    }
+
+    #[test]
+    fn csv_quotes_special_fields() {
+        let docs = ["A \"quoted\", comma".to_string(), "tab\there".to_string()];
+        let multiline = ["first\nsecond".to_string()];
+        let out = delimited(&[rule("value", &docs), rule("plain", &multiline)], ',');
+        assert_eq!(
+            out,
+            "name,modifier,line,referenced_by,refers_to,terminals,docs\n\
+             value,atomic,3,2,1,4,\"A \"\"quoted\"\", comma tab\there\"\n\
+             plain,atomic,3,2,1,4,\"first\nsecond\"\n"
+        );
+    }
+
+    #[test]
+    fn tsv_replaces_delimiters() {
+        let docs = [
+            "A \"quoted\", comma".to_string(),
+            "tab\there\nand\r\nlines".to_string(),
+        ];
+        let out = delimited(&[rule("value", &docs)], '\t');
+        assert_eq!(
+            out,
+            "name\tmodifier\tline\treferenced_by\trefers_to\tterminals\tdocs\n\
+             value\tatomic\t3\t2\t1\t4\tA \"quoted\", comma tab here and  lines\n"
+        );
+    }
+
+    #[test]
+    fn columns_match_json_fields() {
+        let json = serde_json::to_value(rule("value", &[])).unwrap();
+        let mut fields: Vec<_> = json.as_object().unwrap().keys().cloned().collect();
+        let mut columns = COLUMNS.map(String::from).to_vec();
+        fields.sort();
+        columns.sort();
+        assert_eq!(fields, columns);
+    }
+}
@@ -1,15 +1,178 @@
-use std::{env, fs};
+mod check;
+mod config_file;
+mod crate_docs;
+mod deps;
+mod diagnostics;
+mod diff;
+mod exit;
+mod html;
+mod input;
+mod jobs;
+mod legend;
+mod list;
+mod man;
+mod mdbook;
+mod open;
+mod per_rule;
+#[cfg(feature = "png")]
+mod preview;
+mod render;
+mod serve;
+mod stats;
+mod watch;
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // TODO: Replace with clap
-    let filename = env::args().nth(1).ok_or_else(|| "No filename provided")?;
-    let src = fs::read_to_string(filename)?;
-    let (diagram, warnings) = pest_railroad::generate_diagram(&src)?;
+use std::{error::Error, path::Path, process::ExitCode};
 
-    for warning in warnings {
-        eprintln!("Warning: {}", warning);
+use clap::{CommandFactory, Parser, Subcommand};
+
+use crate::{
+    check::CheckArgs,
+    crate_docs::CrateArgs,
+    deps::DepsArgs,
+    diagnostics::{DiagnosticArgs, Reporter},
+    diff::DiffArgs,
+    legend::LegendArgs,
+    list::ListArgs,
+    man::ManArgs,
+    mdbook::MdbookArgs,
+    render::RenderArgs,
+    serve::ServeArgs,
+    stats::StatsArgs,
+};
+
+/// Railroad (aka syntax) SVG diagram generator for Pest grammars
+#[derive(Parser)]
+#[command(
+    version,
+    about,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    render: Option<RenderArgs>,
+
+    #[command(flatten)]
+    diagnostics: DiagnosticArgs,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Render a grammar into a railroad diagram (the default when no subcommand is given)
+    Render(RenderArgs),
+    /// Check grammars for errors and lint problems without rendering them
+    Check(CheckArgs),
+    /// Render every grammar referenced by a `#[grammar]` attribute in the current crate (or workspace) into
+    /// `target/doc/railroad` (also available as `cargo pest-railroad`)
+    Crate(CrateArgs),
+    /// Print the rule reference graph of a grammar for Graphviz or Mermaid
+    Deps(DepsArgs),
+    /// List the rules added, removed and changed between two versions of a grammar
+    Diff(DiffArgs),
+    /// Draw the legend explaining the notation of the diagrams on its own, for documentation to include once
+    Legend(LegendArgs),
+    /// List the rules of a grammar along with their modifiers, doc comments and reference counts
+    List(ListArgs),
+    /// Print the man page of this tool, generated from its command line definition
+    Man(ManArgs),
+    /// Run as an mdBook preprocessor, replacing `pest` code blocks and `{{#railroad path}}` directives in the book's
+    /// chapters with their diagrams
+    Mdbook(MdbookArgs),
+    /// Serve a live-reloading HTML preview of a grammar's diagrams, regenerated whenever the grammar changes
+    Serve(ServeArgs),
+    /// Print statistics about a grammar, such as rule and terminal counts and the size of its diagram
+    Stats(StatsArgs),
+}
+
+impl Command {
+    /// The grammar file the command operates on (if it operates on exactly one)
+    fn input(&self) -> Option<&Path> {
+        match self {
+            Command::Render(args) => Some(&args.input),
+            Command::Check(args) => match args.inputs.as_slice() {
+                [input] => Some(input),
+                _ => None,
+            },
+            Command::Crate(_) => None,
+            Command::Deps(args) => Some(&args.input),
+            Command::Diff(args) => Some(&args.new),
+            Command::Legend(_) => None,
+            Command::List(args) => Some(&args.input),
+            Command::Man(_) => None,
+            Command::Mdbook(_) => None,
+            Command::Serve(args) => Some(&args.input),
+            Command::Stats(args) => Some(&args.input),
+        }
     }
+}
+
+fn run(command: &Command, reporter: &Reporter) -> Result<(), Box<dyn Error>> {
+    match command {
+        Command::Render(args) if args.watch => render::watch(args, reporter),
+        Command::Render(args) => render::render_once(args, reporter),
+        Command::Check(args) => check::check(args, reporter),
+        Command::Crate(args) => crate_docs::render_crate(args, reporter),
+        Command::Deps(args) => deps::deps(args),
+        Command::Diff(args) => diff::diff(args),
+        Command::Legend(args) => legend::legend(args),
+        Command::List(args) => list::list(args),
+        Command::Man(args) => man::man(args, Cli::command()),
+        Command::Mdbook(args) => mdbook::mdbook(args, reporter),
+        Command::Serve(args) => serve::serve(args, reporter),
+        Command::Stats(args) => stats::stats(args),
+    }
+}
 
-    println!("{diagram}");
-    Ok(())
+fn main() -> ExitCode {
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        // Clap would exit with 2 for usage errors, which is the exit code for grammar parse errors here, so they're
+        // deliberately remapped to 1 like any other invalid command line
+        Err(err) => {
+            let _ = err.print();
+            return if err.use_stderr() {
+                ExitCode::from(exit::ERROR)
+            } else {
+                ExitCode::SUCCESS
+            };
+        }
+    };
+    let mut command = match cli.command {
+        Some(command) => command,
+        // Panic safety: Clap requires the render args when no subcommand is given
+        None => Command::Render(cli.render.expect("render args")),
+    };
+
+    let reporter = match Reporter::new(&cli.diagnostics) {
+        Ok(reporter) => reporter,
+        Err(err) => {
+            eprintln!("Error: Unable to open message output: {err}");
+            return ExitCode::from(exit::ERROR);
+        }
+    };
+
+    let config = match &mut command {
+        Command::Render(args) => args.apply_config(),
+        Command::Serve(args) => args.apply_config(),
+        Command::Diff(args) => args.apply_config(),
+        // Check, crate and mdbook load the config file of each grammar themselves
+        Command::Check(_)
+        | Command::Crate(_)
+        | Command::Mdbook(_)
+        | Command::Deps(_)
+        | Command::Legend(_)
+        | Command::List(_)
+        | Command::Man(_)
+        | Command::Stats(_) => Ok(()),
+    };
+    match config.and_then(|()| run(&command, &reporter)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            reporter.error(command.input(), &*err);
+            ExitCode::from(exit::code(&*err))
+        }
+    }
 }
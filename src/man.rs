@@ -0,0 +1,24 @@
+use std::{error::Error, fs, io, path::PathBuf};
+
+use clap::Args;
+use clap_mangen::Man;
+
+#[derive(Args)]
+pub struct ManArgs {
+    /// Write a page for the tool and one for each subcommand (e.g. `pest_railroad_gen-check.1`) into this directory
+    /// instead of printing the tool's page
+    #[arg(short, long, value_name = "DIR")]
+    out_dir: Option<PathBuf>,
+}
+
+/// Generates the roff man page(s) of `cmd`, the definition of the whole command line
+pub fn man(args: &ManArgs, cmd: clap::Command) -> Result<(), Box<dyn Error>> {
+    match &args.out_dir {
+        Some(dir) => {
+            fs::create_dir_all(dir)?;
+            clap_mangen::generate_to(cmd, dir)?;
+        }
+        None => Man::new(cmd).render(&mut io::stdout().lock())?,
+    }
+    Ok(())
+}
@@ -0,0 +1,194 @@
+use std::{
+    error::Error,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use clap::{Args, Subcommand};
+use pest_railroad::{Format, Grammar};
+use serde_json::Value;
+
+use crate::{
+    diagnostics::Reporter,
+    input,
+    render::{ConfigArgs, DiagramArgs},
+};
+
+/// The directive that renders a grammar file in place, as in `{{#railroad grammar.pest}}`
+const DIRECTIVE: &str = "{{#railroad";
+
+#[derive(Args)]
+pub struct MdbookArgs {
+    #[command(subcommand)]
+    command: Option<MdbookCommand>,
+
+    #[command(flatten)]
+    pub diagram: DiagramArgs,
+
+    #[command(flatten)]
+    pub config: ConfigArgs,
+}
+
+#[derive(Subcommand)]
+enum MdbookCommand {
+    /// Tell mdBook whether a renderer is supported (they all are, as the diagrams are plain SVG)
+    Supports { renderer: String },
+}
+
+/// Runs as an mdBook preprocessor: reads the `[context, book]` JSON from stdin, renders the ```` ```pest ```` code
+/// blocks and `{{#railroad path [rules]}}` directives of each chapter into diagrams, and writes the book back to
+/// stdout
+pub fn mdbook(args: &MdbookArgs, reporter: &Reporter) -> Result<(), Box<dyn Error>> {
+    if let Some(MdbookCommand::Supports { .. }) = &args.command {
+        return Ok(());
+    }
+
+    let (context, mut book): (Value, Value) = serde_json::from_reader(io::stdin().lock())
+        .map_err(|err| format!("Unable to read the book from mdBook: {err}"))?;
+    let root = context["root"]
+        .as_str()
+        .map(PathBuf::from)
+        .unwrap_or_default();
+    let src_dir = root.join(context["config"]["book"]["src"].as_str().unwrap_or("src"));
+
+    // Newer versions of mdBook call the sections items
+    let key = if book.get("items").is_some() {
+        "items"
+    } else {
+        "sections"
+    };
+    if let Some(items) = book[key].as_array_mut() {
+        process_items(items, &src_dir, args, reporter)?;
+    }
+
+    let mut stdout = io::stdout().lock();
+    serde_json::to_writer(&mut stdout, &book)?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Renders the diagrams of each chapter in the list, along with those of its sub chapters
+fn process_items(
+    items: &mut [Value],
+    src_dir: &Path,
+    args: &MdbookArgs,
+    reporter: &Reporter,
+) -> Result<(), Box<dyn Error>> {
+    for item in items {
+        // Separators and part titles have no content
+        let Some(chapter) = item.get_mut("Chapter") else {
+            continue;
+        };
+
+        // Draft chapters have no file
+        let path = chapter["path"].as_str().map(|path| src_dir.join(path));
+        if let (Some(path), Some(content)) = (&path, chapter["content"].as_str()) {
+            let content = process_chapter(content, path, args, reporter)
+                .map_err(|err| format!("{}: {err}", path.display()))?;
+            chapter["content"] = Value::String(content);
+        }
+        if let Some(sub_items) = chapter["sub_items"].as_array_mut() {
+            process_items(sub_items, src_dir, args, reporter)?;
+        }
+    }
+    Ok(())
+}
+
+/// Replaces the pest code blocks and railroad directives of a chapter (read from `path`) with their diagrams
+fn process_chapter(
+    content: &str,
+    path: &Path,
+    args: &MdbookArgs,
+    reporter: &Reporter,
+) -> Result<String, Box<dyn Error>> {
+    let mut out = String::with_capacity(content.len());
+    let mut lines = content.lines();
+
+    while let Some(line) = lines.next() {
+        let fence = line.trim_start();
+        let fence = fence
+            .strip_prefix("```")
+            .or_else(|| fence.strip_prefix("~~~"));
+        if fence.is_some_and(|info| info.trim() == "pest") {
+            let mut src = String::new();
+            for line in lines.by_ref() {
+                if matches!(line.trim(), "```" | "~~~") {
+                    break;
+                }
+                src.push_str(line);
+                src.push('\n');
+            }
+            // A code block has no file name to title the diagram after
+            let svg = render_svg(&src, path, Path::new(input::STDIN), &[], args, reporter)?;
+            push_diagram(&mut out, &svg);
+            continue;
+        }
+
+        let mut rest = line;
+        while let Some(start) = rest.find(DIRECTIVE) {
+            let Some(len) = rest[start..].find("}}") else {
+                break;
+            };
+            out.push_str(&rest[..start]);
+            let mut words = rest[start + DIRECTIVE.len()..start + len].split_whitespace();
+            let Some(file) = words.next() else {
+                return Err("{{#railroad}} needs the path of a grammar file".into());
+            };
+            let rules = words
+                .flat_map(|rules| rules.split(','))
+                .map(str::to_string)
+                .collect::<Vec<_>>();
+
+            // Like mdBook's own includes, the path is relative to the chapter
+            let file = path.parent().unwrap_or(Path::new("")).join(file);
+            let src = input::read(&file)
+                .map_err(|err| format!("Unable to read {}: {err}", file.display()))?;
+            let svg = render_svg(&src, &file, &file, &rules, args, reporter)?;
+            push_diagram(&mut out, &svg);
+            rest = &rest[start + len + 2..];
+        }
+        out.push_str(rest);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Renders a grammar into an SVG diagram, limited to the given rules (if any). Warnings are reported against `file`,
+/// whose project config file (if any) applies, while the diagram is titled after `input`.
+fn render_svg(
+    src: &str,
+    file: &Path,
+    input: &Path,
+    rules: &[String],
+    args: &MdbookArgs,
+    reporter: &Reporter,
+) -> Result<String, Box<dyn Error>> {
+    let mut diagram = args.diagram.clone();
+    if let Some(config) = args.config.load(file)? {
+        diagram.apply_config(&config);
+    }
+
+    let (grammar, warnings) = Grammar::parse(src)?;
+    for warning in &warnings {
+        reporter.warning(file, warning);
+    }
+    let mut config = diagram.config(&grammar, input)?;
+    if !rules.is_empty() {
+        config.include = rules.to_vec();
+    }
+
+    let mut svg = Vec::new();
+    pest_railroad::render(&grammar, &config, Format::Svg, &mut svg)?;
+    Ok(String::from_utf8(svg)?)
+}
+
+/// Appends a diagram to the chapter as a block of raw HTML
+fn push_diagram(out: &mut String, svg: &str) {
+    out.push('\n');
+    // A blank line would end the raw HTML block
+    for line in svg.lines().filter(|line| !line.trim().is_empty()) {
+        out.push_str(line);
+        out.push('\n');
+    }
+    out.push('\n');
+}
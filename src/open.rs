@@ -0,0 +1,30 @@
+use std::{io, path::Path, process::Command};
+
+/// Opens a file with the system's default application (typically a browser for SVG and HTML files)
+pub fn open_in_viewer(path: &Path) -> io::Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        // The empty argument is the window title `start` expects before the path
+        command.args(["/C", "start", ""]);
+        command
+    } else {
+        Command::new("xdg-open")
+    };
+
+    let status = command.arg(path).status().map_err(|err| {
+        io::Error::new(
+            err.kind(),
+            format!("Unable to launch a viewer for {}: {err}", path.display()),
+        )
+    })?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!(
+            "Unable to open {} ({status})",
+            path.display()
+        )))
+    }
+}
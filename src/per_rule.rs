@@ -0,0 +1,193 @@
+use std::{
+    error::Error,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use pest_railroad::{Config, Format, Grammar};
+
+use crate::{diagnostics::Reporter, html::escape, jobs::JobsArgs};
+
+/// The name of the page embedding every per-rule diagram
+pub fn index_file_name(format: Format) -> &'static str {
+    match format {
+        Format::Markdown => "index.md",
+        _ => "index.html",
+    }
+}
+
+/// The format of the per-rule diagrams: that of the output, other than Markdown output, which is an index page
+/// referencing SVG diagrams
+pub fn image_format(format: Format) -> Format {
+    match format {
+        Format::Markdown => Format::Svg,
+        format => format,
+    }
+}
+
+/// Whether a format can be written per rule. Only images can be shown on the index page.
+pub fn supports(format: Format) -> bool {
+    match format {
+        Format::Svg | Format::Markdown | Format::Rustdoc => true,
+        #[cfg(feature = "png")]
+        Format::Png => true,
+        _ => false,
+    }
+}
+
+/// Where (and how) the per-rule diagrams are written
+pub struct PerRuleOutput<'a> {
+    pub format: Format,
+    pub dir: &'a Path,
+    /// The path (relative to `dir`) of the file of a rule
+    pub file_name: &'a (dyn Fn(&str) -> PathBuf + Sync),
+    pub jobs: JobsArgs,
+}
+
+/// Writes the diagram of each rule selected by the config to its own file in the output directory (in parallel), along
+/// with an index page (HTML, or Markdown for Markdown output) showing every diagram under a heading with the rule's doc
+/// comments. Progress is shown on the
+/// reporter (if given), along with the time taken by each rule if `verbose` is set.
+pub fn render_per_rule(
+    grammar: &Grammar,
+    config: &Config,
+    output: &PerRuleOutput,
+    reporter: Option<&Reporter>,
+    verbose: bool,
+) -> Result<(), Box<dyn Error>> {
+    let PerRuleOutput {
+        format,
+        dir,
+        file_name,
+        jobs,
+    } = *output;
+    if !supports(format) {
+        return Err("--per-rule only supports the svg, png, md and rustdoc formats".into());
+    }
+    fs::create_dir_all(dir)?;
+
+    let selected = config.select(grammar);
+    let mut rules = selected.rules.iter().collect::<Vec<_>>();
+    // Pest doesn't allow duplicate rules, but if there are any they share a file
+    rules.dedup_by(|a, b| a.name == b.name);
+
+    let results = jobs.run(&rules, |done, rule| {
+        if let Some(reporter) = reporter {
+            reporter.progress(done, rules.len(), &rule.name);
+        }
+        let start = Instant::now();
+
+        // Only the warnings between the rule and the next one belong in its diagram (factored rules have none)
+        let end = grammar
+            .rules
+            .iter()
+            .filter_map(|other| other.line)
+            .filter(|&line| rule.line.is_some_and(|start| line > start))
+            .min();
+        let rule_config = Config {
+            // Rule names never contain wildcards
+            include: vec![rule.name.clone()],
+            start_rule: None,
+            title: Some(config.label(rule)),
+            warnings: config
+                .warnings
+                .iter()
+                .filter(|warning| {
+                    rule.line.is_some_and(|start| warning.line >= start)
+                        && end.is_none_or(|end| warning.line < end)
+                })
+                .cloned()
+                .collect(),
+            // The diagram of every rule shares the same notation, so explaining it in each would be noise
+            legend: false,
+            ..config.clone()
+        };
+        let path = dir.join(file_name(&rule.name));
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = BufWriter::new(File::create(path)?);
+        pest_railroad::render(grammar, &rule_config, image_format(format), &mut file)?;
+        file.flush()?;
+
+        if let Some(reporter) = reporter.filter(|_| verbose) {
+            reporter.status(&format!(
+                "Rendered rule {} in {:.2?}",
+                rule.name,
+                start.elapsed()
+            ));
+        }
+        // Errors cross threads, so they have to be sendable
+        Ok::<_, Box<dyn Error + Send + Sync>>(())
+    });
+    // The bar has to go even if a rule failed, or the error would be drawn above it
+    if let Some(reporter) = reporter {
+        reporter.finish_progress();
+    }
+    results
+        .into_iter()
+        .collect::<Result<(), _>>()
+        .map_err(|err| err as Box<dyn Error>)?;
+
+    // Rustdoc fragments are included into the documentation of each rule instead
+    if format == Format::Rustdoc {
+        return Ok(());
+    }
+
+    let mut index = BufWriter::new(File::create(dir.join(index_file_name(format)))?);
+    if format == Format::Markdown {
+        let index_config = Config {
+            // Expanding the template for a rule named `{rule}` leaves the placeholder for the backend
+            image_template: Some(relative_url(&file_name("{rule}"))),
+            ..config.clone()
+        };
+        pest_railroad::render(grammar, &index_config, format, &mut index)?;
+        index.flush()?;
+        return Ok(());
+    }
+
+    let title = escape(config.title(grammar).unwrap_or("Railroad diagrams"));
+    writeln!(index, "<!DOCTYPE html>")?;
+    writeln!(index, "<html>")?;
+    writeln!(index, "<head>")?;
+    writeln!(index, "<meta charset=\"utf-8\">")?;
+    writeln!(index, "<title>{title}</title>")?;
+    writeln!(index, "</head>")?;
+    writeln!(index, "<body>")?;
+    writeln!(index, "<h1>{title}</h1>")?;
+    for rule in &rules {
+        // Rule names are identifiers, so they never need escaping
+        writeln!(index, "<section id=\"{}\">", rule.name)?;
+        writeln!(
+            index,
+            "<h2><a href=\"#{}\">{}</a></h2>",
+            rule.name,
+            config.label(rule)
+        )?;
+        for doc in &rule.docs {
+            writeln!(index, "<p>{}</p>", escape(doc))?;
+        }
+        writeln!(
+            index,
+            "<img src=\"{}\" alt=\"Railroad diagram of {}\">",
+            escape(&relative_url(&file_name(&rule.name))),
+            rule.name
+        )?;
+        writeln!(index, "</section>")?;
+    }
+    writeln!(index, "</body>")?;
+    writeln!(index, "</html>")?;
+    index.flush()?;
+    Ok(())
+}
+
+/// The URL of a file at a path relative to the index page
+fn relative_url(path: &Path) -> String {
+    let segments = path
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>();
+    segments.join("/")
+}
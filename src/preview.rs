@@ -0,0 +1,214 @@
+use std::{
+    env,
+    error::Error,
+    io::{self, IsTerminal, Write},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use clap::ValueEnum;
+
+/// The largest chunk of base64 data the kitty graphics protocol accepts in one escape sequence
+const KITTY_CHUNK: usize = 4096;
+
+/// The levels of each channel of the sixel palette, which is a 6x6x6 color cube
+const LEVELS: u8 = 6;
+
+/// How an image is shown in the terminal
+#[derive(Clone, Copy, ValueEnum)]
+pub enum PreviewProtocol {
+    /// The kitty graphics protocol if the terminal looks like it supports it, or else sixel
+    Auto,
+    /// The kitty graphics protocol (kitty, WezTerm, Ghostty and Konsole)
+    Kitty,
+    /// Sixel graphics (xterm, foot, mlterm, iTerm2 and others)
+    Sixel,
+}
+
+impl PreviewProtocol {
+    /// Resolves `Auto` by looking at the variables terminals set, as asking the terminal would need raw mode
+    fn resolve(self) -> Self {
+        match self {
+            PreviewProtocol::Auto => {
+                let term = env::var("TERM").unwrap_or_default();
+                let program = env::var("TERM_PROGRAM").unwrap_or_default();
+                if env::var_os("KITTY_WINDOW_ID").is_some()
+                    || term.contains("kitty")
+                    || term.contains("ghostty")
+                    || matches!(program.as_str(), "WezTerm" | "ghostty")
+                    || env::var_os("KONSOLE_VERSION").is_some()
+                {
+                    PreviewProtocol::Kitty
+                } else {
+                    PreviewProtocol::Sixel
+                }
+            }
+            protocol => protocol,
+        }
+    }
+}
+
+/// Shows a PNG image inline in the terminal on stdout
+pub fn show(png: &[u8], protocol: PreviewProtocol) -> Result<(), Box<dyn Error>> {
+    let stdout = io::stdout();
+    if !stdout.is_terminal() {
+        return Err("--preview needs stdout to be a terminal".into());
+    }
+
+    let mut out = stdout.lock();
+    match protocol.resolve() {
+        PreviewProtocol::Kitty => write_kitty(png, &mut out)?,
+        _ => write_sixel(png, &mut out)?,
+    }
+    writeln!(out)?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Writes the escape sequences transmitting and displaying a PNG with the kitty graphics protocol, which decodes the
+/// PNG itself
+fn write_kitty(png: &[u8], out: &mut dyn Write) -> io::Result<()> {
+    let data = STANDARD.encode(png);
+    let chunks = data.as_bytes().chunks(KITTY_CHUNK).collect::<Vec<_>>();
+    for (idx, chunk) in chunks.iter().enumerate() {
+        // Only the first chunk carries the format and action, and every chunk but the last says more will follow
+        let more = u8::from(idx + 1 < chunks.len());
+        if idx == 0 {
+            write!(out, "\x1b_Gf=100,a=T,m={more};")?;
+        } else {
+            write!(out, "\x1b_Gm={more};")?;
+        }
+        out.write_all(chunk)?;
+        write!(out, "\x1b\\")?;
+    }
+    Ok(())
+}
+
+/// A decoded image
+struct Image {
+    width: usize,
+    height: usize,
+    /// The RGB color of each pixel, row by row
+    pixels: Vec<[u8; 3]>,
+}
+
+/// Decodes a PNG, blending any transparency with a white background
+fn decode(png: &[u8]) -> Result<Image, Box<dyn Error>> {
+    let mut decoder = png::Decoder::new(png);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf)?;
+    let (width, height) = (info.width as usize, info.height as usize);
+
+    let channels = info.color_type.samples();
+    let pixels = buf[..info.buffer_size()]
+        .chunks(channels)
+        .map(|pixel| {
+            let (rgb, alpha) = match *pixel {
+                [gray] => ([gray; 3], 255),
+                [gray, alpha] => ([gray; 3], alpha),
+                [r, g, b] => ([r, g, b], 255),
+                [r, g, b, alpha] => ([r, g, b], alpha),
+                // Indexed images are expanded into RGB by the transformations
+                _ => ([255; 3], 0),
+            };
+            rgb.map(|value| {
+                let blended = value as u32 * alpha as u32 + 255 * (255 - alpha as u32);
+                (blended / 255) as u8
+            })
+        })
+        .collect();
+    Ok(Image {
+        width,
+        height,
+        pixels,
+    })
+}
+
+/// The index of the closest color in the palette to a pixel
+fn palette_index([r, g, b]: [u8; 3]) -> usize {
+    let level = |value: u8| ((value as u32 * (LEVELS as u32 - 1) + 127) / 255) as usize;
+    let levels = LEVELS as usize;
+    (level(r) * levels + level(g)) * levels + level(b)
+}
+
+/// Writes the escape sequence drawing a PNG with sixel graphics, which the image is converted into first (with its
+/// colors reduced to a fixed palette, which suits the flat colors of diagrams)
+fn write_sixel(png: &[u8], out: &mut dyn Write) -> Result<(), Box<dyn Error>> {
+    let Image {
+        width,
+        height,
+        pixels,
+    } = decode(png)?;
+    let indices = pixels.into_iter().map(palette_index).collect::<Vec<_>>();
+    let levels = LEVELS as usize;
+
+    let mut sixel = String::new();
+    // Pixels have a 1:1 aspect ratio, and unset pixels keep the background color
+    sixel.push_str(&format!("\x1bP0;1;0q\"1;1;{width};{height}"));
+    for idx in 0..levels.pow(3) {
+        let percent = |level: usize| level * 100 / (levels - 1);
+        sixel.push_str(&format!(
+            "#{idx};2;{};{};{}",
+            percent(idx / levels / levels),
+            percent(idx / levels % levels),
+            percent(idx % levels)
+        ));
+    }
+
+    // Each band is six rows high, and is drawn one color at a time
+    for top in (0..height).step_by(6) {
+        let rows = (top..(top + 6).min(height)).collect::<Vec<_>>();
+        let mut colors = rows
+            .iter()
+            .flat_map(|row| &indices[row * width..(row + 1) * width])
+            .copied()
+            .collect::<Vec<_>>();
+        colors.sort_unstable();
+        colors.dedup();
+
+        for (color_idx, color) in colors.iter().enumerate() {
+            if color_idx > 0 {
+                // Back to the start of the band for the next color
+                sixel.push('$');
+            }
+            sixel.push_str(&format!("#{color}"));
+
+            let mut run: Option<(char, usize)> = None;
+            for column in 0..width {
+                let bits = rows
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, row)| indices[*row * width + column] == *color)
+                    .fold(0, |bits, (bit, _)| bits | 1 << bit);
+                let char = char::from(63 + bits as u8);
+                run = match run {
+                    Some((last, count)) if last == char => Some((last, count + 1)),
+                    Some((last, count)) => {
+                        push_run(&mut sixel, last, count);
+                        Some((char, 1))
+                    }
+                    None => Some((char, 1)),
+                };
+            }
+            // Trailing blank sixels draw nothing
+            if let Some((last, count)) = run.filter(|(last, _)| *last != '?') {
+                push_run(&mut sixel, last, count);
+            }
+        }
+        sixel.push('-');
+    }
+    sixel.push_str("\x1b\\");
+
+    out.write_all(sixel.as_bytes())?;
+    Ok(())
+}
+
+/// Appends a run of the same sixel, compressed with a repeat introducer when that's shorter
+fn push_run(sixel: &mut String, char: char, count: usize) {
+    if count > 3 {
+        sixel.push_str(&format!("!{count}{char}"));
+    } else {
+        sixel.extend(std::iter::repeat_n(char, count));
+    }
+}
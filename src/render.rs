@@ -0,0 +1,1349 @@
+use std::{
+    collections::BTreeMap,
+    env,
+    error::Error,
+    ffi::OsString,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use clap::{Args, ValueEnum};
+use pest_railroad::{
+    expand_footer, Config, DependencyGraph, EndStyle, Format, Grammar, InlineTrivial, LintLevel,
+    LookaheadStyle, PageSize, RasterSize, RuleLayout, TextFont, Theme, Warning,
+};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "png")]
+use crate::preview::{self, PreviewProtocol};
+use crate::{
+    check,
+    config_file::ProjectConfig,
+    diagnostics::Reporter,
+    exit::{self, Failure},
+    input,
+    jobs::JobsArgs,
+    open,
+    per_rule::{self, PerRuleOutput},
+    watch::Watcher,
+};
+
+#[derive(Args)]
+pub struct RenderArgs {
+    /// The pest grammar file to render (`-` reads it from stdin)
+    // Clap leaves the group of a struct with flattened fields empty, but the group is what tells the default command
+    // apart from a subcommand
+    #[arg(group = "RenderArgs")]
+    pub input: PathBuf,
+
+    /// Write the diagram to this file instead of stdout
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Write the diagram of each rule into this directory, or a single diagram named after the grammar file for
+    /// --combined and the formats that can't be written per rule (ignored if --output is given)
+    #[arg(long, value_name = "DIR")]
+    out_dir: Option<PathBuf>,
+
+    /// Write each rule's diagram to its own file in the output directory, along with an `index.html` showing them all
+    /// (the default with --out-dir for the svg, png, md and rustdoc formats, unless --combined is given)
+    #[arg(long, conflicts_with_all = ["output", "emit_metadata", "combined"], overrides_with = "no_per_rule")]
+    per_rule: bool,
+
+    /// Turn --per-rule off, overriding the config file and the default with --out-dir
+    #[arg(long, overrides_with = "per_rule")]
+    no_per_rule: bool,
+
+    /// Name the files written into --out-dir after this template (relative to it), where `{stem}` is the grammar file
+    /// name without its extension, `{rule}` the rule name (required with --per-rule) and `{ext}` the format's extension
+    /// [default: `{stem}.{ext}`, or `{rule}.{ext}` with --per-rule]
+    #[arg(long, value_name = "TEMPLATE")]
+    output_template: Option<String>,
+
+    /// The output format [default: svg]
+    #[arg(short, long, value_enum)]
+    format: Option<OutputFormat>,
+
+    #[command(flatten)]
+    pub diagram: DiagramArgs,
+
+    #[command(flatten)]
+    jobs: JobsArgs,
+
+    /// Fail without writing any output if warnings are produced, including those of the lints run by `check`
+    #[arg(long, overrides_with = "no_strict")]
+    strict: bool,
+
+    /// Turn --strict off, overriding the config file
+    #[arg(long, overrides_with = "strict")]
+    no_strict: bool,
+
+    /// Lint levels by lint code, from the project config file
+    #[arg(skip)]
+    lints: BTreeMap<String, LintLevel>,
+
+    /// Don't print warnings or progress messages (errors are still printed)
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Print each rendered rule and the time taken by each stage
+    #[arg(short, long)]
+    verbose: bool,
+
+    /// Also write a `.meta.json` file next to the output with the position and size of each rule in the diagram, and
+    /// any warnings (requires an output file or directory)
+    #[arg(long)]
+    emit_metadata: bool,
+
+    /// List the warnings in a block at the bottom of the diagram (SVG, HTML and PNG only)
+    #[arg(long, overrides_with = "no_embed_warnings")]
+    embed_warnings: bool,
+
+    /// Turn --embed-warnings off, overriding the config file
+    #[arg(long, overrides_with = "embed_warnings")]
+    no_embed_warnings: bool,
+
+    /// Open the output in the system's default viewer once it has been written (to a temporary file if no output
+    /// file or directory is given)
+    #[arg(long)]
+    open: bool,
+
+    /// Show the diagram inline in the terminal once it has been rendered, with the kitty graphics protocol or sixel
+    /// graphics (the diagram is only written if an output file or directory is given)
+    #[cfg(feature = "png")]
+    #[arg(long, value_enum, value_name = "PROTOCOL", num_args = 0..=1, default_missing_value = "auto")]
+    preview: Option<PreviewProtocol>,
+
+    /// Watch the grammar file and regenerate the output whenever it changes (requires an output file or directory)
+    #[arg(short, long)]
+    pub watch: bool,
+
+    /// How long (in milliseconds) the grammar must be unchanged before regenerating in watch mode
+    #[arg(long, default_value_t = 200, value_name = "MS")]
+    debounce: u64,
+
+    #[command(flatten)]
+    pub config: ConfigArgs,
+}
+
+/// Options controlling the content and appearance of diagrams, shared by every command that renders them
+#[derive(Args, Clone)]
+pub struct DiagramArgs {
+    /// The diagram stylesheet (`auto` follows the viewer's color scheme, `none` leaves styling to the embedding page)
+    /// [default: light]
+    #[arg(short, long, value_enum)]
+    theme: Option<ThemeName>,
+
+    /// Append the contents of this CSS file to the diagram's stylesheet (may be repeated)
+    #[arg(long, value_name = "FILE")]
+    css: Vec<PathBuf>,
+
+    /// The diagram title [default: the first grammar doc comment line, or the file name]
+    #[arg(long)]
+    title: Option<String>,
+
+    /// A line of small text under each diagram, in which {file}, {hash} (a checksum of the grammar), {date} (today, or
+    /// SOURCE_DATE_EPOCH) and {version} are filled in, as in "Generated from {file} on {date}"
+    #[arg(long, value_name = "TEMPLATE")]
+    footer: Option<String>,
+
+    /// Only render the rules matching these comma separated names (`*` and `?` wildcards are supported)
+    #[arg(short, long, value_delimiter = ',', value_name = "RULES")]
+    rules: Vec<String>,
+
+    /// Only render this rule and every rule reachable from it
+    #[arg(short, long, value_name = "RULE")]
+    start_rule: Option<String>,
+
+    /// Draw the rules matching these comma separated names in place of each reference to them, instead of as
+    /// separate diagrams (`*` and `?` wildcards are supported)
+    #[arg(long, value_delimiter = ',', value_name = "RULES")]
+    inline: Vec<String>,
+
+    /// Draw the rules whose body is a single terminal (such as `lparen = { "(" }`) in place of each reference to them
+    /// (`named` also labels each terminal with the rule's name)
+    #[arg(long, value_enum, num_args = 0..=1, default_missing_value = "terminal", value_name = "STYLE")]
+    inline_trivial: Option<InlineTrivialName>,
+
+    /// Draw the rules referenced by each rule in place of the references, this many levels deep, so each diagram is
+    /// self-contained (deeper references stay boxes, linked to their rule's diagram)
+    #[arg(long, value_name = "DEPTH")]
+    expand_depth: Option<usize>,
+
+    /// Factor complex sub-expressions that appear more than once in a rule out into rules of their own (such as
+    /// `expr_1`), drawn once after the rule and referenced where they appeared
+    #[arg(long, overrides_with = "no_factor_repeated")]
+    factor_repeated: bool,
+
+    /// Turn --factor-repeated off, overriding the config file
+    #[arg(long, overrides_with = "factor_repeated")]
+    no_factor_repeated: bool,
+
+    /// Draw the rules matching these comma separated names, and the references to them, with an accent color (`*` and
+    /// `?` wildcards are supported)
+    #[arg(long, value_delimiter = ',', value_name = "RULES")]
+    highlight: Vec<String>,
+
+    /// Leave out references to pest's built-in rules (such as `ASCII_DIGIT`), drawing a plain line in their place
+    #[arg(long, overrides_with = "no_hide_builtins")]
+    hide_builtins: bool,
+
+    /// Turn --hide-builtins off, overriding the config file
+    #[arg(long, overrides_with = "hide_builtins")]
+    no_hide_builtins: bool,
+
+    /// Leave out references to the rules whose names match this pattern (such as `ASCII_*`), and their diagrams
+    #[arg(long, value_name = "PATTERN")]
+    hide_pattern: Option<String>,
+
+    /// Link each rule reference to the URL made by replacing `{rule}` in this template with the rule name
+    #[arg(long, value_name = "URL", value_parser = parse_link_template)]
+    link_template: Option<String>,
+
+    /// Prefix each rule name with its number (its position in the grammar, even when only some rules are rendered)
+    #[arg(long, overrides_with = "no_numbering")]
+    numbering: bool,
+
+    /// Turn --numbering off, overriding the config file
+    #[arg(long, overrides_with = "numbering")]
+    no_numbering: bool,
+
+    /// Append the grammar file name and line of each rule (such as `grammar.pest:42`) to its name
+    #[arg(long, overrides_with = "no_line_refs")]
+    line_refs: bool,
+
+    /// Turn --line-refs off, overriding the config file
+    #[arg(long, overrides_with = "line_refs")]
+    no_line_refs: bool,
+
+    /// Note under each rule that allows whitespace and comments between its elements (because the grammar defines
+    /// WHITESPACE or COMMENT and the rule isn't atomic) that it does
+    #[arg(long, overrides_with = "no_implicit_notes")]
+    implicit_notes: bool,
+
+    /// Turn --implicit-notes off, overriding the config file
+    #[arg(long, overrides_with = "implicit_notes")]
+    no_implicit_notes: bool,
+
+    /// Prefix every id and class in the SVG with this string, so several diagrams can be inlined into one page
+    #[arg(long, value_name = "PREFIX", value_parser = parse_id_prefix)]
+    id_prefix: Option<String>,
+
+    /// Highlight the path under the cursor and dim the alternatives to it, when viewed in a browser
+    #[arg(long, overrides_with = "no_interactive")]
+    interactive: bool,
+
+    /// Turn --interactive off, overriding the config file
+    #[arg(long, overrides_with = "interactive")]
+    no_interactive: bool,
+
+    /// Color the boxes of each rule by its modifier (silent, atomic, compound atomic, non-atomic or normal)
+    #[arg(long, overrides_with = "no_modifier_colors")]
+    modifier_colors: bool,
+
+    /// Turn --modifier-colors off, overriding the config file
+    #[arg(long, overrides_with = "modifier_colors")]
+    no_modifier_colors: bool,
+
+    /// Add a legend explaining the notation after the last rule
+    #[arg(long, overrides_with = "no_legend")]
+    legend: bool,
+
+    /// Turn --legend off, overriding the config file
+    #[arg(long, overrides_with = "legend")]
+    no_legend: bool,
+
+    /// Draw pest's stack operations (PUSH, POP, PEEK, DROP, POP_ALL, PEEK_ALL and PEEK slices) with a stack glyph and a
+    /// color of their own
+    #[arg(long, overrides_with = "no_stack_ops")]
+    stack_ops: bool,
+
+    /// Turn --stack-ops off, overriding the config file
+    #[arg(long, overrides_with = "stack_ops")]
+    no_stack_ops: bool,
+
+    /// How to draw the frames around lookahead: under a label saying what it means, or as light dashed boxes with a
+    /// small `&` or `!` badge (showing the label as a tooltip) [default: labeled]
+    #[arg(long, value_enum, value_name = "STYLE")]
+    lookahead_style: Option<LookaheadStyleName>,
+
+    /// Draw every rule in a single diagram: one file in --out-dir instead of a file for each rule, and one SVG on HTML
+    /// pages instead of one for each rule
+    #[arg(long, overrides_with = "no_combined")]
+    pub combined: bool,
+
+    /// Turn --combined off, overriding the config file
+    #[arg(long, overrides_with = "combined")]
+    no_combined: bool,
+
+    /// Add a table of contents linking to each rule's diagram (HTML only)
+    #[arg(long, overrides_with = "no_toc")]
+    toc: bool,
+
+    /// Turn --toc off, overriding the config file
+    #[arg(long, overrides_with = "toc")]
+    no_toc: bool,
+
+    /// Show the source of each rule (syntax highlighted and collapsible) below its diagram (HTML only)
+    #[arg(long, overrides_with = "no_include_source")]
+    include_source: bool,
+
+    /// Turn --include-source off, overriding the config file
+    #[arg(long, overrides_with = "include_source")]
+    no_include_source: bool,
+
+    /// Add a filter box for finding rules by name or doc comment to the table of contents, which it turns on (HTML only)
+    #[arg(long, overrides_with = "no_search")]
+    search: bool,
+
+    /// Turn --search off, overriding the config file
+    #[arg(long, overrides_with = "search")]
+    no_search: bool,
+
+    /// Let readers click a rule reference to expand the rule's diagram under it (HTML only)
+    #[arg(long, overrides_with = "no_expandable")]
+    expandable: bool,
+
+    /// Turn --expandable off, overriding the config file
+    #[arg(long, overrides_with = "expandable")]
+    no_expandable: bool,
+
+    /// Mark each rule, terminal and rule reference in the diagram with the byte range of its source in the grammar file,
+    /// for editor integrations
+    #[arg(long, overrides_with = "no_source_map")]
+    source_map: bool,
+
+    /// Turn --source-map off, overriding the config file
+    #[arg(long, overrides_with = "source_map")]
+    no_source_map: bool,
+
+    /// Put the diagram of each rule on a page of its own under a header with the rule's name (PDF only)
+    #[arg(long, overrides_with = "no_paginate")]
+    paginate: bool,
+
+    /// Turn --paginate off, overriding the config file
+    #[arg(long, overrides_with = "paginate")]
+    no_paginate: bool,
+
+    /// The paper size of the pages of --paginate, which each diagram is scaled down to fit (`fit` sizes each page to
+    /// fit its diagram instead) [default: fit]
+    #[arg(long, value_enum)]
+    page_size: Option<PageSizeName>,
+
+    /// Draw text diagrams with plain ASCII characters instead of box-drawing ones (txt only)
+    #[arg(long, overrides_with = "no_ascii")]
+    ascii: bool,
+
+    /// Turn --ascii off, overriding the config file
+    #[arg(long, overrides_with = "ascii")]
+    no_ascii: bool,
+
+    /// The font family of the diagram text [default: monospace]
+    #[arg(long, value_name = "FAMILY")]
+    font: Option<String>,
+
+    /// Embed the font (--font, or else the system's monospace font) in the diagram, so it looks the same on machines
+    /// without it installed
+    #[arg(long, overrides_with = "no_embed_fonts")]
+    embed_fonts: bool,
+
+    /// Turn --embed-fonts off, overriding the config file
+    #[arg(long, overrides_with = "embed_fonts")]
+    no_embed_fonts: bool,
+
+    /// The font of the terminals' text, as comma separated `family=NAME`, `size=PX` and `weight=1..1000` settings
+    /// (such as `family=Fira Code,size=13`)
+    #[arg(long, value_name = "FONT", value_parser = parse_text_font)]
+    terminal_font: Option<TextFontArg>,
+
+    /// The font of the text of rule references, in the same form as --terminal-font
+    #[arg(long, value_name = "FONT", value_parser = parse_text_font)]
+    nonterminal_font: Option<TextFontArg>,
+
+    /// The font of rule names, doc comments and labels, in the same form as --terminal-font
+    #[arg(long, value_name = "FONT", value_parser = parse_text_font)]
+    comment_font: Option<TextFontArg>,
+
+    /// Wrap sequences wider than this many pixels onto stacked rows, so long rules don't make extremely wide diagrams
+    #[arg(long, value_name = "PX", value_parser = clap::value_parser!(i64).range(1..))]
+    max_width: Option<i64>,
+
+    /// Cut terminals longer than this many characters short with an ellipsis, showing the whole terminal as a tooltip
+    #[arg(long, value_name = "CHARS", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    max_label_length: Option<usize>,
+
+    /// Lay choices of more than this many alternatives out as columns side by side, so long lists of keywords don't
+    /// make extremely tall diagrams
+    #[arg(long, value_name = "COUNT", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    max_choice_alternatives: Option<usize>,
+
+    /// Draw the diagrams right to left, for documentation in right-to-left languages (--max-width doesn't apply)
+    #[arg(long, overrides_with = "no_rtl")]
+    rtl: bool,
+
+    /// Turn --rtl off, overriding the config file
+    #[arg(long, overrides_with = "rtl")]
+    no_rtl: bool,
+
+    /// How to draw the start and end of each rule: small circles, double bars, or nothing (for fragments embedded in
+    /// other diagrams) [default: simple]
+    #[arg(long, value_enum, value_name = "STYLE")]
+    end_style: Option<EndStyleName>,
+
+    /// Write "end of input" under the end marker of rules ending with EOI, instead of drawing a box for EOI
+    #[arg(long, overrides_with = "no_merge_eoi")]
+    merge_eoi: bool,
+
+    /// Turn --merge-eoi off, overriding the config file
+    #[arg(long, overrides_with = "merge_eoi")]
+    no_merge_eoi: bool,
+
+    /// The space around the diagram, in pixels [default: 10]
+    #[arg(long, value_name = "PX", value_parser = clap::value_parser!(i64).range(0..))]
+    margin: Option<i64>,
+
+    /// The vertical space between rules, in pixels [default: 12]
+    #[arg(long, value_name = "PX", value_parser = clap::value_parser!(i64).range(0..))]
+    rule_spacing: Option<i64>,
+
+    /// Scale raster (PNG) output by this factor (e.g. 2 for high density displays) [default: 1]
+    #[arg(long, value_name = "FACTOR", value_parser = parse_positive, conflicts_with_all = ["dpi", "width"])]
+    scale: Option<f64>,
+
+    /// Rasterize (PNG) output at this pixel density, where 96 is the diagram's natural size
+    #[arg(long, value_parser = parse_positive, conflicts_with = "width")]
+    dpi: Option<f64>,
+
+    /// Scale raster (PNG) output to this width in pixels, keeping its aspect ratio
+    #[arg(long, value_name = "PX", value_parser = clap::value_parser!(u32).range(1..))]
+    width: Option<u32>,
+
+    /// Scale raster (PNG) output down, if needed, so it has at most this many pixels
+    #[arg(long, value_name = "PIXELS", value_parser = clap::value_parser!(u64).range(1..))]
+    max_pixels: Option<u64>,
+}
+
+/// The pixel density at which a diagram is rasterized at its natural size
+const NATURAL_DPI: f64 = 96.0;
+
+fn parse_link_template(value: &str) -> Result<String, String> {
+    if value.contains("{rule}") {
+        Ok(value.to_string())
+    } else {
+        Err("must contain `{rule}`".into())
+    }
+}
+
+/// Checks that a prefix keeps ids and classes valid CSS identifiers
+pub fn parse_id_prefix(value: &str) -> Result<String, String> {
+    let valid_chars = value
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if valid_chars && value.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        Ok(value.to_string())
+    } else {
+        Err("must start with a letter or `_` and contain only letters, digits, `_` and `-`".into())
+    }
+}
+
+/// The font of one kind of diagram text, from the command line or config file
+#[derive(Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TextFontArg {
+    family: Option<String>,
+    size: Option<f64>,
+    weight: Option<u16>,
+}
+
+impl TextFontArg {
+    /// Checks that the size is positive and the weight in CSS's range
+    pub fn validate(&self) -> Result<(), String> {
+        if self
+            .size
+            .is_some_and(|size| size <= 0.0 || !size.is_finite())
+        {
+            return Err("size must be a positive number".into());
+        }
+        if self
+            .weight
+            .is_some_and(|weight| !(1..=1000).contains(&weight))
+        {
+            return Err("weight must be between 1 and 1000".into());
+        }
+        Ok(())
+    }
+}
+
+impl From<TextFontArg> for TextFont {
+    fn from(font: TextFontArg) -> Self {
+        TextFont {
+            family: font.family,
+            size: font.size,
+            weight: font.weight,
+        }
+    }
+}
+
+/// Parses comma separated `family=NAME`, `size=PX` and `weight=N` font settings
+fn parse_text_font(value: &str) -> Result<TextFontArg, String> {
+    let mut font = TextFontArg::default();
+    for setting in value.split(',') {
+        let (key, value) = setting
+            .split_once('=')
+            .ok_or_else(|| format!("expected `key=value`, found `{setting}`"))?;
+        let value = value.trim();
+        match key.trim() {
+            "family" => font.family = Some(value.to_string()),
+            "size" => {
+                font.size = Some(value.parse().map_err(|_| "size must be a number")?);
+            }
+            "weight" => {
+                font.weight = Some(value.parse().map_err(|_| "weight must be a number")?);
+            }
+            key => {
+                return Err(format!(
+                    "unknown font setting `{key}` (expected family, size or weight)"
+                ))
+            }
+        }
+    }
+    font.validate()?;
+    Ok(font)
+}
+
+fn parse_positive(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(value) if value > 0.0 && value.is_finite() => Ok(value),
+        _ => Err("must be a positive number".into()),
+    }
+}
+
+/// Selection of the project config file
+#[derive(Args)]
+pub struct ConfigArgs {
+    /// Use this config file instead of searching for a `pest-railroad.toml` next to (or above) the grammar file
+    #[arg(long, value_name = "FILE", conflicts_with = "no_config")]
+    config: Option<PathBuf>,
+
+    /// Don't load any config file
+    #[arg(long)]
+    no_config: bool,
+}
+
+impl ConfigArgs {
+    /// Loads the project config file for the grammar (if there is one)
+    pub fn load(&self, input: &Path) -> Result<Option<ProjectConfig>, Box<dyn Error>> {
+        let path = match &self.config {
+            Some(path) => Some(path.clone()),
+            None if self.no_config => None,
+            None => ProjectConfig::discover(input),
+        };
+
+        path.map(|path| ProjectConfig::load(&path)).transpose()
+    }
+}
+
+impl DiagramArgs {
+    /// Fills in any settings not given on the command line from the project config file
+    pub fn apply_config(&mut self, config: &ProjectConfig) {
+        self.theme = self.theme.or(config.theme);
+        if self.css.is_empty() {
+            self.css = config.css.clone();
+        }
+        self.footer = self.footer.take().or(config.footer.clone());
+        if self.rules.is_empty() {
+            self.rules = config.rules.clone();
+        }
+        self.start_rule = self.start_rule.take().or(config.start_rule.clone());
+        if self.inline.is_empty() {
+            self.inline = config.inline.clone();
+        }
+        if self.highlight.is_empty() {
+            self.highlight = config.highlight.clone();
+        }
+        self.hide_builtins |= config.hide_builtins && !self.no_hide_builtins;
+        self.hide_pattern = self.hide_pattern.take().or(config.hide_pattern.clone());
+        self.link_template = self.link_template.take().or(config.link_template.clone());
+        self.numbering |= config.numbering && !self.no_numbering;
+        self.line_refs |= config.line_refs && !self.no_line_refs;
+        self.implicit_notes |= config.implicit_notes && !self.no_implicit_notes;
+        self.id_prefix = self.id_prefix.take().or(config.id_prefix.clone());
+        self.interactive |= config.interactive && !self.no_interactive;
+        self.modifier_colors |= config.modifier_colors && !self.no_modifier_colors;
+        self.legend |= config.legend && !self.no_legend;
+        self.stack_ops |= config.stack_ops && !self.no_stack_ops;
+        self.lookahead_style = self.lookahead_style.or(config.lookahead_style);
+        self.combined |= config.combined && !self.no_combined;
+        self.toc |= config.toc && !self.no_toc;
+        self.include_source |= config.include_source && !self.no_include_source;
+        self.search |= config.search && !self.no_search;
+        self.expandable |= config.expandable && !self.no_expandable;
+        self.source_map |= config.source_map && !self.no_source_map;
+        self.paginate |= config.paginate && !self.no_paginate;
+        self.page_size = self.page_size.or(config.page_size);
+        self.inline_trivial = self.inline_trivial.or(config.inline_trivial);
+        self.expand_depth = self.expand_depth.or(config.expand_depth);
+        self.factor_repeated |= config.factor_repeated && !self.no_factor_repeated;
+        self.ascii |= config.ascii && !self.no_ascii;
+        self.font = self.font.take().or(config.font.clone());
+        self.embed_fonts |= config.embed_fonts && !self.no_embed_fonts;
+        self.terminal_font = self.terminal_font.take().or(config.terminal_font.clone());
+        self.nonterminal_font = self
+            .nonterminal_font
+            .take()
+            .or(config.nonterminal_font.clone());
+        self.comment_font = self.comment_font.take().or(config.comment_font.clone());
+        if self.scale.is_none() && self.dpi.is_none() && self.width.is_none() {
+            (self.scale, self.dpi, self.width) = (config.scale, config.dpi, config.width);
+        }
+        self.max_pixels = self.max_pixels.or(config.max_pixels);
+        self.max_width = self.max_width.or(config.max_width);
+        self.max_label_length = self.max_label_length.or(config.max_label_length);
+        self.max_choice_alternatives = self
+            .max_choice_alternatives
+            .or(config.max_choice_alternatives);
+        self.rtl |= config.rtl && !self.no_rtl;
+        self.end_style = self.end_style.or(config.end_style);
+        self.merge_eoi |= config.merge_eoi && !self.no_merge_eoi;
+        self.margin = self.margin.or(config.margin);
+        self.rule_spacing = self.rule_spacing.or(config.rule_spacing);
+    }
+
+    /// Builds the library config for rendering `grammar` (read from `input`)
+    pub fn config(&self, grammar: &Grammar, input: &Path) -> Result<Config, Box<dyn Error>> {
+        if let Some(start_rule) = &self.start_rule {
+            if !DependencyGraph::new(grammar).contains(start_rule) {
+                return Err(format!("Start rule '{start_rule}' not found in grammar").into());
+            }
+        }
+        let file_name = (!crate::input::is_stdin(input))
+            .then(|| input.file_name())
+            .flatten()
+            .map(|name| name.to_string_lossy().into_owned());
+
+        Ok(Config {
+            include: self.rules.clone(),
+            start_rule: self.start_rule.clone(),
+            theme: self.theme.map(Theme::from).unwrap_or_default(),
+            css: self
+                .css
+                .iter()
+                .map(|path| {
+                    fs::read_to_string(path)
+                        .map_err(|err| format!("Unable to read CSS file {}: {err}", path.display()))
+                })
+                .collect::<Result<_, _>>()?,
+            title: self.title.clone().or_else(|| {
+                // The library falls back to the grammar docs itself
+                file_name.clone().filter(|_| grammar.docs.is_empty())
+            }),
+            name: (!crate::input::is_stdin(input))
+                .then(|| input.file_stem())
+                .flatten()
+                .map(|stem| stem.to_string_lossy().into_owned()),
+            highlight: self.highlight.clone(),
+            warnings: Vec::new(),
+            footer: self
+                .footer
+                .as_deref()
+                .map(|footer| expand_footer(footer, grammar, file_name.as_deref())),
+            inline: self.inline.clone(),
+            inline_trivial: self.inline_trivial.map(Into::into).unwrap_or_default(),
+            expand_depth: self.expand_depth,
+            factor_repeated: self.factor_repeated,
+            hide_builtins: self.hide_builtins,
+            hide_pattern: self.hide_pattern.clone(),
+            link_template: self.link_template.clone(),
+            numbering: self.numbering,
+            line_refs: self.line_refs,
+            implicit_notes: self.implicit_notes,
+            file_name,
+            id_prefix: self.id_prefix.clone(),
+            interactive: self.interactive,
+            modifier_colors: self.modifier_colors,
+            legend: self.legend,
+            stack_ops: self.stack_ops,
+            // Deep links to the rules of generated documentation always work
+            anchors: true,
+            lookahead_style: self.lookahead_style.map(Into::into).unwrap_or_default(),
+            combined: self.combined,
+            toc: self.toc,
+            include_source: self.include_source,
+            search: self.search,
+            expandable: self.expandable,
+            source_map: self.source_map,
+            paginate: self.paginate,
+            page_size: self.page_size.and_then(Into::into),
+            ascii: self.ascii,
+            image_template: None,
+            font: self.font.clone(),
+            embed_fonts: self.embed_fonts,
+            terminal_font: self
+                .terminal_font
+                .clone()
+                .map(Into::into)
+                .unwrap_or_default(),
+            nonterminal_font: self
+                .nonterminal_font
+                .clone()
+                .map(Into::into)
+                .unwrap_or_default(),
+            comment_font: self
+                .comment_font
+                .clone()
+                .map(Into::into)
+                .unwrap_or_default(),
+            raster_size: match (self.scale, self.dpi, self.width) {
+                (_, _, Some(width)) => RasterSize::Width(width),
+                (_, Some(dpi), _) => RasterSize::Scale(dpi / NATURAL_DPI),
+                (scale, ..) => RasterSize::Scale(scale.unwrap_or(1.0)),
+            },
+            max_pixels: self.max_pixels,
+            max_width: self.max_width,
+            max_label_length: self.max_label_length,
+            max_choice_alternatives: self.max_choice_alternatives,
+            rtl: self.rtl,
+            end_style: self.end_style.map(Into::into).unwrap_or_default(),
+            merge_eoi: self.merge_eoi,
+            margin: self.margin,
+            rule_spacing: self.rule_spacing,
+            ..Config::default()
+        })
+    }
+}
+
+impl RenderArgs {
+    /// Fills in any settings not given on the command line from the project config file (if any)
+    pub fn apply_config(&mut self) -> Result<(), Box<dyn Error>> {
+        if let Some(config) = self.config.load(&self.input)? {
+            self.format = self.format.or(config.format);
+            self.diagram.apply_config(&config);
+            self.out_dir = self.out_dir.take().or(config.output_dir);
+            self.output_template = self.output_template.take().or(config.output_template);
+            self.strict |= config.strict && !self.no_strict;
+            self.lints = config.lints;
+            self.embed_warnings |= config.embed_warnings && !self.no_embed_warnings;
+            // Asking for a single output file on the command line overrides per-rule output
+            self.per_rule |= config.per_rule
+                && !self.no_per_rule
+                && self.output.is_none()
+                && !self.emit_metadata;
+        }
+
+        // An output directory gets a file for each rule, unless a single file is asked for (or can only be written)
+        self.per_rule |= self.out_dir.is_some()
+            && self.output.is_none()
+            && !self.emit_metadata
+            && !self.no_per_rule
+            && !self.diagram.combined
+            && per_rule::supports(self.format())
+            && self
+                .output_template
+                .as_deref()
+                .is_none_or(|template| template.contains("{rule}"));
+        Ok(())
+    }
+
+    /// Whether the diagram is shown in the terminal
+    fn previews(&self) -> bool {
+        #[cfg(feature = "png")]
+        return self.preview.is_some();
+        #[cfg(not(feature = "png"))]
+        return false;
+    }
+
+    fn format(&self) -> Format {
+        self.format.unwrap_or(OutputFormat::Svg).into()
+    }
+
+    /// The path (relative to the output directory) of the file for `rule` (if writing per-rule output), as named by
+    /// the output template
+    fn output_file(&self, rule: Option<&str>) -> PathBuf {
+        let default = match rule {
+            Some(_) => "{rule}.{ext}",
+            None => "{stem}.{ext}",
+        };
+        let format = match rule {
+            Some(_) => per_rule::image_format(self.format()),
+            None => self.format(),
+        };
+        let stem = self.input.file_stem().unwrap_or(self.input.as_os_str());
+        let path = self
+            .output_template
+            .as_deref()
+            .unwrap_or(default)
+            .replace("{stem}", &stem.to_string_lossy())
+            .replace("{ext}", format.backend().extension());
+        PathBuf::from(match rule {
+            Some(rule) => path.replace("{rule}", rule),
+            None => path,
+        })
+    }
+
+    /// The file to write the output to (or `None` for stdout)
+    fn output_path(&self) -> Option<PathBuf> {
+        let file = self.output_file(None);
+
+        if self.per_rule {
+            return self
+                .out_dir
+                .as_ref()
+                .map(|dir| dir.join(per_rule::index_file_name(self.format())));
+        }
+
+        match (&self.output, &self.out_dir) {
+            (Some(output), _) => Some(output.clone()),
+            (None, Some(dir)) => Some(dir.join(file)),
+            // A viewer needs a file to open
+            (None, None) if self.open => {
+                let mut name = OsString::from("pest_railroad-");
+                name.push(file);
+                Some(env::temp_dir().join(name))
+            }
+            (None, None) => None,
+        }
+    }
+}
+
+/// The contents of the `--emit-metadata` file
+#[derive(Serialize)]
+struct Metadata<'a> {
+    /// The size (in pixels) of the whole diagram
+    width: i64,
+    height: i64,
+    rules: Vec<RuleLayout>,
+    warnings: &'a [Warning],
+}
+
+#[derive(Clone, Copy, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputFormat {
+    /// A single SVG diagram of every rule
+    Svg,
+    /// A single SVG with a `<symbol>` for each rule, for referencing with `<use>`
+    Sprite,
+    /// A standalone HTML page embedding the diagram
+    Html,
+    /// A Markdown document with a heading, the doc comments and the diagram of each rule
+    Md,
+    /// An HTML fragment styled to match the rustdoc theme, for `#[doc = include_str!(...)]`
+    Rustdoc,
+    /// A PNG image of the diagram
+    #[cfg(feature = "png")]
+    Png,
+    /// A small PNG overview of the start rule (or the first rule), for README headers
+    #[cfg(feature = "png")]
+    Thumbnail,
+    /// A vector PDF of the diagram
+    #[cfg(feature = "pdf")]
+    Pdf,
+    /// An EPS figure of the diagram, for print pipelines
+    #[cfg(feature = "eps")]
+    Eps,
+    /// An `<img>` tag of each rule's diagram with the SVG inlined, for pages that block external images
+    DataUri,
+    /// A Markdown image of each rule's diagram with the SVG inlined, for issue trackers that block external images
+    DataUriMd,
+    /// A text diagram of each rule, for terminals and plain-text docs
+    Txt,
+    /// A Mermaid flowchart of each rule's structure, for wikis that render Mermaid
+    Mermaid,
+    /// A Graphviz graph of each rule's structure, for laying out with existing Graphviz tooling
+    Dot,
+    /// LaTeX TikZ pictures of each rule's diagram, for papers
+    Tikz,
+    /// JavaScript for the railroad-diagrams library, for drawing diagrams client-side
+    RailroadJs,
+    /// A Typst document of each rule's diagram, for including in Typst documents
+    Typst,
+    /// The grammar as ISO EBNF text, for specifications
+    Ebnf,
+    /// An approximate ANTLR4 grammar skeleton, for porting
+    Antlr,
+    /// A static site of the diagrams (a page, each rule's SVG diagram, a stylesheet and metadata), as a zip archive or
+    /// (if the output file doesn't end in `.zip`) a directory
+    Bundle,
+    /// The parsed grammar structure as JSON
+    Json,
+    /// The parsed grammar structure as XML, for XSLT and other XML tooling
+    Xml,
+    /// The laid-out diagram as JSON, for custom renderers and interactive viewers
+    JsonDiagram,
+}
+
+#[derive(Clone, Copy, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeName {
+    Light,
+    Dark,
+    Rust,
+    Coal,
+    Navy,
+    Ayu,
+    Monochrome,
+    #[serde(rename = "high-contrast")]
+    HighContrast,
+    Auto,
+    None,
+}
+
+#[derive(Clone, Copy, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PageSizeName {
+    Fit,
+    A4,
+    Letter,
+}
+
+impl From<PageSizeName> for Option<PageSize> {
+    fn from(size: PageSizeName) -> Self {
+        match size {
+            PageSizeName::Fit => None,
+            PageSizeName::A4 => Some(PageSize::A4),
+            PageSizeName::Letter => Some(PageSize::Letter),
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InlineTrivialName {
+    Off,
+    Terminal,
+    Named,
+}
+
+impl From<InlineTrivialName> for InlineTrivial {
+    fn from(style: InlineTrivialName) -> Self {
+        match style {
+            InlineTrivialName::Off => InlineTrivial::Off,
+            InlineTrivialName::Terminal => InlineTrivial::Terminal,
+            InlineTrivialName::Named => InlineTrivial::Named,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LookaheadStyleName {
+    Labeled,
+    Badge,
+}
+
+impl From<LookaheadStyleName> for LookaheadStyle {
+    fn from(style: LookaheadStyleName) -> Self {
+        match style {
+            LookaheadStyleName::Labeled => LookaheadStyle::Labeled,
+            LookaheadStyleName::Badge => LookaheadStyle::Badge,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EndStyleName {
+    Simple,
+    Full,
+    None,
+}
+
+impl From<EndStyleName> for EndStyle {
+    fn from(style: EndStyleName) -> Self {
+        match style {
+            EndStyleName::Simple => EndStyle::Simple,
+            EndStyleName::Full => EndStyle::Full,
+            EndStyleName::None => EndStyle::None,
+        }
+    }
+}
+
+impl From<ThemeName> for Theme {
+    fn from(theme: ThemeName) -> Self {
+        match theme {
+            ThemeName::Light => Theme::Light,
+            ThemeName::Dark => Theme::Dark,
+            ThemeName::Rust => Theme::Rust,
+            ThemeName::Coal => Theme::Coal,
+            ThemeName::Navy => Theme::Navy,
+            ThemeName::Ayu => Theme::Ayu,
+            ThemeName::Monochrome => Theme::Monochrome,
+            ThemeName::HighContrast => Theme::HighContrast,
+            ThemeName::Auto => Theme::Auto,
+            ThemeName::None => Theme::None,
+        }
+    }
+}
+
+impl From<OutputFormat> for Format {
+    fn from(format: OutputFormat) -> Self {
+        match format {
+            OutputFormat::Svg => Format::Svg,
+            OutputFormat::Sprite => Format::Sprite,
+            OutputFormat::Html => Format::Html,
+            OutputFormat::Md => Format::Markdown,
+            OutputFormat::Rustdoc => Format::Rustdoc,
+            #[cfg(feature = "png")]
+            OutputFormat::Png => Format::Png,
+            #[cfg(feature = "png")]
+            OutputFormat::Thumbnail => Format::Thumbnail,
+            #[cfg(feature = "pdf")]
+            OutputFormat::Pdf => Format::Pdf,
+            #[cfg(feature = "eps")]
+            OutputFormat::Eps => Format::Eps,
+            OutputFormat::DataUri => Format::DataUri,
+            OutputFormat::DataUriMd => Format::DataUriMarkdown,
+            OutputFormat::Txt => Format::Text,
+            OutputFormat::Mermaid => Format::Mermaid,
+            OutputFormat::Dot => Format::Dot,
+            OutputFormat::Tikz => Format::Tikz,
+            OutputFormat::RailroadJs => Format::RailroadJs,
+            OutputFormat::Typst => Format::Typst,
+            OutputFormat::Ebnf => Format::Ebnf,
+            OutputFormat::Antlr => Format::Antlr,
+            OutputFormat::Bundle => Format::Bundle,
+            OutputFormat::Json => Format::Json,
+            OutputFormat::Xml => Format::Xml,
+            OutputFormat::JsonDiagram => Format::JsonDiagram,
+        }
+    }
+}
+
+pub fn render(args: &RenderArgs, reporter: &Reporter) -> Result<(), Box<dyn Error>> {
+    if input::is_stdin(&args.input) && args.output.is_none() && args.out_dir.is_some() {
+        return Err(
+            "--out-dir needs a grammar file to name the output after (use --output with stdin)"
+                .into(),
+        );
+    }
+    if args.per_rule && args.out_dir.is_none() {
+        return Err("--per-rule requires --out-dir".into());
+    }
+    if let Some(template) = &args.output_template {
+        match (args.per_rule, template.contains("{rule}")) {
+            (true, false) => {
+                return Err(
+                    "--output-template needs `{rule}` to name the files of --per-rule".into(),
+                )
+            }
+            (false, true) => return Err("`{rule}` in --output-template requires --per-rule".into()),
+            _ => {}
+        }
+    }
+    let start = Instant::now();
+    let src = input::read(&args.input)?;
+    let (grammar, mut warnings) = Grammar::parse(&src)?;
+
+    // Strict mode holds the grammar to the same lints as `check`, at the levels of the project config file
+    if args.strict {
+        warnings.extend(pest_railroad::lint(&grammar));
+        warnings.sort_by_key(|warning| (warning.line, warning.column));
+    }
+    let levels = check::levels(args.lints.iter().map(|(code, &level)| (code, level)))?;
+    warnings.retain(|warning| levels[warning.code] != LintLevel::Allow);
+
+    if args.verbose {
+        eprintln!(
+            "Parsed {} rule(s) from {} in {:.2?}",
+            grammar.rules.len(),
+            args.input.display(),
+            start.elapsed()
+        );
+    }
+
+    if !args.quiet {
+        for warning in &warnings {
+            reporter.warning(&args.input, warning);
+        }
+    }
+
+    if args.strict && !warnings.is_empty() {
+        return Err(Failure {
+            message: format!("{} warning(s) produced in strict mode", warnings.len()),
+            code: exit::STRICT,
+        }
+        .into());
+    }
+
+    let mut config = args.diagram.config(&grammar, &args.input)?;
+    if args.embed_warnings {
+        config.warnings = warnings.clone();
+    }
+    let format = args.format();
+
+    if args.verbose {
+        for rule in config.select(&grammar).rules {
+            match rule.line {
+                Some(line) => eprintln!("Rendering rule {} (line {line})", rule.name),
+                None => eprintln!("Rendering rule {} (factored)", rule.name),
+            }
+        }
+    }
+
+    let output = args.output_path();
+    if args.emit_metadata && output.is_none() {
+        return Err("--emit-metadata requires --output or --out-dir".into());
+    }
+
+    let start = Instant::now();
+    match &output {
+        Some(_) if args.per_rule => {
+            // Panic safety: Checked above
+            let dir = args.out_dir.as_ref().expect("output directory");
+            let reporter = (!args.quiet).then_some(reporter);
+            let output = PerRuleOutput {
+                format,
+                dir,
+                file_name: &|rule| args.output_file(Some(rule)),
+                jobs: args.jobs,
+            };
+            per_rule::render_per_rule(&grammar, &config, &output, reporter, args.verbose)?;
+        }
+        Some(output)
+            if format == Format::Bundle && output.extension().is_none_or(|ext| ext != "zip") =>
+        {
+            for file in pest_railroad::bundle(&grammar, &config)? {
+                let path = output.join(&file.path);
+                if let Some(dir) = path.parent() {
+                    fs::create_dir_all(dir)?;
+                }
+                fs::write(path, file.contents)?;
+            }
+        }
+        Some(output) => {
+            if let Some(dir) = output.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            let mut file = BufWriter::new(File::create(output)?);
+            pest_railroad::render(&grammar, &config, format, &mut file)?;
+            file.flush()?;
+        }
+        None if args.previews() => {}
+        None => pest_railroad::render(&grammar, &config, format, &mut io::stdout().lock())?,
+    }
+
+    #[cfg(feature = "png")]
+    if let Some(protocol) = args.preview {
+        let mut png = Vec::new();
+        pest_railroad::render(&grammar, &config, Format::Png, &mut png)?;
+        preview::show(&png, protocol)?;
+    }
+
+    if let Some(output) = output.filter(|_| args.emit_metadata) {
+        let (width, height) = pest_railroad::diagram_size(&grammar, &config);
+        let metadata = Metadata {
+            width,
+            height,
+            rules: pest_railroad::rule_layout(&grammar, &config),
+            warnings: &warnings,
+        };
+        let file = BufWriter::new(File::create(output.with_extension("meta.json"))?);
+        serde_json::to_writer_pretty(file, &metadata)?;
+    }
+
+    if args.verbose {
+        eprintln!("Rendered output in {:.2?}", start.elapsed());
+    }
+    Ok(())
+}
+
+pub fn watch(args: &RenderArgs, reporter: &Reporter) -> Result<(), Box<dyn Error>> {
+    if input::is_stdin(&args.input) {
+        return Err("Watch mode requires a grammar file (not stdin)".into());
+    }
+    if args.output_path().is_none() {
+        return Err("Watch mode requires --output or --out-dir".into());
+    }
+
+    let input = args.input.clone();
+    let mut watcher = Watcher::new([input.clone()], Duration::from_millis(args.debounce));
+
+    if !args.quiet {
+        eprintln!("Watching {} for changes...", input.display());
+    }
+
+    let mut opened = false;
+    loop {
+        // Errors shouldn't end the session - the grammar is likely just mid-edit
+        match render(args, reporter) {
+            Ok(()) => {
+                if !args.quiet {
+                    eprintln!("Regenerated diagram for {}", input.display());
+                }
+                // The viewer is only opened once - the user can refresh it after that
+                if args.open && !opened {
+                    opened = true;
+                    open_output(args, reporter);
+                }
+            }
+            Err(err) => reporter.error(Some(&input), &*err),
+        }
+
+        watcher.wait_for_change();
+    }
+}
+
+/// Renders once, opening the output afterwards if requested
+pub fn render_once(args: &RenderArgs, reporter: &Reporter) -> Result<(), Box<dyn Error>> {
+    render(args, reporter)?;
+    if args.open {
+        open_output(args, reporter);
+    }
+    Ok(())
+}
+
+/// Opens the rendered output in the system viewer. Failing to do so isn't fatal as the output was still written.
+fn open_output(args: &RenderArgs, reporter: &Reporter) {
+    if let Some(path) = args.output_path() {
+        if let Err(err) = open::open_in_viewer(&path) {
+            reporter.error(Some(&args.input), &err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::*;
+    use crate::{config_file::CONFIG_FILE_NAME, Cli};
+
+    /// The render arguments of a command line, with a config file holding `toml` (written to a temporary file named
+    /// after the test) applied
+    fn configured(test: &str, args: &[&str], toml: &str) -> RenderArgs {
+        let dir = env::temp_dir().join("pest_railroad_tests").join(test);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(CONFIG_FILE_NAME);
+        fs::write(&path, toml).unwrap();
+
+        let path = path.to_str().unwrap();
+        let cli = Cli::try_parse_from(
+            ["pest_railroad_gen", "grammar.pest", "--config", path]
+                .iter()
+                .chain(args),
+        )
+        .unwrap();
+        let mut args = cli.render.unwrap();
+        args.apply_config().unwrap();
+        args
+    }
+
+    const CONFIG: &str = r#"
+        format = "html"
+        rules = ["value"]
+        start-rule = "object"
+        output-dir = "docs"
+        strict = true
+    "#;
+
+    #[test]
+    fn config_file_values_apply() {
+        let args = configured("config_file_values_apply", &[], CONFIG);
+        assert!(matches!(args.format, Some(OutputFormat::Html)));
+        assert_eq!(args.diagram.rules, ["value"]);
+        assert_eq!(args.diagram.start_rule.as_deref(), Some("object"));
+        assert!(args
+            .out_dir
+            .unwrap()
+            .ends_with("config_file_values_apply/docs"));
+        assert!(args.strict);
+    }
+
+    #[test]
+    fn flags_override_config_file() {
+        let args = configured(
+            "flags_override_config_file",
+            &[
+                "-f",
+                "svg",
+                "-r",
+                "array",
+                "-s",
+                "array",
+                "--out-dir",
+                "out",
+            ],
+            CONFIG,
+        );
+        assert!(matches!(args.format, Some(OutputFormat::Svg)));
+        assert_eq!(args.diagram.rules, ["array"]);
+        assert_eq!(args.diagram.start_rule.as_deref(), Some("array"));
+        assert_eq!(args.out_dir, Some(PathBuf::from("out")));
+    }
+
+    /// A boolean setting of the config file (named like its flag), and how to read it from the arguments
+    type BooleanSetting = (&'static str, fn(&RenderArgs) -> bool);
+
+    const BOOLEAN_SETTINGS: &[BooleanSetting] = &[
+        ("strict", |args| args.strict),
+        ("toc", |args| args.diagram.toc),
+        ("embed-fonts", |args| args.diagram.embed_fonts),
+        ("hide-builtins", |args| args.diagram.hide_builtins),
+        ("numbering", |args| args.diagram.numbering),
+        ("per-rule", |args| args.per_rule),
+        ("embed-warnings", |args| args.embed_warnings),
+        ("include-source", |args| args.diagram.include_source),
+        ("paginate", |args| args.diagram.paginate),
+        ("search", |args| args.diagram.search),
+        ("ascii", |args| args.diagram.ascii),
+        ("interactive", |args| args.diagram.interactive),
+        ("expandable", |args| args.diagram.expandable),
+        ("source-map", |args| args.diagram.source_map),
+        ("combined", |args| args.diagram.combined),
+        ("modifier-colors", |args| args.diagram.modifier_colors),
+        ("legend", |args| args.diagram.legend),
+        ("rtl", |args| args.diagram.rtl),
+        ("line-refs", |args| args.diagram.line_refs),
+        ("stack-ops", |args| args.diagram.stack_ops),
+        ("implicit-notes", |args| args.diagram.implicit_notes),
+        ("factor-repeated", |args| args.diagram.factor_repeated),
+        ("merge-eoi", |args| args.diagram.merge_eoi),
+    ];
+
+    #[test]
+    fn boolean_settings_apply() {
+        for &(setting, value) in BOOLEAN_SETTINGS {
+            let args = configured("boolean_settings_apply", &[], "");
+            assert!(!value(&args), "{setting} is off by default");
+            let args = configured("boolean_settings_apply", &[], &format!("{setting} = true"));
+            assert!(value(&args), "{setting} is turned on by the config file");
+        }
+    }
+
+    #[test]
+    fn no_flags_override_config_file() {
+        for &(setting, value) in BOOLEAN_SETTINGS {
+            let toml = format!("{setting} = true");
+            let flag = format!("--no-{setting}");
+            let args = configured("no_flags_override_config_file", &[&flag], &toml);
+            assert!(!value(&args), "{flag} overrides the config file");
+        }
+    }
+
+    #[test]
+    fn last_of_flag_and_no_flag_wins() {
+        let args = configured(
+            "last_of_flag_and_no_flag_wins",
+            &["--strict", "--no-strict"],
+            CONFIG,
+        );
+        assert!(!args.strict);
+        let args = configured(
+            "last_of_flag_and_no_flag_wins",
+            &["--no-strict", "--strict"],
+            "",
+        );
+        assert!(args.strict);
+    }
+
+    #[test]
+    fn out_dir_writes_per_rule_by_default() {
+        let test = "out_dir_writes_per_rule_by_default";
+        assert!(configured(test, &["--out-dir", "out"], "").per_rule);
+        assert!(!configured(test, &["--out-dir", "out", "--combined"], "").per_rule);
+        assert!(!configured(test, &["--out-dir", "out", "--no-per-rule"], "").per_rule);
+        assert!(!configured(test, &["--out-dir", "out", "-f", "pdf"], "").per_rule);
+        assert!(!configured(test, &[], "output-dir = \"out\"\ncombined = true").per_rule);
+    }
+}
@@ -0,0 +1,194 @@
+use std::{
+    error::Error,
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    path::PathBuf,
+    sync::Mutex,
+    thread,
+    time::Duration,
+};
+
+use clap::Args;
+use pest_railroad::{Format, Grammar};
+
+use crate::{
+    diagnostics::Reporter,
+    html::escape,
+    input,
+    render::{ConfigArgs, DiagramArgs},
+    watch::Watcher,
+};
+
+/// How often (in milliseconds) the preview page checks whether it is out of date
+const RELOAD_INTERVAL_MS: u64 = 500;
+
+#[derive(Args)]
+pub struct ServeArgs {
+    /// The pest grammar file to preview
+    pub input: PathBuf,
+
+    /// The address to listen on
+    #[arg(long, default_value = "127.0.0.1")]
+    host: String,
+
+    /// The port to listen on
+    #[arg(short, long, default_value_t = 8080)]
+    port: u16,
+
+    #[command(flatten)]
+    pub diagram: DiagramArgs,
+
+    /// How long (in milliseconds) the grammar must be unchanged before regenerating the preview
+    #[arg(long, default_value_t = 200, value_name = "MS")]
+    debounce: u64,
+
+    #[command(flatten)]
+    pub config: ConfigArgs,
+}
+
+impl ServeArgs {
+    /// Fills in any settings not given on the command line from the project config file (if any)
+    pub fn apply_config(&mut self) -> Result<(), Box<dyn Error>> {
+        if let Some(config) = self.config.load(&self.input)? {
+            self.diagram.apply_config(&config);
+        }
+        Ok(())
+    }
+}
+
+/// The current preview page. The version is bumped on every regeneration so open pages know to reload.
+struct Page {
+    html: String,
+    version: u64,
+}
+
+pub fn serve(args: &ServeArgs, reporter: &Reporter) -> Result<(), Box<dyn Error>> {
+    if input::is_stdin(&args.input) {
+        return Err("The preview server requires a grammar file (not stdin)".into());
+    }
+    let listener = TcpListener::bind((args.host.as_str(), args.port))?;
+    let page = Mutex::new(Page {
+        html: render_page(args, reporter, 0),
+        version: 0,
+    });
+
+    eprintln!(
+        "Serving a preview of {} at http://{}/",
+        args.input.display(),
+        listener.local_addr()?
+    );
+
+    thread::scope(|scope| {
+        scope.spawn(|| {
+            let mut watcher =
+                Watcher::new([args.input.clone()], Duration::from_millis(args.debounce));
+            loop {
+                watcher.wait_for_change();
+
+                let version = page.lock().expect("page lock").version + 1;
+                let html = render_page(args, reporter, version);
+                *page.lock().expect("page lock") = Page { html, version };
+                eprintln!("Regenerated preview for {}", args.input.display());
+            }
+        });
+
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(err) => {
+                    reporter.error(Some(&args.input), &err);
+                    continue;
+                }
+            };
+
+            let page = &page;
+            scope.spawn(move || {
+                // A client going away mid-request is of no interest to the user
+                let _ = handle(stream, page);
+            });
+        }
+    });
+
+    Ok(())
+}
+
+/// Renders the grammar into the preview page. Errors are shown on the page itself (as well as reported) so the
+/// preview keeps working while the grammar is mid-edit.
+fn render_page(args: &ServeArgs, reporter: &Reporter, version: u64) -> String {
+    let mut html = match render_html(args, reporter) {
+        Ok(html) => html,
+        Err(err) => {
+            reporter.error(Some(&args.input), &*err);
+            format!(
+                "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Error</title>\n</head>\n<body>\n\
+                 <pre>Error: {}</pre>\n</body>\n</html>\n",
+                escape(&err.to_string())
+            )
+        }
+    };
+
+    let script = format!(
+        "<script>\nsetInterval(() => fetch(\"/version\")\n    .then((res) => res.text())\n    \
+         .then((version) => {{ if (version !== \"{version}\") location.reload(); }})\n    \
+         .catch(() => {{}}), {RELOAD_INTERVAL_MS});\n</script>\n"
+    );
+    match html.rfind("</body>") {
+        Some(idx) => html.insert_str(idx, &script),
+        None => html.push_str(&script),
+    }
+    html
+}
+
+fn render_html(args: &ServeArgs, reporter: &Reporter) -> Result<String, Box<dyn Error>> {
+    let src = input::read(&args.input)?;
+    let (grammar, warnings) = Grammar::parse(&src)?;
+
+    for warning in &warnings {
+        reporter.warning(&args.input, warning);
+    }
+
+    let config = args.diagram.config(&grammar, &args.input)?;
+    let mut html = Vec::new();
+    pest_railroad::render(&grammar, &config, Format::Html, &mut html)?;
+    Ok(String::from_utf8(html)?)
+}
+
+/// Answers a single HTTP request: `/` is the preview page and `/version` its current version
+fn handle(stream: TcpStream, page: &Mutex<Page>) -> io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    // The headers are of no interest, but they must be read before responding
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (status, content_type, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/")) => {
+            let page = page.lock().expect("page lock");
+            ("200 OK", "text/html; charset=utf-8", page.html.clone())
+        }
+        (Some("GET"), Some("/version")) => {
+            let page = page.lock().expect("page lock");
+            ("200 OK", "text/plain", page.version.to_string())
+        }
+        (Some("GET"), _) => ("404 Not Found", "text/plain", "Not found".to_string()),
+        _ => (
+            "405 Method Not Allowed",
+            "text/plain",
+            "Method not allowed".to_string(),
+        ),
+    };
+
+    let mut stream = reader.into_inner();
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\n\
+         Cache-Control: no-store\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
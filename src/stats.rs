@@ -0,0 +1,189 @@
+use std::{cmp::Reverse, error::Error, path::PathBuf};
+
+use clap::Args;
+use pest_railroad::{Config, DependencyGraph, Expr, Grammar, Modifier};
+use serde::Serialize;
+
+use crate::input;
+
+/// How many of the most referenced rules are shown
+const TOP_REFERENCED: usize = 5;
+
+#[derive(Args)]
+pub struct StatsArgs {
+    /// The pest grammar file to summarize (`-` reads it from stdin)
+    pub input: PathBuf,
+
+    /// Print the statistics as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Default, Serialize)]
+struct Stats<'a> {
+    rules: RuleCounts,
+    terminals: TerminalCounts,
+    /// The deepest expression nesting, along with the rule it occurs in
+    deepest_nesting: Option<Nesting<'a>>,
+    most_referenced: Vec<Referenced<'a>>,
+    /// The size (in pixels) of the rendered diagram
+    diagram_width: i64,
+    diagram_height: i64,
+}
+
+#[derive(Default, Serialize)]
+struct RuleCounts {
+    total: usize,
+    normal: usize,
+    silent: usize,
+    atomic: usize,
+    compound_atomic: usize,
+    non_atomic: usize,
+}
+
+#[derive(Default, Serialize)]
+struct TerminalCounts {
+    total: usize,
+    strings: usize,
+    insensitive_strings: usize,
+    ranges: usize,
+}
+
+#[derive(Serialize)]
+struct Nesting<'a> {
+    depth: usize,
+    rule: &'a str,
+}
+
+#[derive(Serialize)]
+struct Referenced<'a> {
+    rule: &'a str,
+    /// The number of rules referencing this rule
+    references: usize,
+}
+
+pub fn stats(args: &StatsArgs) -> Result<(), Box<dyn Error>> {
+    let src = input::read(&args.input)?;
+    let (grammar, _) = Grammar::parse(&src)?;
+    let stats = collect(&grammar);
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+    } else {
+        print_stats(&stats);
+    }
+    Ok(())
+}
+
+fn collect(grammar: &Grammar) -> Stats<'_> {
+    let mut stats = Stats::default();
+
+    for rule in &grammar.rules {
+        let counts = &mut stats.rules;
+        counts.total += 1;
+        *match rule.modifier {
+            Modifier::Normal => &mut counts.normal,
+            Modifier::Silent => &mut counts.silent,
+            Modifier::Atomic => &mut counts.atomic,
+            Modifier::CompoundAtomic => &mut counts.compound_atomic,
+            Modifier::NonAtomic => &mut counts.non_atomic,
+        } += 1;
+
+        rule.expr.walk(&mut |expr| {
+            let counts = &mut stats.terminals;
+            match expr {
+                Expr::Str { .. } => counts.strings += 1,
+                Expr::InsensitiveStr { .. } => counts.insensitive_strings += 1,
+                Expr::Range { .. } => counts.ranges += 1,
+                _ => return,
+            }
+            counts.total += 1;
+        });
+
+        let depth = depth(&rule.expr);
+        if stats
+            .deepest_nesting
+            .as_ref()
+            .is_none_or(|deepest| depth > deepest.depth)
+        {
+            stats.deepest_nesting = Some(Nesting {
+                depth,
+                rule: &rule.name,
+            });
+        }
+    }
+
+    let graph = DependencyGraph::new(grammar);
+    let mut referenced: Vec<_> = grammar
+        .rules
+        .iter()
+        .map(|rule| Referenced {
+            rule: &rule.name,
+            references: graph.referenced_by(&rule.name).count(),
+        })
+        .filter(|referenced| referenced.references > 0)
+        .collect();
+    // Stable, so ties stay in source order
+    referenced.sort_by_key(|referenced| Reverse(referenced.references));
+    referenced.truncate(TOP_REFERENCED);
+    stats.most_referenced = referenced;
+
+    (stats.diagram_width, stats.diagram_height) =
+        pest_railroad::diagram_size(grammar, &Config::default());
+    stats
+}
+
+/// The nesting depth of an expression (a lone term has a depth of 1)
+fn depth(expr: &Expr) -> usize {
+    1 + expr.children().iter().map(depth).max().unwrap_or(0)
+}
+
+fn print_stats(stats: &Stats<'_>) {
+    let rules = &stats.rules;
+    println!("Rules: {}", rules.total);
+    for (label, count) in [
+        ("normal", rules.normal),
+        ("silent", rules.silent),
+        ("atomic", rules.atomic),
+        ("compound atomic", rules.compound_atomic),
+        ("non-atomic", rules.non_atomic),
+    ] {
+        if count > 0 {
+            println!("  {label}: {count}");
+        }
+    }
+
+    let terminals = &stats.terminals;
+    println!("Terminals: {}", terminals.total);
+    for (label, count) in [
+        ("strings", terminals.strings),
+        ("insensitive strings", terminals.insensitive_strings),
+        ("ranges", terminals.ranges),
+    ] {
+        if count > 0 {
+            println!("  {label}: {count}");
+        }
+    }
+
+    if let Some(deepest) = &stats.deepest_nesting {
+        println!("Deepest nesting: {} (rule {})", deepest.depth, deepest.rule);
+    }
+
+    if !stats.most_referenced.is_empty() {
+        println!("Most referenced rules:");
+        let width = stats
+            .most_referenced
+            .iter()
+            .map(|referenced| referenced.rule.len())
+            .max()
+            .unwrap_or(0);
+        for referenced in &stats.most_referenced {
+            println!("  {:<width$}  {}", referenced.rule, referenced.references);
+        }
+    }
+
+    println!(
+        "Estimated diagram size: {} x {} px",
+        stats.diagram_width, stats.diagram_height
+    );
+}
@@ -0,0 +1,65 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+/// How often the watched files are polled for changes
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Polls a set of files for modifications, coalescing bursts of writes (as editors tend to do) into a single change
+pub struct Watcher {
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+    debounce: Duration,
+}
+
+impl Watcher {
+    pub fn new(paths: impl IntoIterator<Item = PathBuf>, debounce: Duration) -> Self {
+        let files = paths
+            .into_iter()
+            .map(|path| {
+                let modified = modified(&path);
+                (path, modified)
+            })
+            .collect();
+
+        Self { files, debounce }
+    }
+
+    /// Blocks until at least one watched file has changed and then stayed unchanged for the debounce period
+    pub fn wait_for_change(&mut self) {
+        // Wait for the first change
+        while !self.poll() {
+            thread::sleep(POLL_INTERVAL);
+        }
+
+        // Keep waiting while the files are still being written
+        loop {
+            thread::sleep(self.debounce);
+            if !self.poll() {
+                break;
+            }
+        }
+    }
+
+    /// Refreshes the stored modification times, returning true if any of them changed
+    fn poll(&mut self) -> bool {
+        let mut changed = false;
+
+        for (path, last_modified) in &mut self.files {
+            let modified = modified(path);
+            if modified != *last_modified {
+                *last_modified = modified;
+                changed = true;
+            }
+        }
+
+        changed
+    }
+}
+
+/// The modification time of a file (or `None` if it is missing, such as in the middle of an editor's atomic save)
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}